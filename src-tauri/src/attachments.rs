@@ -0,0 +1,320 @@
+//! File attachments for AI Anywhere
+//! Extracts the text of attached files (plain text, source code, CSV, JSON, PDF and DOCX)
+//! so it can be sent as labelled blocks with the user message
+
+use regex::Regex;
+use serde::Serialize;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use crate::tokens::count_tokens;
+
+/// Largest file read as an attachment
+const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Most files accepted in one request
+const MAX_ATTACHMENTS: usize = 10;
+
+/// Extensions read as UTF-8 text: documents, data files and source code
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt",
+    "md",
+    "markdown",
+    "rst",
+    "log",
+    "csv",
+    "tsv",
+    "json",
+    "jsonl",
+    "xml",
+    "yaml",
+    "yml",
+    "toml",
+    "ini",
+    "cfg",
+    "conf",
+    "env",
+    "html",
+    "htm",
+    "css",
+    "scss",
+    "rs",
+    "py",
+    "js",
+    "jsx",
+    "ts",
+    "tsx",
+    "mjs",
+    "cjs",
+    "java",
+    "kt",
+    "kts",
+    "scala",
+    "c",
+    "h",
+    "cpp",
+    "cc",
+    "hpp",
+    "cs",
+    "go",
+    "rb",
+    "php",
+    "swift",
+    "m",
+    "mm",
+    "dart",
+    "lua",
+    "r",
+    "pl",
+    "sh",
+    "bash",
+    "zsh",
+    "ps1",
+    "bat",
+    "sql",
+    "vue",
+    "svelte",
+    "gradle",
+    "cmake",
+    "dockerfile",
+    "makefile",
+    "tex",
+];
+
+/// Text of an attached file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// File name shown to the model
+    pub name: String,
+    pub text: String,
+}
+
+impl Attachment {
+    /// The file's text between labelled markers
+    pub fn block(&self) -> String {
+        format!(
+            "[File: {}]\n{}\n[End of file: {}]",
+            self.name,
+            self.text.trim_end(),
+            self.name
+        )
+    }
+}
+
+/// An attached file as shown in the UI, with the tokens its block adds to the prompt
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentInfo {
+    pub path: String,
+    pub name: String,
+    pub tokens: usize,
+}
+
+impl AttachmentInfo {
+    /// Extract a file's text and count its tokens for `model`
+    pub async fn inspect(path: String, model: String) -> Result<Self, String> {
+        blocking(move || {
+            let attachment = load_attachment(Path::new(&path))?;
+            Ok(Self {
+                tokens: count_tokens(&attachment.block(), &model),
+                name: attachment.name,
+                path,
+            })
+        })
+        .await
+    }
+}
+
+/// Extract the text of every attached file
+pub async fn load_attachments(paths: &[String]) -> Result<Vec<Attachment>, String> {
+    if paths.len() > MAX_ATTACHMENTS {
+        return Err(format!(
+            "Too many files ({}); at most {} can be attached",
+            paths.len(),
+            MAX_ATTACHMENTS
+        ));
+    }
+    let paths = paths.to_vec();
+    blocking(move || {
+        paths
+            .iter()
+            .map(|path| load_attachment(Path::new(path)))
+            .collect()
+    })
+    .await
+}
+
+/// Extract the text of one file without blocking the async runtime
+pub async fn read_attachment(path: PathBuf) -> Result<Attachment, String> {
+    blocking(move || load_attachment(&path)).await
+}
+
+/// Run file reading and text extraction on the blocking thread pool, as files of up
+/// to 20 MB would otherwise stall the async runtime
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("Failed to read the attached files: {}", e))?
+}
+
+/// The blocks of all attachments, to be appended to the user message
+pub fn attachment_blocks(attachments: &[Attachment]) -> String {
+    attachments
+        .iter()
+        .map(Attachment::block)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Extract the text of one file, chosen by its extension
+fn load_attachment(path: &Path) -> Result<Attachment, String> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("file")
+        .to_string();
+    // Files like `Makefile` or `Dockerfile` have no extension
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or(&name)
+        .to_lowercase();
+
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?
+        .len();
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "{} is too large ({} MB); files up to {} MB can be attached",
+            name,
+            size / (1024 * 1024),
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ));
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+
+    let text = match extension.as_str() {
+        "pdf" => pdf_text(&bytes, &name)?,
+        "docx" => docx_text(&bytes).map_err(|e| format!("Failed to read {}: {}", name, e))?,
+        ext if TEXT_EXTENSIONS.contains(&ext) => decode_text(&bytes)
+            .ok_or_else(|| format!("{} is not a text file", name))?,
+        _ => {
+            return Err(format!(
+                "Unsupported file type: {} (text, source code, CSV, JSON, PDF and DOCX files can be attached)",
+                name
+            ))
+        }
+    };
+
+    if text.trim().is_empty() {
+        return Err(format!("{} contains no text", name));
+    }
+    Ok(Attachment { name, text })
+}
+
+/// Extract the text of a PDF. pdf-extract panics on some malformed or unsupported
+/// files, which is reported as a failure instead of ending the command.
+fn pdf_text(bytes: &[u8], name: &str) -> Result<String, String> {
+    match std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(bytes)) {
+        Ok(Ok(text)) => Ok(text),
+        Ok(Err(e)) => Err(format!("Failed to extract the text of {}: {}", name, e)),
+        Err(_) => Err(format!("Failed to extract the text of {}", name)),
+    }
+}
+
+/// Decode UTF-8 text (a BOM is dropped); `None` for binary content
+fn decode_text(bytes: &[u8]) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Text of a Word document: the paragraphs of `word/document.xml`
+fn docx_text(bytes: &[u8]) -> Result<String, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|e| e.to_string())?
+        .read_to_string(&mut xml)
+        .map_err(|e| e.to_string())?;
+    Ok(document_xml_text(&xml))
+}
+
+/// Text runs of WordprocessingML, with paragraph ends, breaks and tabs kept
+fn document_xml_text(xml: &str) -> String {
+    let token =
+        Regex::new(r"<w:t(?:\s[^>]*)?>([^<]*)</w:t>|<w:tab\s*/>|<w:br\s*/>|</w:p>").unwrap();
+
+    let mut text = String::new();
+    for captures in token.captures_iter(xml) {
+        match captures.get(1) {
+            Some(run) => text.push_str(&unescape_xml(run.as_str())),
+            None if captures[0].starts_with("<w:tab") => text.push('\t'),
+            None => text.push('\n'),
+        }
+    }
+    text
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-anywhere-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_text_files_become_labelled_blocks() {
+        let path = temp_file("notes.md", b"\xEF\xBB\xBF# Notes\n\nShip it.\n");
+        let attachment = load_attachment(&path).unwrap();
+        assert_eq!(attachment.name, "notes.md");
+        assert_eq!(
+            attachment.block(),
+            "[File: notes.md]\n# Notes\n\nShip it.\n[End of file: notes.md]"
+        );
+
+        let path = temp_file("Makefile", b"all:\n\tcargo build\n");
+        assert!(load_attachment(&path).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_and_binary_files_are_rejected() {
+        let error = load_attachment(&temp_file("photo.png", b"\x89PNG")).unwrap_err();
+        assert!(error.contains("Unsupported file type"), "{}", error);
+
+        let error = load_attachment(&temp_file("data.csv", b"a,b\0c")).unwrap_err();
+        assert!(error.contains("not a text file"), "{}", error);
+
+        assert!(load_attachment(&temp_file("empty.txt", b"  \n")).is_err());
+        assert!(load_attachments(&vec!["a.txt".to_string(); 11])
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_document_xml_text() {
+        let xml = r#"<w:document><w:body>
+            <w:p><w:r><w:t>Dear</w:t></w:r><w:r><w:t xml:space="preserve"> Ana,</w:t></w:r></w:p>
+            <w:p><w:r><w:t>Q&amp;A</w:t><w:tab/><w:t>at 5</w:t><w:br/><w:t>Thanks</w:t></w:r></w:p>
+            </w:body></w:document>"#;
+        assert_eq!(document_xml_text(xml), "Dear Ana,\nQ&A\tat 5\nThanks\n");
+    }
+}
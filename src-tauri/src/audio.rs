@@ -0,0 +1,269 @@
+//! Audio splitting for AI Anywhere
+//! Decodes recordings too large to upload in one piece and cuts them into segments,
+//! at a pause where there is one, so they can be transcribed separately
+
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tokio_util::sync::CancellationToken;
+
+/// Largest file the transcription endpoints accept (Whisper: 25 MB)
+pub const MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Sample rate of the segments; speech models work at 16 kHz anyway
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Longest segment: 10 minutes of 16 kHz 16-bit mono WAV is about 19 MB
+pub const MAX_SEGMENT_SECONDS: u32 = 600;
+
+/// Window over which loudness is measured when looking for a pause
+const FRAME_SAMPLES: usize = SAMPLE_RATE as usize / 50;
+
+/// RMS loudness below which a frame counts as silence (about -40 dBFS)
+const SILENCE_RMS: f64 = 0.01 * i16::MAX as f64;
+
+/// Decode an audio file to 16 kHz mono samples, stopping early if `cancel` is cancelled
+pub fn decode_mono(path: &Path, cancel: &CancellationToken) -> Result<Vec<i16>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open audio file: {}", e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let unsupported = |e: DecodeError| {
+        format!(
+            "This audio format cannot be split for transcription ({}). Convert the file to MP3, WAV, FLAC or M4A.",
+            e
+        )
+    };
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(unsupported)?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("The file has no audio track")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(unsupported)?;
+
+    let mut resampler: Option<Resampler> = None;
+    let mut samples = Vec::new();
+    loop {
+        if cancel.is_cancelled() {
+            return Err("Cancelled".to_string());
+        }
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(format!("Failed to read audio file: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet is skipped, as players do
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        let resampler = resampler.get_or_insert_with(|| Resampler::new(spec.rate, SAMPLE_RATE));
+        for frame in buffer.samples().chunks(channels) {
+            let mono = frame.iter().sum::<f32>() / channels as f32;
+            resampler.push(mono, &mut samples);
+        }
+    }
+
+    if samples.is_empty() {
+        return Err("No audio found in the file".to_string());
+    }
+    Ok(samples)
+}
+
+/// Split samples into segments of at most `max_len` samples. Each cut is made at the
+/// quietest moment of the last tenth of the segment if it is silent, otherwise at
+/// `max_len`.
+pub fn split_segments(samples: &[i16], max_len: usize) -> Vec<Range<usize>> {
+    let search_len = max_len / 10;
+    let mut segments = Vec::new();
+    let mut start = 0;
+
+    while samples.len() - start > max_len {
+        let end = start + max_len;
+        let search_start = end - search_len;
+        let cut =
+            quietest_point(&samples[search_start..end]).map_or(end, |offset| search_start + offset);
+        segments.push(start..cut);
+        start = cut;
+    }
+    segments.push(start..samples.len());
+    segments
+}
+
+/// Middle of the quietest frame (the latest on ties), if that frame is silent
+fn quietest_point(samples: &[i16]) -> Option<usize> {
+    let (index, rms) = samples
+        .chunks(FRAME_SAMPLES)
+        .map(|frame| {
+            let power = frame.iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+            (power / frame.len() as f64).sqrt()
+        })
+        .enumerate()
+        .min_by(|(i, a), (j, b)| a.total_cmp(b).then(j.cmp(i)))?;
+
+    (rms < SILENCE_RMS).then(|| {
+        let frame_start = index * FRAME_SAMPLES;
+        frame_start + (samples.len() - frame_start).min(FRAME_SAMPLES) / 2
+    })
+}
+
+/// Encode 16 kHz mono samples as a 16-bit PCM WAV file
+pub fn encode_wav(samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// Converts a stream of samples to another rate. Each output sample is the average of
+/// the input samples it covers, which also filters out what the lower rate cannot hold.
+struct Resampler {
+    /// Input samples per output sample
+    step: f64,
+    /// Input samples read so far
+    read: f64,
+    /// Input position at which the next output sample is complete
+    next: f64,
+    sum: f32,
+    count: u32,
+    last: f32,
+}
+
+impl Resampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        let step = from_rate.max(1) as f64 / to_rate as f64;
+        Self {
+            step,
+            read: 0.0,
+            next: step,
+            sum: 0.0,
+            count: 0,
+            last: 0.0,
+        }
+    }
+
+    fn push(&mut self, sample: f32, output: &mut Vec<i16>) {
+        self.read += 1.0;
+        self.sum += sample;
+        self.count += 1;
+        // When upsampling, one input sample completes several output samples
+        while self.read >= self.next {
+            if self.count > 0 {
+                self.last = self.sum / self.count as f32;
+                self.sum = 0.0;
+                self.count = 0;
+            }
+            output.push((self.last.clamp(-1.0, 1.0) * i16::MAX as f32) as i16);
+            self.next += self.step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| ((i as f32 * 0.1).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    #[test]
+    fn test_segments_are_cut_at_pauses() {
+        let mut samples = tone(14_000);
+        samples.extend(vec![0; 1_000]);
+        samples.extend(tone(25_000));
+
+        // The pause falls in the last tenth of the first segment; the second has none
+        assert_eq!(
+            split_segments(&samples, 16_000),
+            vec![0..14_560, 14_560..30_560, 30_560..40_000]
+        );
+        assert_eq!(split_segments(&samples[..9_000], 16_000), vec![0..9_000]);
+    }
+
+    #[test]
+    fn test_wav_round_trip() {
+        let samples = tone(4_000);
+        let path =
+            std::env::temp_dir().join(format!("ai-anywhere-audio-{}.wav", std::process::id()));
+        std::fs::write(&path, encode_wav(&samples)).unwrap();
+
+        let decoded = decode_mono(&path, &CancellationToken::new());
+        std::fs::remove_file(&path).ok();
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.len(), samples.len());
+        assert!(decoded
+            .iter()
+            .zip(&samples)
+            .all(|(a, b)| (a - b).abs() <= 1));
+    }
+
+    #[test]
+    fn test_resampling() {
+        let mut output = Vec::new();
+        let mut resampler = Resampler::new(48_000, SAMPLE_RATE);
+        for _ in 0..4_800 {
+            resampler.push(0.5, &mut output);
+        }
+        assert_eq!(output.len(), 1_600);
+        assert!(output.iter().all(|&s| s == (0.5 * i16::MAX as f32) as i16));
+
+        output.clear();
+        let mut resampler = Resampler::new(8_000, SAMPLE_RATE);
+        for _ in 0..800 {
+            resampler.push(-0.25, &mut output);
+        }
+        assert_eq!(output.len(), 1_600);
+    }
+}
@@ -2,10 +2,17 @@
 //! Handles loading, saving, and managing application settings
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use uuid::Uuid;
 
 use crate::encryption;
+use crate::providers::Usage;
+use crate::tokens::normalize_model;
+
+/// Id of the profile created for fresh and migrated configurations
+pub const DEFAULT_PROFILE_ID: &str = "default";
 
 /// Paste behavior options
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -17,15 +24,108 @@ pub enum PasteBehavior {
     ReviewMode,
 }
 
-/// Application configuration
+/// API flavor spoken by the configured endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// OpenAI-compatible REST API (OpenAI, OpenRouter, LiteLLM, ...)
+    #[default]
+    OpenAi,
+    /// Native Anthropic Messages API
+    Anthropic,
+    /// Native Ollama API (`/api/chat`, `/api/tags`)
+    Ollama,
+    /// Google Gemini API (`generateContent`)
+    Gemini,
+}
+
+/// What a budget limit is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum BudgetUnit {
+    /// Prompt plus completion tokens
+    #[default]
+    Tokens,
+    /// Cost in the configured currency (models without a price count as free)
+    Currency,
+}
+
+/// Spending limits of an endpoint profile; unset limits are not enforced
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Budget {
+    #[serde(default)]
+    pub unit: BudgetUnit,
+
+    /// Limit for the current local day
+    #[serde(default)]
+    pub daily_limit: Option<f64>,
+
+    /// Limit for the current calendar month
+    #[serde(default)]
+    pub monthly_limit: Option<f64>,
+
+    /// Percentage of a limit at which a warning notification is shown
+    #[serde(default = "default_budget_warning_percent")]
+    pub warning_percent: u32,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            unit: BudgetUnit::default(),
+            daily_limit: None,
+            monthly_limit: None,
+            warning_percent: default_budget_warning_percent(),
+        }
+    }
+}
+
+impl Budget {
+    /// Check that limits are positive and the warning threshold is a percentage
+    pub fn validate(&self) -> Result<(), String> {
+        for limit in [self.daily_limit, self.monthly_limit].into_iter().flatten() {
+            if !limit.is_finite() || limit <= 0.0 {
+                return Err(format!(
+                    "Budget limits must be greater than 0 (got {})",
+                    limit
+                ));
+            }
+        }
+        if !(1..=100).contains(&self.warning_percent) {
+            return Err(format!(
+                "Budget warning threshold must be between 1 and 100% (got {}%)",
+                self.warning_percent
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Model slot an operation takes its model from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelSlot {
+    Llm,
+    Image,
+    Audio,
+    Tts,
+}
+
+/// A named API endpoint (URL, credentials and extra headers)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Configuration {
-    /// Global hotkey string (e.g., "Ctrl+Space")
-    #[serde(default = "default_hotkey")]
-    pub hotkey: String,
+pub struct EndpointProfile {
+    /// Stable identifier referenced by model slots and operation routes
+    pub id: String,
 
-    /// API base URL for OpenAI-compatible endpoints
+    /// Display name (e.g. "OpenRouter", "Local Whisper")
+    pub name: String,
+
+    /// API flavor of the endpoint
+    #[serde(default)]
+    pub provider: ProviderKind,
+
+    /// API base URL
     #[serde(default = "default_api_base_url")]
     pub api_base_url: String,
 
@@ -33,14 +133,203 @@ pub struct Configuration {
     #[serde(default)]
     pub api_key: String,
 
+    /// Extra HTTP headers sent with every request (e.g. OpenRouter's `HTTP-Referer`)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Daily and monthly spending limits
+    #[serde(default)]
+    pub budget: Budget,
+
     /// Plaintext API key (not persisted, used for testing with unsaved keys)
     #[serde(skip)]
     pub plaintext_api_key: Option<String>,
+}
+
+impl Default for EndpointProfile {
+    fn default() -> Self {
+        Self {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "Default".to_string(),
+            provider: ProviderKind::default(),
+            api_base_url: default_api_base_url(),
+            api_key: String::new(),
+            headers: HashMap::new(),
+            budget: Budget::default(),
+            plaintext_api_key: None,
+        }
+    }
+}
+
+impl EndpointProfile {
+    /// Get decrypted API key
+    pub fn get_api_key(&self) -> String {
+        // If plaintext API key is set (from form input), use it directly
+        if let Some(ref plaintext) = self.plaintext_api_key {
+            return plaintext.clone();
+        }
+        // Otherwise decrypt the stored encrypted key
+        if self.api_key.is_empty() {
+            return String::new();
+        }
+        encryption::decrypt(&self.api_key).unwrap_or_default()
+    }
+
+    /// Set API key (encrypts before storing)
+    pub fn set_api_key(&mut self, key: &str) {
+        if key.is_empty() {
+            self.api_key = String::new();
+        } else {
+            self.api_key = encryption::encrypt(key).unwrap_or_default();
+        }
+    }
+}
+
+/// A model on a specific endpoint profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelTarget {
+    /// Profile serving the model (empty = the profile serving the primary model)
+    #[serde(default)]
+    pub profile_id: String,
+    pub model: String,
+}
+
+/// Price of a model in the configured currency, per million tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    /// Cost of a completion with the given usage
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_per_million
+            + usage.completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Profile as submitted by the settings form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointProfileInput {
+    /// Empty for new profiles (an id is generated)
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub provider: ProviderKind,
+    pub api_base_url: String,
+    /// Plaintext key; `None` or empty keeps the stored key
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// `None` keeps the stored budget
+    #[serde(default)]
+    pub budget: Option<Budget>,
+}
+
+/// Endpoint fields of configurations written before profiles existed
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyEndpoint {
+    #[serde(default)]
+    provider: ProviderKind,
+    #[serde(default = "default_api_base_url")]
+    api_base_url: String,
+    #[serde(default)]
+    api_key: String,
+}
+
+/// Application configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Configuration {
+    /// Global hotkey string (e.g., "Ctrl+Space")
+    #[serde(default = "default_hotkey")]
+    pub hotkey: String,
+
+    /// Named API endpoints
+    #[serde(default)]
+    pub profiles: Vec<EndpointProfile>,
+
+    /// Profile used when no slot or operation route applies
+    #[serde(default)]
+    pub default_profile_id: String,
+
+    /// Profile serving the text model (empty = default profile)
+    #[serde(default)]
+    pub llm_profile_id: String,
+
+    /// Profile serving the image model (empty = default profile)
+    #[serde(default)]
+    pub image_profile_id: String,
+
+    /// Profile serving the audio transcription model (empty = default profile)
+    #[serde(default)]
+    pub audio_profile_id: String,
+
+    /// Profile serving the TTS model (empty = default profile)
+    #[serde(default)]
+    pub tts_profile_id: String,
+
+    /// Per-operation profile overrides, keyed by operation type or custom task id
+    #[serde(default)]
+    pub operation_profiles: HashMap<String, String>,
+
+    /// Ollama `keep_alive` duration (e.g. "5m", "-1"); empty uses the server default
+    #[serde(default)]
+    pub ollama_keep_alive: String,
+
+    /// Ollama context window (`num_ctx`); 0 uses the model default
+    #[serde(default)]
+    pub ollama_num_ctx: u32,
+
+    /// Retries after a rate-limited (429) or server error (5xx) response; 0 disables retrying
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay before the first retry in milliseconds (doubled for each further retry)
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+
+    /// Continuation turns sent when a response is cut off at the token limit; 0 disables them
+    #[serde(default = "default_max_continuations")]
+    pub max_continuations: u32,
+
+    /// Rounds of tool calls a model may make before answering
+    #[serde(default = "default_max_tool_rounds")]
+    pub max_tool_rounds: u32,
+
+    /// Folders the `read_file` tool may read from
+    #[serde(default)]
+    pub tool_folders: Vec<String>,
 
     /// Text/chat model name
     #[serde(default)]
     pub llm_model: String,
 
+    /// Models tried in order when the text model fails
+    #[serde(default)]
+    pub fallback_models: Vec<ModelTarget>,
+
+    /// Context window overrides in tokens, keyed by model name (for models the
+    /// built-in table does not know or gets wrong)
+    #[serde(default)]
+    pub context_windows: HashMap<String, u32>,
+
+    /// Token prices keyed by model name, used to cost the usage ledger
+    #[serde(default)]
+    pub model_prices: HashMap<String, ModelPrice>,
+
+    /// Currency of `model_prices` (a label such as "USD")
+    #[serde(default = "default_currency")]
+    pub currency: String,
+
     /// Image generation model name
     #[serde(default)]
     pub image_model: String,
@@ -92,6 +381,10 @@ pub struct Configuration {
     /// Number of days to keep media files (0 = never delete)
     #[serde(default = "default_media_retention_days")]
     pub media_retention_days: u32,
+
+    /// Keep the reasoning of thinking models in history entries
+    #[serde(default)]
+    pub save_reasoning_to_history: bool,
 }
 
 fn default_hotkey() -> String {
@@ -102,6 +395,30 @@ fn default_api_base_url() -> String {
     "https://api.openai.com/v1".to_string()
 }
 
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_continuations() -> u32 {
+    2
+}
+
+fn default_max_tool_rounds() -> u32 {
+    5
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_budget_warning_percent() -> u32 {
+    80
+}
+
 fn default_tts_model() -> String {
     "tts-1-hd".to_string()
 }
@@ -122,10 +439,25 @@ impl Default for Configuration {
     fn default() -> Self {
         Self {
             hotkey: default_hotkey(),
-            api_base_url: default_api_base_url(),
-            api_key: String::new(),
-            plaintext_api_key: None,
+            profiles: vec![EndpointProfile::default()],
+            default_profile_id: DEFAULT_PROFILE_ID.to_string(),
+            llm_profile_id: String::new(),
+            image_profile_id: String::new(),
+            audio_profile_id: String::new(),
+            tts_profile_id: String::new(),
+            operation_profiles: HashMap::new(),
+            ollama_keep_alive: String::new(),
+            ollama_num_ctx: 0,
+            max_retries: default_max_retries(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            max_continuations: default_max_continuations(),
+            max_tool_rounds: default_max_tool_rounds(),
+            tool_folders: Vec::new(),
             llm_model: String::new(),
+            fallback_models: Vec::new(),
+            context_windows: HashMap::new(),
+            model_prices: HashMap::new(),
+            currency: default_currency(),
             image_model: String::new(),
             audio_model: String::new(),
             tts_model: default_tts_model(),
@@ -139,6 +471,7 @@ impl Default for Configuration {
             tts_models: Vec::new(),
             history_limit: default_history_limit(),
             media_retention_days: default_media_retention_days(),
+            save_reasoning_to_history: false,
         }
     }
 }
@@ -180,9 +513,16 @@ impl Configuration {
         let content = fs::read_to_string(&config_path)
             .map_err(|e| format!("Failed to read config file: {}", e))?;
 
-        let config: Configuration = serde_json::from_str(&content)
+        let mut config: Configuration = serde_json::from_str(&content)
             .map_err(|e| format!("Failed to parse config file: {}", e))?;
 
+        // Configs from before endpoint profiles had a single endpoint; turn it into a profile
+        if config.profiles.is_empty() {
+            let legacy: LegacyEndpoint = serde_json::from_str(&content).unwrap_or_default();
+            config.migrate_legacy_endpoint(legacy);
+            config.save()?;
+        }
+
         Ok(config)
     }
 
@@ -199,25 +539,210 @@ impl Configuration {
         Ok(())
     }
 
-    /// Get decrypted API key
-    pub fn get_api_key(&self) -> String {
-        // If plaintext API key is set (from form input), use it directly
-        if let Some(ref plaintext) = self.plaintext_api_key {
-            return plaintext.clone();
+    /// Turn the single endpoint of an old configuration into the default profile
+    fn migrate_legacy_endpoint(&mut self, legacy: LegacyEndpoint) {
+        self.profiles.push(EndpointProfile {
+            provider: legacy.provider,
+            api_base_url: legacy.api_base_url,
+            // Already encrypted, copied as-is
+            api_key: legacy.api_key,
+            ..Default::default()
+        });
+        self.default_profile_id = DEFAULT_PROFILE_ID.to_string();
+    }
+
+    /// Find a profile by id
+    pub fn profile(&self, id: &str) -> Option<&EndpointProfile> {
+        self.profiles.iter().find(|p| p.id == id)
+    }
+
+    /// The default profile (falls back to the first profile if the id is stale)
+    pub fn default_profile(&self) -> Option<&EndpointProfile> {
+        self.profile(&self.default_profile_id)
+            .or_else(|| self.profiles.first())
+    }
+
+    /// Mutable access to the default profile
+    pub fn default_profile_mut(&mut self) -> Option<&mut EndpointProfile> {
+        let index = self
+            .profiles
+            .iter()
+            .position(|p| p.id == self.default_profile_id)
+            .unwrap_or(0);
+        self.profiles.get_mut(index)
+    }
+
+    /// Look up a profile by id, or the default profile when no id is given
+    pub fn profile_or_default(&self, id: Option<&str>) -> Result<&EndpointProfile, String> {
+        match id {
+            Some(id) if !id.is_empty() => self
+                .profile(id)
+                .ok_or_else(|| format!("Unknown endpoint profile: {}", id)),
+            _ => self.default_profile().ok_or_else(|| {
+                "No endpoint profile configured. Please add one in Settings.".to_string()
+            }),
         }
-        // Otherwise decrypt the stored encrypted key
-        if self.api_key.is_empty() {
-            return String::new();
+    }
+
+    /// Price of a model: an exact match on the model name, then a match ignoring
+    /// case and vendor prefixes ("openai/gpt-4o" is priced as "gpt-4o")
+    pub fn model_price(&self, model: &str) -> Option<&ModelPrice> {
+        if let Some(price) = self.model_prices.get(model.trim()) {
+            return Some(price);
         }
-        encryption::decrypt(&self.api_key).unwrap_or_default()
+        let model = normalize_model(model);
+        self.model_prices
+            .iter()
+            .find(|(name, _)| normalize_model(name) == model)
+            .map(|(_, price)| price)
     }
 
-    /// Set API key (encrypts before storing)
-    pub fn set_api_key(&mut self, key: &str) {
-        if key.is_empty() {
-            self.api_key = String::new();
-        } else {
-            self.api_key = encryption::encrypt(key).unwrap_or_default();
+    /// Profile id assigned to a model slot (may be empty)
+    fn slot_profile_id(&self, slot: ModelSlot) -> &str {
+        match slot {
+            ModelSlot::Llm => &self.llm_profile_id,
+            ModelSlot::Image => &self.image_profile_id,
+            ModelSlot::Audio => &self.audio_profile_id,
+            ModelSlot::Tts => &self.tts_profile_id,
+        }
+    }
+
+    /// Resolve the profile serving an operation: the operation route wins over the
+    /// model slot, which wins over the default profile. Stale ids fall through.
+    pub fn resolve_profile(
+        &self,
+        operation_type: &str,
+        slot: ModelSlot,
+    ) -> Option<&EndpointProfile> {
+        self.operation_profiles
+            .get(operation_type)
+            .and_then(|id| self.profile(id))
+            .or_else(|| self.profile(self.slot_profile_id(slot)))
+            .or_else(|| self.default_profile())
+    }
+
+    /// Replace the profile list with the submitted one. Profiles keep their stored
+    /// key unless a new one is given; routes to removed profiles are cleared.
+    pub fn update_profiles(&mut self, inputs: Vec<EndpointProfileInput>) {
+        let previous = std::mem::take(&mut self.profiles);
+
+        for input in inputs {
+            let id = if input.id.is_empty() {
+                Uuid::new_v4().to_string()
+            } else {
+                input.id
+            };
+
+            let stored = previous.iter().find(|p| p.id == id);
+            let mut profile = EndpointProfile {
+                api_key: stored.map(|p| p.api_key.clone()).unwrap_or_default(),
+                budget: input
+                    .budget
+                    .or_else(|| stored.map(|p| p.budget))
+                    .unwrap_or_default(),
+                id,
+                name: input.name,
+                provider: input.provider,
+                api_base_url: input.api_base_url,
+                headers: input.headers,
+                plaintext_api_key: None,
+            };
+            if let Some(key) = input.api_key {
+                if !key.is_empty() {
+                    profile.set_api_key(&key);
+                }
+            }
+            self.profiles.push(profile);
+        }
+
+        if self.profile(&self.default_profile_id).is_none() {
+            self.default_profile_id = self
+                .profiles
+                .first()
+                .map(|p| p.id.clone())
+                .unwrap_or_default();
+        }
+
+        let known: Vec<String> = self.profiles.iter().map(|p| p.id.clone()).collect();
+        for slot_id in [
+            &mut self.llm_profile_id,
+            &mut self.image_profile_id,
+            &mut self.audio_profile_id,
+            &mut self.tts_profile_id,
+        ] {
+            if !known.contains(slot_id) {
+                slot_id.clear();
+            }
+        }
+        self.operation_profiles.retain(|_, id| known.contains(id));
+        self.fallback_models
+            .retain(|target| target.profile_id.is_empty() || known.contains(&target.profile_id));
+    }
+
+    /// Check that at least one profile exists, ids are unique and every route points at a profile
+    pub fn validate_profiles(&self) -> Result<(), String> {
+        if self.profiles.is_empty() {
+            return Err("At least one endpoint profile is required".to_string());
+        }
+
+        for (i, profile) in self.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                return Err("Endpoint profile name cannot be empty".to_string());
+            }
+            if self.profiles[..i].iter().any(|p| p.id == profile.id) {
+                return Err(format!("Duplicate endpoint profile id: {}", profile.id));
+            }
+            profile
+                .budget
+                .validate()
+                .map_err(|e| format!("{} ({})", e, profile.name))?;
+        }
+
+        let routes = [
+            &self.default_profile_id,
+            &self.llm_profile_id,
+            &self.image_profile_id,
+            &self.audio_profile_id,
+            &self.tts_profile_id,
+        ];
+        let fallback_ids = self.fallback_models.iter().map(|target| &target.profile_id);
+        for id in routes
+            .into_iter()
+            .chain(self.operation_profiles.values())
+            .chain(fallback_ids)
+        {
+            if !id.is_empty() && self.profile(id).is_none() {
+                return Err(format!("Unknown endpoint profile: {}", id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Endpoint profile for frontend (API key masked)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointProfileDto {
+    pub id: String,
+    pub name: String,
+    pub provider: ProviderKind,
+    pub api_base_url: String,
+    pub api_key_set: bool,
+    pub headers: HashMap<String, String>,
+    pub budget: Budget,
+}
+
+impl From<&EndpointProfile> for EndpointProfileDto {
+    fn from(profile: &EndpointProfile) -> Self {
+        Self {
+            id: profile.id.clone(),
+            name: profile.name.clone(),
+            provider: profile.provider,
+            api_base_url: profile.api_base_url.clone(),
+            api_key_set: !profile.api_key.is_empty(),
+            headers: profile.headers.clone(),
+            budget: profile.budget,
         }
     }
 }
@@ -227,9 +752,29 @@ impl Configuration {
 #[serde(rename_all = "camelCase")]
 pub struct ConfigurationDto {
     pub hotkey: String,
+    /// Provider, URL and key state of the default profile
+    pub provider: ProviderKind,
     pub api_base_url: String,
     pub api_key_set: bool,
+    pub profiles: Vec<EndpointProfileDto>,
+    pub default_profile_id: String,
+    pub llm_profile_id: String,
+    pub image_profile_id: String,
+    pub audio_profile_id: String,
+    pub tts_profile_id: String,
+    pub operation_profiles: HashMap<String, String>,
+    pub ollama_keep_alive: String,
+    pub ollama_num_ctx: u32,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    pub max_continuations: u32,
+    pub max_tool_rounds: u32,
+    pub tool_folders: Vec<String>,
     pub llm_model: String,
+    pub fallback_models: Vec<ModelTarget>,
+    pub context_windows: HashMap<String, u32>,
+    pub model_prices: HashMap<String, ModelPrice>,
+    pub currency: String,
     pub image_model: String,
     pub audio_model: String,
     pub tts_model: String,
@@ -243,15 +788,40 @@ pub struct ConfigurationDto {
     pub tts_models: Vec<String>,
     pub history_limit: usize,
     pub media_retention_days: u32,
+    pub save_reasoning_to_history: bool,
 }
 
 impl From<&Configuration> for ConfigurationDto {
     fn from(config: &Configuration) -> Self {
+        let default_profile = config.default_profile().cloned().unwrap_or_default();
         Self {
             hotkey: config.hotkey.clone(),
-            api_base_url: config.api_base_url.clone(),
-            api_key_set: !config.api_key.is_empty(),
+            provider: default_profile.provider,
+            api_base_url: default_profile.api_base_url.clone(),
+            api_key_set: !default_profile.api_key.is_empty(),
+            profiles: config
+                .profiles
+                .iter()
+                .map(EndpointProfileDto::from)
+                .collect(),
+            default_profile_id: config.default_profile_id.clone(),
+            llm_profile_id: config.llm_profile_id.clone(),
+            image_profile_id: config.image_profile_id.clone(),
+            audio_profile_id: config.audio_profile_id.clone(),
+            tts_profile_id: config.tts_profile_id.clone(),
+            operation_profiles: config.operation_profiles.clone(),
+            ollama_keep_alive: config.ollama_keep_alive.clone(),
+            ollama_num_ctx: config.ollama_num_ctx,
+            max_retries: config.max_retries,
+            retry_base_delay_ms: config.retry_base_delay_ms,
+            max_continuations: config.max_continuations,
+            max_tool_rounds: config.max_tool_rounds,
+            tool_folders: config.tool_folders.clone(),
             llm_model: config.llm_model.clone(),
+            fallback_models: config.fallback_models.clone(),
+            context_windows: config.context_windows.clone(),
+            model_prices: config.model_prices.clone(),
+            currency: config.currency.clone(),
             image_model: config.image_model.clone(),
             audio_model: config.audio_model.clone(),
             tts_model: config.tts_model.clone(),
@@ -265,6 +835,120 @@ impl From<&Configuration> for ConfigurationDto {
             tts_models: config.tts_models.clone(),
             history_limit: config.history_limit,
             media_retention_days: config.media_retention_days,
+            save_reasoning_to_history: config.save_reasoning_to_history,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(id: &str) -> EndpointProfile {
+        EndpointProfile {
+            id: id.to_string(),
+            name: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_legacy_endpoint_migration() {
+        let json = r#"{"provider":"ollama","apiBaseUrl":"http://localhost:11434","apiKey":"enc","llmModel":"llama3"}"#;
+        let mut config: Configuration = serde_json::from_str(json).unwrap();
+        assert!(config.profiles.is_empty());
+
+        config.migrate_legacy_endpoint(serde_json::from_str(json).unwrap());
+
+        let profile = config.default_profile().unwrap();
+        assert_eq!(profile.id, DEFAULT_PROFILE_ID);
+        assert_eq!(profile.provider, ProviderKind::Ollama);
+        assert_eq!(profile.api_base_url, "http://localhost:11434");
+        assert_eq!(profile.api_key, "enc");
+        assert_eq!(config.llm_model, "llama3");
+    }
+
+    #[test]
+    fn test_resolve_profile_precedence() {
+        let mut config = Configuration {
+            profiles: vec![profile("main"), profile("whisper"), profile("local")],
+            default_profile_id: "main".to_string(),
+            audio_profile_id: "whisper".to_string(),
+            ..Default::default()
+        };
+        config
+            .operation_profiles
+            .insert("textSummarization".to_string(), "local".to_string());
+
+        let resolve = |op: &str, slot| config.resolve_profile(op, slot).unwrap().id.clone();
+        assert_eq!(resolve("speechToText", ModelSlot::Audio), "whisper");
+        assert_eq!(resolve("textSummarization", ModelSlot::Llm), "local");
+        assert_eq!(resolve("grammarCorrection", ModelSlot::Llm), "main");
+    }
+
+    #[test]
+    fn test_update_profiles_keeps_keys_and_prunes_routes() {
+        let mut config = Configuration {
+            profiles: vec![
+                EndpointProfile {
+                    api_key: "stored".to_string(),
+                    budget: Budget {
+                        daily_limit: Some(100_000.0),
+                        ..Default::default()
+                    },
+                    ..profile("main")
+                },
+                profile("old"),
+            ],
+            default_profile_id: "main".to_string(),
+            image_profile_id: "old".to_string(),
+            ..Default::default()
+        };
+
+        config.update_profiles(vec![EndpointProfileInput {
+            id: "main".to_string(),
+            name: "Main".to_string(),
+            provider: ProviderKind::OpenAi,
+            api_base_url: "https://example.com/v1".to_string(),
+            api_key: None,
+            headers: HashMap::new(),
+            budget: None,
+        }]);
+
+        assert_eq!(config.profiles.len(), 1);
+        assert_eq!(config.profiles[0].api_key, "stored");
+        assert_eq!(config.profiles[0].budget.daily_limit, Some(100_000.0));
+        assert!(config.image_profile_id.is_empty());
+        assert!(config.validate_profiles().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_route() {
+        let config = Configuration {
+            tts_profile_id: "missing".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate_profiles().is_err());
+
+        let mut config = Configuration::default();
+        config.profiles[0].budget.monthly_limit = Some(0.0);
+        assert!(config.validate_profiles().is_err());
+    }
+
+    #[test]
+    fn test_model_price_lookup_and_cost() {
+        let mut config = Configuration::default();
+        config.model_prices.insert(
+            "GPT-4o".to_string(),
+            ModelPrice {
+                input_per_million: 2.5,
+                output_per_million: 10.0,
+            },
+        );
+
+        let price = config.model_price("openai/gpt-4o").unwrap();
+        let cost = price.cost(&Usage::new(1_000_000, 500_000));
+        assert!((cost - 7.5).abs() < 1e-9);
+        assert!(config.model_price("gpt-4o-mini").is_none());
+    }
+}
@@ -7,6 +7,11 @@ use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
 
+use crate::providers::GenerationParams;
+use crate::structured::{compile_schema, template_fields};
+use crate::tokens::Encoding;
+use crate::tools::Tool;
+
 /// Option types for custom task form controls
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -31,6 +36,39 @@ pub struct CustomTaskOption {
     pub required: bool,
 }
 
+/// JSON output of a custom task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOutput {
+    /// JSON Schema the answer must match
+    pub schema: serde_json::Value,
+    /// Template rendering the validated JSON for pasting, with `{field}` placeholders
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl JsonOutput {
+    /// Check the schema compiles and the template only uses fields it defines
+    pub fn validate(&self) -> Result<(), String> {
+        compile_schema(&self.schema)?;
+
+        let template = self.template.as_deref().unwrap_or_default();
+        if let Some(properties) = self.schema["properties"].as_object() {
+            let unknown: Vec<String> = template_fields(template)
+                .into_iter()
+                .filter(|field| !properties.contains_key(field))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(format!(
+                    "Output template uses fields not in the schema: {}",
+                    unknown.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A user-created custom task
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -40,52 +78,65 @@ pub struct CustomTask {
     pub description: String,
     pub system_prompt: String,
     pub options: Vec<CustomTaskOption>,
+    /// Generation parameters; unset fields are not sent to the provider
+    #[serde(default)]
+    pub params: GenerationParams,
+    /// Structured output: the answer is JSON validated against a schema
+    #[serde(default)]
+    pub json_output: Option<JsonOutput>,
+    /// Local tools the model may call while answering
+    #[serde(default)]
+    pub tools: Vec<Tool>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Task as submitted by the custom task form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomTaskInput {
+    pub name: String,
+    pub description: String,
+    pub system_prompt: String,
+    pub options: Vec<CustomTaskOption>,
+    #[serde(default)]
+    pub params: GenerationParams,
+    #[serde(default)]
+    pub json_output: Option<JsonOutput>,
+    #[serde(default)]
+    pub tools: Vec<Tool>,
+}
+
 impl CustomTask {
-    pub fn new(
-        name: String,
-        description: String,
-        system_prompt: String,
-        options: Vec<CustomTaskOption>,
-    ) -> Self {
+    pub fn new(input: CustomTaskInput) -> Self {
         let now = chrono::Utc::now().to_rfc3339();
         Self {
             id: Uuid::new_v4().to_string(),
-            name,
-            description,
-            system_prompt,
-            options,
+            name: input.name,
+            description: input.description,
+            system_prompt: input.system_prompt,
+            options: input.options,
+            params: input.params,
+            json_output: input.json_output,
+            tools: input.tools,
             created_at: now.clone(),
             updated_at: now,
         }
     }
 
-    // Token estimation constants (same as frontend)
-    const MAX_ESTIMATED_TOKENS: usize = 16000;
-    const TOKEN_CORRECTION_FACTOR: f64 = 1.20;
-
-    /// Estimates the number of tokens in a text string.
-    /// Uses word count * 1.33 * 1.20 as approximation.
-    fn estimate_tokens(text: &str) -> usize {
-        if text.trim().is_empty() {
-            return 0;
-        }
-        let words: usize = text.split_whitespace().count();
-        (words as f64 * 1.33 * Self::TOKEN_CORRECTION_FACTOR).ceil() as usize
-    }
+    /// Maximum tokens allowed in a system prompt
+    const MAX_SYSTEM_PROMPT_TOKENS: usize = 16000;
 
     /// Validate that the system prompt contains all required placeholders for defined options
     pub fn validate(&self) -> Result<(), String> {
         // Check token limit
-        let token_count = Self::estimate_tokens(&self.system_prompt);
-        if token_count > Self::MAX_ESTIMATED_TOKENS {
+        // Tasks are not tied to a model, so count with the generic vocabulary
+        let token_count = Encoding::Cl100kBase.count(&self.system_prompt);
+        if token_count > Self::MAX_SYSTEM_PROMPT_TOKENS {
             return Err(format!(
-                "System prompt too long (~{} tokens). Maximum: {}",
+                "System prompt too long ({} tokens). Maximum: {}",
                 token_count,
-                Self::MAX_ESTIMATED_TOKENS
+                Self::MAX_SYSTEM_PROMPT_TOKENS
             ));
         }
 
@@ -138,6 +189,12 @@ impl CustomTask {
             return Err("System prompt cannot be empty".to_string());
         }
 
+        self.params.validate()?;
+
+        if let Some(ref json_output) = self.json_output {
+            json_output.validate()?;
+        }
+
         Ok(())
     }
 
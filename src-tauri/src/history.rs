@@ -8,6 +8,9 @@ use std::path::PathBuf;
 use tokio::fs as async_fs;
 use uuid::Uuid;
 
+use crate::providers::Usage;
+use crate::threads::ThreadManager;
+
 /// A single history entry - matches TypeScript HistoryEntry/HistoryEntryResponse
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,9 +21,35 @@ pub struct HistoryEntry {
     pub response_text: Option<String>,
     pub operation_options: HashMap<String, String>,
     pub media_path: Option<String>,
+    /// Model that produced the response (absent in older entries)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Tokens used to produce the response (absent in older entries)
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// Reasoning of a thinking model, if saving it is enabled
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    /// Conversation thread the exchange belongs to, if it can be continued
+    #[serde(default)]
+    pub thread_id: Option<String>,
     pub created_at: String,
 }
 
+/// Details of the response an entry was created from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseDetails {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    #[serde(default)]
+    pub thread_id: Option<String>,
+}
+
 impl HistoryEntry {
     pub fn new(
         operation_type: String,
@@ -28,6 +57,7 @@ impl HistoryEntry {
         response_text: Option<String>,
         operation_options: HashMap<String, String>,
         media_path: Option<String>,
+        details: ResponseDetails,
     ) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
@@ -36,6 +66,10 @@ impl HistoryEntry {
             response_text,
             operation_options,
             media_path,
+            model: details.model,
+            usage: details.usage,
+            reasoning: details.reasoning,
+            thread_id: details.thread_id,
             created_at: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -149,9 +183,11 @@ impl HistoryManager {
             let entries_to_remove: Vec<HistoryEntry> = history.drain(limit..).collect();
 
             // Delete associated media files
-            for entry in entries_to_remove {
-                Self::delete_entry_media(&entry);
+            for entry in &entries_to_remove {
+                Self::delete_entry_media(entry);
             }
+            Self::save(&history).await?;
+            return Self::delete_orphan_threads(&entries_to_remove, &history).await;
         }
 
         Self::save(&history).await
@@ -165,11 +201,26 @@ impl HistoryManager {
         if let Some(pos) = history.iter().position(|e| e.id == id) {
             let entry = history.remove(pos);
             Self::delete_entry_media(&entry);
+            Self::save(&history).await?;
+            return Self::delete_orphan_threads(&[entry], &history).await;
         }
 
         Self::save(&history).await
     }
 
+    /// Delete the threads of removed entries that no remaining entry belongs to
+    async fn delete_orphan_threads(
+        removed: &[HistoryEntry],
+        remaining: &[HistoryEntry],
+    ) -> Result<(), String> {
+        let orphans: Vec<String> = removed
+            .iter()
+            .filter_map(|e| e.thread_id.clone())
+            .filter(|id| !remaining.iter().any(|e| e.thread_id.as_ref() == Some(id)))
+            .collect();
+        ThreadManager::delete(&orphans).await
+    }
+
     /// Delete media files associated with an entry
     fn delete_entry_media(entry: &HistoryEntry) {
         if let Some(ref path) = entry.media_path {
@@ -187,7 +238,8 @@ impl HistoryManager {
         }
 
         // Save empty history
-        Self::save(&Vec::new()).await
+        Self::save(&Vec::new()).await?;
+        ThreadManager::clear().await
     }
 
     /// Search history entries - async version
@@ -207,7 +259,6 @@ impl HistoryManager {
     }
 
     /// Save image to media folder and return the path
-    #[allow(dead_code)]
     pub fn save_image(data: &[u8], format: &str) -> Result<String, String> {
         let media_path = Self::get_media_path();
         println!("[HistoryManager] Media path for image: {:?}", media_path);
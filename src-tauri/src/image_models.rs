@@ -0,0 +1,238 @@
+//! Image model capabilities for AI Anywhere
+//! The sizes, options and request parameters each image model family accepts, so the
+//! Image Generation operation offers only valid choices and requests leave out the rest
+
+use crate::tokens::normalize_model;
+
+/// Sizes offered for models without a known list. FLUX, Stable Diffusion and similar
+/// gateways take most sizes.
+const ANY_SIZES: &[&str] = &[
+    "512x512",
+    "768x768",
+    "1024x1024",
+    "512x768",
+    "768x1152",
+    "832x1248",
+    "896x1344",
+    "768x512",
+    "1152x768",
+    "1248x832",
+    "1344x896",
+    "768x1024",
+    "936x1248",
+    "1024x768",
+    "1248x936",
+];
+
+/// What an image model accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageCapabilities {
+    /// Sizes as "WIDTHxHEIGHT"
+    pub sizes: &'static [&'static str],
+    pub default_size: &'static str,
+    /// Values of the `quality` parameter, the first being the default (empty: not accepted)
+    pub qualities: &'static [&'static str],
+    /// Values of the `style` parameter, the first being the default (empty: not accepted)
+    pub styles: &'static [&'static str],
+    /// Most images per request (at most 4, which is as many as the review shows well)
+    pub max_images: u32,
+    /// Accepts `/images/edits`
+    pub edits: bool,
+    /// Accepts `/images/variations`
+    pub variations: bool,
+    /// Accepts `response_format` (GPT Image always answers in base64 and rejects it)
+    pub response_format: bool,
+}
+
+/// Models without an entry get every choice, as before the table existed
+const UNKNOWN_MODEL: ImageCapabilities = ImageCapabilities {
+    sizes: ANY_SIZES,
+    default_size: "512x768",
+    qualities: &["hd", "standard"],
+    styles: &["vivid", "natural"],
+    max_images: 4,
+    edits: true,
+    variations: true,
+    response_format: true,
+};
+
+/// Open-weight models behind OpenAI-compatible gateways: any size, no quality or style
+const OPEN_MODEL: ImageCapabilities = ImageCapabilities {
+    sizes: ANY_SIZES,
+    default_size: "1024x1024",
+    qualities: &[],
+    styles: &[],
+    max_images: 4,
+    edits: false,
+    variations: false,
+    response_format: true,
+};
+
+/// Capabilities by model id prefix (of the name without vendor prefix); the first match wins
+const IMAGE_MODELS: &[(&str, ImageCapabilities)] = &[
+    (
+        "dall-e-3",
+        ImageCapabilities {
+            sizes: &["1024x1024", "1792x1024", "1024x1792"],
+            default_size: "1024x1024",
+            qualities: &["hd", "standard"],
+            styles: &["vivid", "natural"],
+            max_images: 1,
+            edits: false,
+            variations: false,
+            response_format: true,
+        },
+    ),
+    (
+        "dall-e-2",
+        ImageCapabilities {
+            sizes: &["256x256", "512x512", "1024x1024"],
+            default_size: "1024x1024",
+            qualities: &[],
+            styles: &[],
+            max_images: 4,
+            edits: true,
+            variations: true,
+            response_format: true,
+        },
+    ),
+    (
+        "gpt-image",
+        ImageCapabilities {
+            sizes: &["1024x1024", "1536x1024", "1024x1536"],
+            default_size: "1024x1024",
+            qualities: &["auto", "high", "medium", "low"],
+            styles: &[],
+            max_images: 4,
+            edits: true,
+            variations: false,
+            response_format: false,
+        },
+    ),
+    ("flux", OPEN_MODEL),
+    ("stable-diffusion", OPEN_MODEL),
+    ("sdxl", OPEN_MODEL),
+    ("sd3", OPEN_MODEL),
+];
+
+impl ImageCapabilities {
+    /// Capabilities of an image model from the table
+    pub fn for_model(model: &str) -> &'static ImageCapabilities {
+        let name = normalize_model(model);
+        IMAGE_MODELS
+            .iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+            .map_or(&UNKNOWN_MODEL, |(_, capabilities)| capabilities)
+    }
+
+    /// The size if the model accepts it, its default size otherwise
+    pub fn size(&self, size: &str) -> String {
+        let size = if self.sizes.contains(&size) {
+            size
+        } else {
+            self.default_size
+        };
+        size.to_string()
+    }
+
+    /// The quality to send: the one asked for if accepted, else the default, or none
+    /// if the model takes no `quality`
+    pub fn quality(&self, quality: Option<&str>) -> Option<String> {
+        pick(self.qualities, quality)
+    }
+
+    /// The style to send, like `quality`
+    pub fn style(&self, style: Option<&str>) -> Option<String> {
+        pick(self.styles, style)
+    }
+
+    /// Modes of the Image Generation operation the model supports
+    pub fn modes(&self) -> Vec<&'static str> {
+        let mut modes = vec!["generate"];
+        if self.edits {
+            modes.push("edit");
+        }
+        if self.variations {
+            modes.push("variation");
+        }
+        modes
+    }
+}
+
+fn pick(values: &[&str], value: Option<&str>) -> Option<String> {
+    let default = values.first()?;
+    let value = value
+        .filter(|value| values.contains(value))
+        .unwrap_or(default);
+    Some(value.to_string())
+}
+
+/// Size with its aspect ratio, as shown in the options ("1792x1024 (7:4 Landscape)")
+pub fn size_label(size: &str) -> String {
+    let Some((width, height)) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+    else {
+        return size.to_string();
+    };
+    let divisor = gcd(width, height).max(1);
+    let shape = match width.cmp(&height) {
+        std::cmp::Ordering::Equal => "Square",
+        std::cmp::Ordering::Greater => "Landscape",
+        std::cmp::Ordering::Less => "Portrait",
+    };
+    format!(
+        "{} ({}:{} {})",
+        size,
+        width / divisor,
+        height / divisor,
+        shape
+    )
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_families() {
+        let dalle3 = ImageCapabilities::for_model("openai/dall-e-3");
+        assert_eq!(dalle3.size("512x768"), "1024x1024");
+        assert_eq!(dalle3.size("1792x1024"), "1792x1024");
+        assert_eq!(
+            dalle3.quality(Some("standard")),
+            Some("standard".to_string())
+        );
+        assert_eq!(dalle3.modes(), vec!["generate"]);
+
+        let flux = ImageCapabilities::for_model("black-forest-labs/FLUX.1-schnell");
+        assert_eq!(flux.style(Some("vivid")), None);
+        assert_eq!(flux.quality(Some("hd")), None);
+
+        let gpt_image = ImageCapabilities::for_model("gpt-image-1");
+        assert_eq!(gpt_image.quality(Some("hd")), Some("auto".to_string()));
+        assert!(!gpt_image.response_format);
+        assert_eq!(gpt_image.modes(), vec!["generate", "edit"]);
+
+        // Unknown models keep every choice
+        let unknown = ImageCapabilities::for_model("my-image-model");
+        assert_eq!(unknown.size("832x1248"), "832x1248");
+        assert_eq!(unknown.modes(), vec!["generate", "edit", "variation"]);
+    }
+
+    #[test]
+    fn test_size_label() {
+        assert_eq!(size_label("1792x1024"), "1792x1024 (7:4 Landscape)");
+        assert_eq!(size_label("832x1248"), "832x1248 (2:3 Portrait)");
+        assert_eq!(size_label("256x256"), "256x256 (1:1 Square)");
+        assert_eq!(size_label("auto"), "auto");
+    }
+}
@@ -1,36 +1,56 @@
 //! AI Anywhere - Universal AI Assistant
 //! Tauri 2.0 backend library
 
+mod attachments;
+mod audio;
 mod clipboard;
 mod config;
 mod custom_tasks;
 mod encryption;
 mod history;
+mod image_models;
 mod llm;
 mod operations;
+mod providers;
+mod requests;
+mod structured;
 mod text;
+mod threads;
+mod tokens;
+mod tools;
+mod usage;
+mod vision;
 #[cfg(target_os = "linux")]
 mod wayland;
 
-use config::{Configuration, ConfigurationDto, PasteBehavior};
-use custom_tasks::{CustomTask, CustomTaskOption, CustomTasksManager};
-use history::{HistoryEntry, HistoryManager};
+use attachments::AttachmentInfo;
+use config::{
+    Configuration, ConfigurationDto, EndpointProfile, EndpointProfileInput, ModelPrice,
+    ModelTarget, PasteBehavior, ProviderKind,
+};
+use custom_tasks::{CustomTask, CustomTaskInput, CustomTasksManager};
+use history::{HistoryEntry, HistoryManager, ResponseDetails};
 use llm::{LlmRequest, LlmResponse, LlmService};
 use operations::Operation;
+use requests::RequestRegistry;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::atomic::AtomicBool;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     AppHandle, Emitter, Manager, State,
 };
+use threads::{Thread, ThreadManager};
+use usage::{UsageManager, UsageSummary};
 
 /// Application state
 pub struct AppState {
     config: Mutex<Configuration>,
-    cancel_flag: Arc<AtomicBool>,
+    /// In-flight LLM requests and their cancellation tokens
+    requests: RequestRegistry,
+    /// Budget limits already warned about ("<profile id>:<day or month>")
+    budget_warnings: Mutex<HashSet<String>>,
 }
 
 impl AppState {
@@ -38,7 +58,8 @@ impl AppState {
         let config = Configuration::load().unwrap_or_default();
         Self {
             config: Mutex::new(config),
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            requests: RequestRegistry::new(),
+            budget_warnings: Mutex::new(HashSet::new()),
         }
     }
 }
@@ -47,50 +68,24 @@ impl AppState {
 // Helper Functions
 // ============================================================================
 
-/// Download an image from URL and copy it to the system clipboard
+/// Copy an image file (e.g. a generated image in the media folder) to the system clipboard
 async fn copy_image_to_clipboard(
     app: &AppHandle,
-    image_url: &str,
+    image_path: &str,
     debug_logging: bool,
 ) -> Result<(), String> {
     use tauri_plugin_clipboard_manager::ClipboardExt;
 
     if debug_logging {
-        println!(
-            "[copy_image_to_clipboard] Starting download from: {}",
-            image_url
-        );
-    }
-
-    // Create HTTP client
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    // Download the image
-    let response = client
-        .get(image_url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download image: {}", e))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        return Err(format!("Download failed with status: {}", status));
+        println!("[copy_image_to_clipboard] Reading image: {}", image_path);
     }
 
-    let bytes = response
-        .bytes()
+    let bytes = tokio::fs::read(image_path)
         .await
-        .map_err(|e| format!("Failed to read image bytes: {}", e))?;
-
-    if debug_logging {
-        println!("[copy_image_to_clipboard] Downloaded {} bytes", bytes.len());
-    }
+        .map_err(|e| format!("Failed to read image: {}", e))?;
 
     if bytes.is_empty() {
-        return Err("Downloaded image is empty".to_string());
+        return Err("Image file is empty".to_string());
     }
 
     // Decode the image to get RGBA data
@@ -125,6 +120,69 @@ async fn copy_image_to_clipboard(
     Ok(())
 }
 
+/// Refuse a request whose endpoint profile has used up a budget limit. Limits past their
+/// warning threshold raise a notification, once per profile and period.
+async fn check_budget(
+    app: &AppHandle,
+    state: &AppState,
+    config: &Configuration,
+    operation_type: &str,
+) -> Result<(), String> {
+    use tauri_plugin_notification::NotificationExt;
+
+    let Some(profile) =
+        config.resolve_profile(operation_type, LlmService::model_slot(operation_type))
+    else {
+        return Ok(());
+    };
+
+    let uses = UsageManager::budget_use(profile).await?;
+    if let Some(exceeded) = uses.iter().find(|u| u.is_exceeded()) {
+        return Err(format!(
+            "The {} budget of endpoint profile '{}' is exhausted ({} used). New requests are refused until {} or until the limit is raised in Settings.",
+            exceeded.period.name(),
+            profile.name,
+            exceeded.amounts(&config.currency),
+            exceeded.period.reset()
+        ));
+    }
+
+    let warning_percent = profile.budget.warning_percent as f64;
+    for budget_use in uses.iter().filter(|u| u.percent() >= warning_percent) {
+        let key = format!("{}:{}", profile.id, budget_use.period_key);
+        let first_warning = state
+            .budget_warnings
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(key);
+        if !first_warning {
+            continue;
+        }
+
+        let message = format!(
+            "Endpoint profile '{}' has used {:.0}% of its {} budget ({}).",
+            profile.name,
+            budget_use.percent(),
+            budget_use.period.name(),
+            budget_use.amounts(&config.currency)
+        );
+        if config.enable_debug_logging {
+            println!("[check_budget] {}", message);
+        }
+        if let Err(e) = app
+            .notification()
+            .builder()
+            .title("AI Anywhere - Budget warning")
+            .body(&message)
+            .show()
+        {
+            eprintln!("[check_budget] Failed to show notification: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
 // ============================================================================
 // Configuration Commands
 // ============================================================================
@@ -139,9 +197,50 @@ async fn get_configuration(state: State<'_, AppState>) -> Result<ConfigurationDt
 #[serde(rename_all = "camelCase")]
 struct SaveConfigRequest {
     hotkey: String,
-    api_base_url: String,
+    // Single-endpoint fields (older frontends); applied to the default profile
+    // when `profiles` is not sent
+    #[serde(default)]
+    provider: Option<ProviderKind>,
+    #[serde(default)]
+    api_base_url: Option<String>,
     api_key: Option<String>,
+    #[serde(default)]
+    profiles: Option<Vec<EndpointProfileInput>>,
+    #[serde(default)]
+    default_profile_id: Option<String>,
+    #[serde(default)]
+    llm_profile_id: Option<String>,
+    #[serde(default)]
+    image_profile_id: Option<String>,
+    #[serde(default)]
+    audio_profile_id: Option<String>,
+    #[serde(default)]
+    tts_profile_id: Option<String>,
+    #[serde(default)]
+    operation_profiles: Option<HashMap<String, String>>,
+    #[serde(default)]
+    ollama_keep_alive: Option<String>,
+    #[serde(default)]
+    ollama_num_ctx: Option<u32>,
+    #[serde(default)]
+    max_retries: Option<u32>,
+    #[serde(default)]
+    retry_base_delay_ms: Option<u64>,
+    #[serde(default)]
+    max_continuations: Option<u32>,
+    #[serde(default)]
+    max_tool_rounds: Option<u32>,
+    #[serde(default)]
+    tool_folders: Option<Vec<String>>,
     llm_model: String,
+    #[serde(default)]
+    fallback_models: Option<Vec<ModelTarget>>,
+    #[serde(default)]
+    context_windows: Option<HashMap<String, u32>>,
+    #[serde(default)]
+    model_prices: Option<HashMap<String, ModelPrice>>,
+    #[serde(default)]
+    currency: Option<String>,
     image_model: String,
     audio_model: String,
     tts_model: String,
@@ -155,6 +254,8 @@ struct SaveConfigRequest {
     tts_models: Vec<String>,
     history_limit: usize,
     media_retention_days: u32,
+    #[serde(default)]
+    save_reasoning_to_history: Option<bool>,
 }
 
 #[tauri::command]
@@ -163,9 +264,9 @@ async fn save_configuration(
     request: SaveConfigRequest,
 ) -> Result<(), String> {
     let mut config = state.config.lock().map_err(|e| e.to_string())?;
+    let previous = config.clone();
 
     config.hotkey = request.hotkey;
-    config.api_base_url = request.api_base_url;
     config.llm_model = request.llm_model;
     config.image_model = request.image_model;
     config.audio_model = request.audio_model;
@@ -181,12 +282,104 @@ async fn save_configuration(
     config.history_limit = request.history_limit;
     config.media_retention_days = request.media_retention_days;
 
-    // Only update API key if provided
-    if let Some(key) = request.api_key {
-        if !key.is_empty() {
-            config.set_api_key(&key);
+    if let Some(keep_alive) = request.ollama_keep_alive {
+        config.ollama_keep_alive = keep_alive;
+    }
+    if let Some(num_ctx) = request.ollama_num_ctx {
+        config.ollama_num_ctx = num_ctx;
+    }
+    if let Some(fallback_models) = request.fallback_models {
+        config.fallback_models = fallback_models
+            .into_iter()
+            .filter(|target| !target.model.trim().is_empty())
+            .collect();
+    }
+    if let Some(context_windows) = request.context_windows {
+        config.context_windows = context_windows
+            .into_iter()
+            .filter(|(model, window)| !model.trim().is_empty() && *window > 0)
+            .map(|(model, window)| (model.trim().to_string(), window))
+            .collect();
+    }
+    if let Some(model_prices) = request.model_prices {
+        config.model_prices = model_prices
+            .into_iter()
+            .filter(|(model, price)| {
+                !model.trim().is_empty()
+                    && price.input_per_million >= 0.0
+                    && price.output_per_million >= 0.0
+            })
+            .map(|(model, price)| (model.trim().to_string(), price))
+            .collect();
+    }
+    if let Some(currency) = request.currency {
+        if !currency.trim().is_empty() {
+            config.currency = currency.trim().to_string();
         }
     }
+    if let Some(max_retries) = request.max_retries {
+        config.max_retries = max_retries;
+    }
+    if let Some(delay_ms) = request.retry_base_delay_ms {
+        config.retry_base_delay_ms = delay_ms;
+    }
+    if let Some(max_continuations) = request.max_continuations {
+        config.max_continuations = max_continuations;
+    }
+    if let Some(max_tool_rounds) = request.max_tool_rounds {
+        config.max_tool_rounds = max_tool_rounds;
+    }
+    if let Some(tool_folders) = request.tool_folders {
+        config.tool_folders = tool_folders;
+    }
+    if let Some(save_reasoning) = request.save_reasoning_to_history {
+        config.save_reasoning_to_history = save_reasoning;
+    }
+
+    if let Some(profiles) = request.profiles {
+        config.update_profiles(profiles);
+    } else if let Some(profile) = config.default_profile_mut() {
+        // Single-endpoint form: edit the default profile
+        if let Some(api_base_url) = request.api_base_url {
+            profile.api_base_url = api_base_url;
+        }
+        // Only update the provider if provided (older frontends don't send it)
+        if let Some(provider) = request.provider {
+            profile.provider = provider;
+        }
+        // Only update API key if provided
+        if let Some(key) = request.api_key {
+            if !key.is_empty() {
+                profile.set_api_key(&key);
+            }
+        }
+    }
+
+    // Only update routing if provided (older frontends don't send it)
+    if let Some(id) = request.default_profile_id {
+        config.default_profile_id = id;
+    }
+    if let Some(id) = request.llm_profile_id {
+        config.llm_profile_id = id;
+    }
+    if let Some(id) = request.image_profile_id {
+        config.image_profile_id = id;
+    }
+    if let Some(id) = request.audio_profile_id {
+        config.audio_profile_id = id;
+    }
+    if let Some(id) = request.tts_profile_id {
+        config.tts_profile_id = id;
+    }
+    if let Some(operation_profiles) = request.operation_profiles {
+        config.operation_profiles = operation_profiles;
+    }
+
+    // Reject dangling profile references without keeping the half-applied changes
+    if let Err(e) = config.validate_profiles() {
+        *config = previous;
+        return Err(e);
+    }
 
     config.save()
 }
@@ -210,8 +403,10 @@ async fn update_models(
 // ============================================================================
 
 #[tauri::command]
-async fn get_operations() -> Result<Vec<Operation>, String> {
-    Ok(operations::get_default_operations())
+async fn get_operations(state: State<'_, AppState>) -> Result<Vec<Operation>, String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?;
+    // Image options are limited to what the configured image model accepts
+    Ok(operations::get_operations(&config.image_model))
 }
 
 // ============================================================================
@@ -234,18 +429,25 @@ async fn save_history_entry(
     response_text: Option<String>,
     operation_options: HashMap<String, String>,
     media_path: Option<String>,
+    details: Option<ResponseDetails>,
 ) -> Result<HistoryEntry, String> {
-    let history_limit = {
+    let (history_limit, save_reasoning) = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
-        config.history_limit
+        (config.history_limit, config.save_reasoning_to_history)
     }; // Lock is released here
 
+    let mut details = details.unwrap_or_default();
+    if !save_reasoning {
+        details.reasoning = None;
+    }
+
     let entry = HistoryEntry::new(
         operation_type,
         prompt_text,
         response_text,
         operation_options,
         media_path,
+        details,
     );
     HistoryManager::add_entry(entry.clone(), history_limit).await?;
 
@@ -262,6 +464,12 @@ async fn clear_history() -> Result<(), String> {
     HistoryManager::clear().await
 }
 
+/// Conversation thread of a history entry, for continuing it
+#[tauri::command]
+async fn get_thread(id: String) -> Result<Option<Thread>, String> {
+    ThreadManager::get(&id).await
+}
+
 #[tauri::command]
 async fn cleanup_old_media(state: State<'_, AppState>) -> Result<u32, String> {
     let retention_days = {
@@ -271,6 +479,25 @@ async fn cleanup_old_media(state: State<'_, AppState>) -> Result<u32, String> {
     HistoryManager::cleanup_old_media(retention_days).await
 }
 
+// ============================================================================
+// Usage Commands
+// ============================================================================
+
+/// Daily and monthly token and cost totals from the usage ledger, by model and operation
+#[tauri::command]
+async fn get_usage_summary(
+    state: State<'_, AppState>,
+    days: Option<u32>,
+    months: Option<u32>,
+) -> Result<UsageSummary, String> {
+    let config = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.clone()
+    };
+
+    UsageManager::summary(&config, days.unwrap_or(30), months.unwrap_or(12)).await
+}
+
 // ============================================================================
 // Custom Tasks Commands
 // ============================================================================
@@ -286,26 +513,13 @@ async fn get_custom_task(id: String) -> Result<Option<CustomTask>, String> {
 }
 
 #[tauri::command]
-async fn create_custom_task(
-    name: String,
-    description: String,
-    system_prompt: String,
-    options: Vec<CustomTaskOption>,
-) -> Result<CustomTask, String> {
-    let task = CustomTask::new(name, description, system_prompt, options);
-    CustomTasksManager::create(task)
+async fn create_custom_task(task: CustomTaskInput) -> Result<CustomTask, String> {
+    CustomTasksManager::create(CustomTask::new(task))
 }
 
 #[tauri::command]
-async fn update_custom_task(
-    id: String,
-    name: String,
-    description: String,
-    system_prompt: String,
-    options: Vec<CustomTaskOption>,
-) -> Result<CustomTask, String> {
-    let task = CustomTask::new(name, description, system_prompt, options);
-    CustomTasksManager::update(&id, task)
+async fn update_custom_task(id: String, task: CustomTaskInput) -> Result<CustomTask, String> {
+    CustomTasksManager::update(&id, CustomTask::new(task))
 }
 
 #[tauri::command]
@@ -338,18 +552,30 @@ async fn process_llm_request(
         config.clone()
     };
 
-    let service = LlmService::new(config.clone());
-    let response = service.process_request(request).await;
+    let in_flight = state.requests.register(request.request_id.clone())?;
+
+    if let Err(e) = check_budget(&app, &state, &config, &request.operation_type).await {
+        return Ok(LlmResponse::error(e).with_request_id(in_flight.id()));
+    }
+
+    let service = LlmService::new(config.clone())
+        .with_app_handle(app.clone())
+        .with_request(in_flight.id(), in_flight.token());
+    let response = service
+        .process_request(request)
+        .await
+        .with_request_id(in_flight.id());
 
     if response.success {
         // Handle image responses
         if response.is_image {
-            if let Some(image_url) = &response.image_url {
+            // With several images the first one is pasted; the others are in the history
+            if let Some(image_path) = response.images.first() {
                 match config.paste_behavior {
                     PasteBehavior::AutoPaste => {
-                        // Download and copy image to clipboard
+                        // Copy image to clipboard
                         if let Err(e) =
-                            copy_image_to_clipboard(&app, image_url, config.enable_debug_logging)
+                            copy_image_to_clipboard(&app, image_path, config.enable_debug_logging)
                                 .await
                         {
                             if config.enable_debug_logging {
@@ -366,9 +592,9 @@ async fn process_llm_request(
                         }
                     }
                     PasteBehavior::ClipboardMode => {
-                        // Download and copy image to clipboard only
+                        // Copy image to clipboard only
                         if let Err(e) =
-                            copy_image_to_clipboard(&app, image_url, config.enable_debug_logging)
+                            copy_image_to_clipboard(&app, image_path, config.enable_debug_logging)
                                 .await
                         {
                             if config.enable_debug_logging {
@@ -424,29 +650,35 @@ async fn process_llm_request_streaming(
     state: State<'_, AppState>,
     request: LlmRequest,
 ) -> Result<LlmResponse, String> {
-    use std::sync::atomic::Ordering;
-
-    let (config, cancel_flag) = {
+    let config = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
-        (config.clone(), state.cancel_flag.clone())
+        config.clone()
     };
 
-    // Reset cancel flag
-    cancel_flag.store(false, Ordering::Relaxed);
+    let in_flight = state.requests.register(request.request_id.clone())?;
+
+    if let Err(e) = check_budget(&app, &state, &config, &request.operation_type).await {
+        return Ok(LlmResponse::error(e).with_request_id(in_flight.id()));
+    }
 
     // Check if operation supports streaming
     if !LlmService::supports_streaming(&request.operation_type) {
         // Fall back to non-streaming for non-text operations (images, audio, etc.)
-        let service = LlmService::new(config.clone());
-        let response = service.process_request(request).await;
+        let service = LlmService::new(config.clone())
+            .with_app_handle(app.clone())
+            .with_request(in_flight.id(), in_flight.token());
+        let response = service
+            .process_request(request)
+            .await
+            .with_request_id(in_flight.id());
 
         // Handle image responses for non-streaming operations
         if response.success && response.is_image {
-            if let Some(image_url) = &response.image_url {
+            if let Some(image_path) = response.images.first() {
                 match config.paste_behavior {
                     PasteBehavior::AutoPaste => {
                         if let Err(e) =
-                            copy_image_to_clipboard(&app, image_url, config.enable_debug_logging)
+                            copy_image_to_clipboard(&app, image_path, config.enable_debug_logging)
                                 .await
                         {
                             if config.enable_debug_logging {
@@ -460,7 +692,7 @@ async fn process_llm_request_streaming(
                     }
                     PasteBehavior::ClipboardMode => {
                         if let Err(e) =
-                            copy_image_to_clipboard(&app, image_url, config.enable_debug_logging)
+                            copy_image_to_clipboard(&app, image_path, config.enable_debug_logging)
                                 .await
                         {
                             if config.enable_debug_logging {
@@ -476,10 +708,11 @@ async fn process_llm_request_streaming(
         return Ok(response);
     }
 
-    let service = LlmService::new(config.clone());
+    let service = LlmService::new(config.clone()).with_request(in_flight.id(), in_flight.token());
     let response = service
-        .process_streaming_request(&request, &app, cancel_flag)
-        .await;
+        .process_streaming_request(&request, &app)
+        .await
+        .with_request_id(in_flight.id());
 
     // Handle paste behavior for successful responses
     if response.success {
@@ -509,69 +742,200 @@ async fn process_llm_request_streaming(
     Ok(response)
 }
 
-/// Cancel the current streaming request
+/// Cancel a running request, or every running request when no id is given
 #[tauri::command]
-async fn cancel_llm_request(state: State<'_, AppState>) -> Result<(), String> {
-    use std::sync::atomic::Ordering;
-    state.cancel_flag.store(true, Ordering::Relaxed);
+async fn cancel_llm_request(
+    state: State<'_, AppState>,
+    request_id: Option<String>,
+) -> Result<(), String> {
+    match request_id {
+        Some(request_id) => {
+            state.requests.cancel(&request_id)?;
+        }
+        None => {
+            state.requests.cancel_all()?;
+        }
+    }
     Ok(())
 }
 
+/// Fetch models from a profile (the default profile if `profile_id` is not given)
 #[tauri::command]
-async fn get_models_from_api(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+async fn get_models_from_api(
+    state: State<'_, AppState>,
+    profile_id: Option<String>,
+) -> Result<Vec<String>, String> {
     let config = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.clone()
     };
+    let provider = config.profile_or_default(profile_id.as_deref())?.provider;
 
     let service = LlmService::new(config);
-    let response = service.get_models().await?;
+    let models = service.get_models(profile_id.as_deref()).await?;
+
+    // Ollama only serves local chat models, so the tags list is the model list.
+    // Persist it so the app keeps working offline.
+    if provider == ProviderKind::Ollama {
+        let mut config = state.config.lock().map_err(|e| e.to_string())?;
+        config.models = models.clone();
+        config.save()?;
+    }
 
-    Ok(response.data.into_iter().map(|m| m.id).collect())
+    Ok(models)
 }
 
+/// Pull a model on an Ollama profile's server (emits ollama-pull-progress events)
 #[tauri::command]
-async fn test_connection(state: State<'_, AppState>) -> Result<(), String> {
+async fn pull_ollama_model(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    model: String,
+    profile_id: Option<String>,
+) -> Result<(), String> {
     let config = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.clone()
     };
 
+    let profile = config.profile_or_default(profile_id.as_deref())?;
+    if profile.provider != ProviderKind::Ollama {
+        return Err("Model pulling is only available for the Ollama provider".to_string());
+    }
+
+    let provider = providers::OllamaProvider::new(
+        providers::build_client(profile, config.enable_debug_logging),
+        profile.api_base_url.clone(),
+        profile.get_api_key(),
+        config.ollama_keep_alive.clone(),
+        config.ollama_num_ctx,
+        config.enable_debug_logging,
+    );
+
+    provider
+        .pull_model(&model, |progress| {
+            let _ = app.emit("ollama-pull-progress", progress);
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Refresh the local model list now that the pull finished
     let service = LlmService::new(config);
-    service.test_connection().await
+    let models = service.get_models(profile_id.as_deref()).await?;
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+    config.models = models;
+    config.save()
 }
 
 #[tauri::command]
-async fn get_models_with_endpoint(
+async fn test_connection(
     state: State<'_, AppState>,
-    api_base_url: String,
-    api_key: Option<String>,
-) -> Result<Vec<String>, String> {
-    // Get the stored config for API key if not provided
-    let stored_config = {
+    profile_id: Option<String>,
+) -> Result<(), String> {
+    let config = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.clone()
     };
 
-    // Create a temporary config with the provided endpoint
-    let mut config = Configuration::default();
-    config.api_base_url = api_base_url;
+    let service = LlmService::new(config);
+    service.test_connection(profile_id.as_deref()).await
+}
 
-    // Use provided API key if given (as plaintext), otherwise use the stored encrypted one
+/// Count the tokens of a prompt for the model that would process it
+#[tauri::command]
+async fn count_tokens(
+    state: State<'_, AppState>,
+    text: String,
+    operation_type: Option<String>,
+) -> Result<tokens::TokenCount, String> {
+    let config = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.clone()
+    };
+
+    let service = LlmService::new(config);
+    Ok(service.count_prompt_tokens(&text, operation_type.as_deref()))
+}
+
+/// Check files before they are attached, returning the tokens each one adds to the prompt
+#[tauri::command]
+async fn inspect_attachments(
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let model = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.llm_model.clone()
+    };
+
+    let mut attachments = Vec::with_capacity(paths.len());
+    for path in paths {
+        attachments.push(AttachmentInfo::inspect(path, model.clone()).await?);
+    }
+    Ok(attachments)
+}
+
+/// Build a one-profile configuration for testing unsaved endpoint settings.
+/// Key, provider and headers default to those of the stored profile `profile_id`
+/// (or the default profile).
+fn endpoint_config(
+    stored_config: &Configuration,
+    api_base_url: String,
+    api_key: Option<String>,
+    provider: Option<ProviderKind>,
+    profile_id: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<Configuration, String> {
+    let stored_profile = stored_config
+        .profile_or_default(profile_id.as_deref())?
+        .clone();
+
+    let mut profile = EndpointProfile {
+        api_base_url,
+        provider: provider.unwrap_or(stored_profile.provider),
+        headers: headers.unwrap_or(stored_profile.headers),
+        ..stored_profile
+    };
+
+    // Use provided API key if given (as plaintext), otherwise keep the stored one
     if let Some(key) = api_key {
         if !key.is_empty() {
-            config.plaintext_api_key = Some(key);
-        } else {
-            config.api_key = stored_config.api_key;
+            profile.plaintext_api_key = Some(key);
         }
-    } else {
-        config.api_key = stored_config.api_key;
     }
 
-    let service = LlmService::new(config);
-    let response = service.get_models().await?;
+    Ok(Configuration {
+        default_profile_id: profile.id.clone(),
+        profiles: vec![profile],
+        ..Default::default()
+    })
+}
 
-    Ok(response.data.into_iter().map(|m| m.id).collect())
+#[tauri::command]
+async fn get_models_with_endpoint(
+    state: State<'_, AppState>,
+    api_base_url: String,
+    api_key: Option<String>,
+    provider: Option<ProviderKind>,
+    profile_id: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<Vec<String>, String> {
+    // Get the stored config for API key if not provided
+    let stored_config = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.clone()
+    };
+
+    let config = endpoint_config(
+        &stored_config,
+        api_base_url,
+        api_key,
+        provider,
+        profile_id,
+        headers,
+    )?;
+    let service = LlmService::new(config);
+    service.get_models(None).await
 }
 
 #[tauri::command]
@@ -579,6 +943,9 @@ async fn test_connection_with_endpoint(
     state: State<'_, AppState>,
     api_base_url: String,
     api_key: Option<String>,
+    provider: Option<ProviderKind>,
+    profile_id: Option<String>,
+    headers: Option<HashMap<String, String>>,
 ) -> Result<(), String> {
     // Get the stored config for API key if not provided
     let stored_config = {
@@ -586,23 +953,16 @@ async fn test_connection_with_endpoint(
         config.clone()
     };
 
-    // Create a temporary config with the provided endpoint
-    let mut config = Configuration::default();
-    config.api_base_url = api_base_url;
-
-    // Use provided API key if given (as plaintext), otherwise use the stored encrypted one
-    if let Some(key) = api_key {
-        if !key.is_empty() {
-            config.plaintext_api_key = Some(key);
-        } else {
-            config.api_key = stored_config.api_key;
-        }
-    } else {
-        config.api_key = stored_config.api_key;
-    }
-
+    let config = endpoint_config(
+        &stored_config,
+        api_base_url,
+        api_key,
+        provider,
+        profile_id,
+        headers,
+    )?;
     let service = LlmService::new(config);
-    service.test_connection().await
+    service.test_connection(None).await
 }
 
 // ============================================================================
@@ -737,82 +1097,9 @@ fn mask_api_key(key: String) -> String {
 }
 
 // ============================================================================
-// File Download Commands
+// Media File Commands
 // ============================================================================
 
-/// Download an image from URL and save it to the media folder, returning the local path
-/// This is used for persisting generated images to history
-#[tauri::command]
-async fn save_generated_image(
-    state: State<'_, AppState>,
-    image_url: String,
-) -> Result<String, String> {
-    let debug_logging = {
-        let config = state.config.lock().map_err(|e| e.to_string())?;
-        config.enable_debug_logging
-    };
-
-    if debug_logging {
-        println!(
-            "[save_generated_image] Starting download from: {}",
-            image_url
-        );
-    }
-
-    // Create HTTP client
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    // Download the image
-    let response = client
-        .get(&image_url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download image: {}", e))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "Download failed with status {}: {}",
-            status, error_text
-        ));
-    }
-
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read image bytes: {}", e))?;
-
-    if debug_logging {
-        println!("[save_generated_image] Downloaded {} bytes", bytes.len());
-    }
-
-    if bytes.is_empty() {
-        return Err("Downloaded image is empty".to_string());
-    }
-
-    // Determine format from URL or default to png
-    let format = if image_url.contains(".jpg") || image_url.contains(".jpeg") {
-        "jpg"
-    } else if image_url.contains(".webp") {
-        "webp"
-    } else {
-        "png"
-    };
-
-    // Save to media folder
-    let saved_path = HistoryManager::save_image(&bytes, format)?;
-
-    if debug_logging {
-        println!("[save_generated_image] Saved to: {}", saved_path);
-    }
-
-    Ok(saved_path)
-}
-
 /// Clear all media files in the media folder
 #[tauri::command]
 async fn clear_all_media() -> Result<u32, String> {
@@ -835,12 +1122,11 @@ async fn clear_all_media() -> Result<u32, String> {
     Ok(deleted_count)
 }
 
-/// Download an image from a URL and save it to a local file
-/// This bypasses CORS restrictions by using the backend HTTP client
+/// Save a generated image from the media folder to a user-selected location
 #[tauri::command]
-async fn download_and_save_image(
+async fn save_image_as(
     state: State<'_, AppState>,
-    image_url: String,
+    image_path: String,
     save_path: String,
 ) -> Result<(), String> {
     let debug_logging = {
@@ -849,89 +1135,12 @@ async fn download_and_save_image(
     };
 
     if debug_logging {
-        println!("[download_and_save_image] Starting download...");
-        println!("[download_and_save_image] URL: {}", image_url);
-        println!("[download_and_save_image] Save path: {}", save_path);
-    }
-
-    // Create HTTP client with timeout
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()
-        .map_err(|e| {
-            let err = format!("Failed to create HTTP client: {}", e);
-            if debug_logging {
-                println!("[download_and_save_image] Error: {}", err);
-            }
-            err
-        })?;
-
-    // Download the image
-    if debug_logging {
-        println!("[download_and_save_image] Sending GET request...");
+        println!("[save_image_as] Copying {} to {}", image_path, save_path);
     }
 
-    let response = client.get(&image_url).send().await.map_err(|e| {
-        let err = format!("Failed to download image: {}", e);
-        if debug_logging {
-            println!("[download_and_save_image] Request error: {}", err);
-        }
-        err
-    })?;
-
-    let status = response.status();
-    if debug_logging {
-        println!("[download_and_save_image] Response status: {}", status);
-    }
-
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        let err = format!("Download failed with status {}: {}", status, error_text);
-        if debug_logging {
-            println!("[download_and_save_image] Error response: {}", err);
-        }
-        return Err(err);
-    }
-
-    // Get the image bytes
-    let bytes = response.bytes().await.map_err(|e| {
-        let err = format!("Failed to read image bytes: {}", e);
-        if debug_logging {
-            println!("[download_and_save_image] Error reading bytes: {}", err);
-        }
-        err
-    })?;
-
-    if debug_logging {
-        println!("[download_and_save_image] Downloaded {} bytes", bytes.len());
-    }
-
-    if bytes.is_empty() {
-        let err = "Downloaded image is empty (0 bytes)".to_string();
-        if debug_logging {
-            println!("[download_and_save_image] Error: {}", err);
-        }
-        return Err(err);
-    }
-
-    // Write to file
-    if debug_logging {
-        println!("[download_and_save_image] Writing to file: {}", save_path);
-    }
-
-    std::fs::write(&save_path, &bytes).map_err(|e| {
-        let err = format!("Failed to write image file: {} (path: {})", e, save_path);
-        if debug_logging {
-            println!("[download_and_save_image] Write error: {}", err);
-        }
-        err
-    })?;
-
-    if debug_logging {
-        println!("[download_and_save_image] Successfully saved image!");
-    }
-
-    Ok(())
+    std::fs::copy(&image_path, &save_path)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to save image: {} (path: {})", e, save_path))
 }
 
 /// Copy an audio file from one location to another
@@ -1023,20 +1232,20 @@ async fn bring_window_to_front(app: AppHandle) -> Result<(), String> {
     }
 }
 
-/// Copy an image from URL to clipboard (exposed as Tauri command)
+/// Copy an image file to clipboard (exposed as Tauri command)
 /// This is used by the frontend to copy images to clipboard
 #[tauri::command]
 async fn copy_image_to_clipboard_command(
     app: AppHandle,
     state: State<'_, AppState>,
-    image_url: String,
+    image_path: String,
 ) -> Result<(), String> {
     let debug_logging = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.enable_debug_logging
     };
 
-    copy_image_to_clipboard(&app, &image_url, debug_logging).await
+    copy_image_to_clipboard(&app, &image_path, debug_logging).await
 }
 
 // ============================================================================
@@ -1148,8 +1357,11 @@ pub fn run() {
             save_history_entry,
             delete_history_entry,
             clear_history,
+            get_thread,
             cleanup_old_media,
             clear_all_media,
+            // Usage
+            get_usage_summary,
             // Custom Tasks
             get_custom_tasks,
             get_custom_task,
@@ -1163,7 +1375,10 @@ pub fn run() {
             process_llm_request_streaming,
             cancel_llm_request,
             get_models_from_api,
+            pull_ollama_model,
             test_connection,
+            count_tokens,
+            inspect_attachments,
             get_models_with_endpoint,
             test_connection_with_endpoint,
             // Text capture
@@ -1183,8 +1398,7 @@ pub fn run() {
             decrypt_api_key,
             mask_api_key,
             // File Downloads
-            download_and_save_image,
-            save_generated_image,
+            save_image_as,
             // Audio file operations
             copy_audio_file,
             // Image to Clipboard
@@ -1,52 +1,164 @@
 //! LLM Service module for AI Anywhere
-//! Handles all AI API communication with OpenAI-compatible endpoints
+//! Builds prompts for operations and custom tasks and dispatches them to the configured provider
 
-use futures_util::StreamExt;
-use reqwest::multipart;
+use futures_util::stream::{self, StreamExt};
+use image::ImageFormat;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::json;
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
-
-use crate::config::Configuration;
-use crate::custom_tasks::CustomTasksManager;
+use tokio_util::sync::CancellationToken;
+
+use crate::attachments::{attachment_blocks, load_attachments};
+use crate::audio::{
+    decode_mono, encode_wav, split_segments, MAX_SEGMENT_SECONDS, MAX_UPLOAD_BYTES, SAMPLE_RATE,
+};
+use crate::config::{Configuration, EndpointProfile, ModelSlot, ProviderKind};
+use crate::custom_tasks::{CustomTasksManager, JsonOutput};
 use crate::history::HistoryManager;
+use crate::image_models::ImageCapabilities;
 use crate::operations::OperationType;
+use crate::providers::{
+    create_provider, think_blocks, ChatMessage, ChatRequest, ChatResponse, GenerationParams,
+    ImageEditRequest, ImageRequest, ImageResponse, ImageVariationRequest, LlmProvider,
+    ProviderError, ResponseSchema, RetryPolicy, SpeechRequest, StreamPart, StreamSink,
+    ThinkTagSplitter, TranscriptionRequest, Usage,
+};
+use crate::structured::{compile_schema, parse_answer, render_template};
 use crate::text::{extract_size_dimensions, normalize_transcription, process_llm_response};
+use crate::threads::{trim_to_fit, Thread, ThreadManager};
+use crate::tokens::{
+    count_message_tokens, count_tokens, known_context_window, max_input_tokens, split_into_chunks,
+    Encoding, TokenCount, DEFAULT_CONTEXT_WINDOW,
+};
+use crate::tools::{run_call, Tool};
+use crate::usage::{UsageManager, UsageRecord};
+use crate::vision::{load_edit_images, load_images, ImageInput};
 
-/// Maximum estimated tokens allowed in a prompt (security limit)
-/// This matches the frontend limit to provide defense in depth
-const MAX_ESTIMATED_TOKENS: usize = 16000;
+/// Segments of a long recording transcribed at the same time
+const MAX_PARALLEL_TRANSCRIPTIONS: usize = 3;
 
-/// Token estimation correction factor (20% safety margin)
-const TOKEN_CORRECTION_FACTOR: f64 = 1.20;
+/// Response budget assumed when an operation does not set `max_tokens`
+const DEFAULT_MAX_TOKENS: u32 = 4096;
 
-/// Estimates the number of tokens in a text string.
-/// Uses word count * 1.33 * 1.20 as approximation (matches frontend logic).
-fn estimate_tokens(text: &str) -> usize {
-    if text.trim().is_empty() {
-        return 0;
-    }
-    let words: usize = text.split_whitespace().count();
-    ((words as f64) * 1.33 * TOKEN_CORRECTION_FACTOR).ceil() as usize
-}
+/// Smallest chunk worth sending when splitting a long input
+const MIN_CHUNK_TOKENS: usize = 256;
+
+/// Rounds of summarizing partial summaries before the final reduce step
+const MAX_REDUCE_ROUNDS: usize = 4;
+
+/// Instruction for merging the partial summaries of a long document
+const REDUCE_INSTRUCTION: &str = "The following are summaries of consecutive parts of a single \
+    document. Combine them into one summary of the whole document.";
+
+/// Instruction of the turn sent after a response was cut off at the token limit
+const CONTINUE_INSTRUCTION: &str = "Your previous answer was cut off because it reached the \
+    length limit. Continue exactly where it stopped, without repeating anything or adding any \
+    commentary.";
+
+/// Times a JSON answer that does not match the task's schema is sent back for correction
+const MAX_JSON_REPAIRS: usize = 2;
+
+/// Instruction of the turn sent after a JSON answer failed validation (followed by the errors)
+const JSON_REPAIR_INSTRUCTION: &str = "Your answer does not match the required JSON Schema. \
+    Reply again with only the corrected JSON.";
 
-/// Validates that the prompt doesn't exceed the maximum token limit.
+/// Validates that the prompt fits the model's context window, leaving room for the response.
 /// Returns an error message if validation fails.
-fn validate_prompt_length(prompt: &str) -> Result<(), String> {
-    let estimated_tokens = estimate_tokens(prompt);
-    if estimated_tokens > MAX_ESTIMATED_TOKENS {
+fn validate_prompt_length(chat_request: &ChatRequest, context_window: u32) -> Result<(), String> {
+    let tokens = count_message_tokens(
+        chat_request.messages.iter().map(|m| m.content.as_str()),
+        &chat_request.model,
+    );
+    let max_input = max_input_tokens(context_window, response_budget(&chat_request.params));
+    if tokens > max_input as usize {
         return Err(format!(
-            "Prompt too long (~{} estimated tokens). Maximum allowed: {} tokens.",
-            estimated_tokens, MAX_ESTIMATED_TOKENS
+            "Prompt too long ({} tokens). Model '{}' accepts up to {} prompt tokens ({} token context window, {} reserved for the response).",
+            tokens,
+            chat_request.model,
+            max_input,
+            context_window,
+            context_window - max_input
         ));
     }
     Ok(())
 }
 
+/// Tokens reserved for the response: the configured `max_tokens` or the default budget
+fn response_budget(params: &GenerationParams) -> u32 {
+    params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+}
+
+/// System prompt, option keys and generation parameters of a built-in operation or custom task
+struct TaskDefinition {
+    /// Label in the usage ledger: the operation type, or the custom task's name
+    name: String,
+    system_prompt: String,
+    option_keys: Vec<String>,
+    params: GenerationParams,
+    /// Schema and paste template of a custom task with structured output
+    json_output: Option<JsonOutput>,
+    /// Local tools offered to the model (custom tasks only)
+    tools: Vec<Tool>,
+}
+
+impl TaskDefinition {
+    /// Look up a built-in operation, then a custom task, by operation type
+    fn resolve(operation_type: &str) -> Option<Self> {
+        if let Ok(op_type) = serde_json::from_value::<OperationType>(json!(operation_type)) {
+            if let Some(op) = crate::operations::get_default_operations()
+                .into_iter()
+                .find(|op| op.operation_type == op_type)
+            {
+                return Some(Self {
+                    name: operation_type.to_string(),
+                    system_prompt: op.system_prompt,
+                    option_keys: op.options.into_iter().map(|o| o.key).collect(),
+                    params: op.params,
+                    json_output: None,
+                    tools: Vec::new(),
+                });
+            }
+        }
+
+        match CustomTasksManager::get(operation_type) {
+            Ok(Some(task)) => Some(Self {
+                name: task.name,
+                system_prompt: task.system_prompt,
+                option_keys: task.options.into_iter().map(|o| o.key).collect(),
+                params: task.params,
+                json_output: task.json_output,
+                tools: task.tools,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A chat response and the target that produced it
+struct ChatAnswer {
+    response: ChatResponse,
+    profile_id: String,
+    model: String,
+}
+
+/// Image request parameters, already checked against the image model
+struct ImageParams {
+    capabilities: &'static ImageCapabilities,
+    size: String,
+    n: u32,
+}
+
+impl ImageParams {
+    fn response_format(&self) -> Option<String> {
+        self.capabilities
+            .response_format
+            .then(|| "b64_json".to_string())
+    }
+}
+
 /// LLM Request structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -56,6 +168,22 @@ pub struct LlmRequest {
     pub selected_text: Option<String>,
     pub options: HashMap<String, String>,
     pub audio_file_path: Option<String>,
+    /// Images for multimodal models, sent with the user message.
+    /// The first one is the input image of an image edit or variation.
+    #[serde(default)]
+    pub images: Vec<ImageInput>,
+    /// Mask of an image edit: its transparent areas are the ones to change
+    #[serde(default)]
+    pub mask: Option<ImageInput>,
+    /// Paths of files whose text is sent with the user message
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Caller-chosen request id (one is generated when omitted)
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// Thread this request follows up: its messages are sent before the prompt
+    #[serde(default)]
+    pub thread_id: Option<String>,
 }
 
 /// LLM Response structure
@@ -66,11 +194,26 @@ pub struct LlmResponse {
     pub content: Option<String>,
     pub error: Option<String>,
     pub is_image: bool,
-    pub image_url: Option<String>,
+    /// Media files of the generated images, best viewed side by side when there are several
+    pub images: Vec<String>,
     pub is_audio: bool,
     pub audio_data: Option<Vec<u8>>,
     pub audio_format: Option<String>,
     pub audio_file_path: Option<String>,
+    /// Model that produced the result (a fallback model if the primary one failed)
+    pub model: Option<String>,
+    /// Tokens used by the request, if the provider reported them
+    pub usage: Option<Usage>,
+    /// Reasoning of a thinking model, kept apart from the answer (never pasted)
+    pub reasoning: Option<String>,
+    /// The text is still cut off at the token limit after every continuation round
+    pub incomplete: bool,
+    /// Validated answer of a task with a JSON output schema (`content` is the text to paste)
+    pub json: Option<serde_json::Value>,
+    /// Id of the request, as used in its events and by `cancel_llm_request`
+    pub request_id: Option<String>,
+    /// Thread the exchange was recorded in, for follow-up requests
+    pub thread_id: Option<String>,
 }
 
 impl Default for LlmResponse {
@@ -80,11 +223,18 @@ impl Default for LlmResponse {
             content: None,
             error: None,
             is_image: false,
-            image_url: None,
+            images: Vec::new(),
             is_audio: false,
             audio_data: None,
             audio_format: None,
             audio_file_path: None,
+            model: None,
+            usage: None,
+            reasoning: None,
+            incomplete: false,
+            json: None,
+            request_id: None,
+            thread_id: None,
         }
     }
 }
@@ -106,12 +256,11 @@ impl LlmResponse {
         }
     }
 
-    pub fn image(url: String) -> Self {
+    pub fn images(paths: Vec<String>) -> Self {
         Self {
             success: true,
-            content: Some(url.clone()),
             is_image: true,
-            image_url: Some(url),
+            images: paths,
             ..Default::default()
         }
     }
@@ -127,50 +276,319 @@ impl LlmResponse {
             ..Default::default()
         }
     }
+
+    /// Record the model that produced this response
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_usage(mut self, usage: Option<Usage>) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
 }
 
 /// Streaming chunk event payload
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StreamingChunk {
+    pub request_id: String,
     pub content: String,
     pub done: bool,
 }
 
-/// Models response from API
-#[derive(Debug, Deserialize)]
-pub struct ModelsResponse {
-    pub data: Vec<ModelInfo>,
+/// Streaming reasoning event payload (`llm-stream-reasoning`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReasoningChunk {
+    pub request_id: String,
+    pub content: String,
+}
+
+/// Stream cancelled event payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamCancelled {
+    pub request_id: String,
+}
+
+/// Retry event payload (emitted before waiting to retry a failed request)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryEvent {
+    pub request_id: String,
+    /// Number of the upcoming retry (1-based)
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    pub error: String,
+}
+
+/// Tool call event payload (`llm-tool-call`, emitted before the tool runs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallEvent {
+    pub request_id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+    /// Round of tool calls (1-based)
+    pub round: u32,
+}
+
+/// Stage of a request processed in chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStage {
+    /// Processing the chunks of the input
+    Map,
+    /// Merging partial summaries
+    Reduce,
+    /// Transcribing the segments of a long recording (`current` counts finished segments)
+    Transcribe,
+}
+
+/// Chunked processing progress payload (emitted before each chunk is sent)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkProgress {
+    pub request_id: String,
+    /// Number of the chunk being processed (1-based)
+    pub current: usize,
+    pub total: usize,
+    pub stage: ChunkStage,
+}
+
+/// How the outputs of a chunked request are merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkMerge {
+    /// Join the outputs in order (translation, rewrite)
+    Concatenate,
+    /// Summarize the partial summaries (summarization)
+    Summarize,
+}
+
+impl ChunkMerge {
+    /// Operations whose input may be split into chunks when it exceeds the context window
+    fn for_operation(operation_type: &str) -> Option<Self> {
+        match serde_json::from_value::<OperationType>(json!(operation_type)).ok()? {
+            OperationType::TextTranslation | OperationType::TextRewrite => {
+                Some(ChunkMerge::Concatenate)
+            }
+            OperationType::TextSummarization => Some(ChunkMerge::Summarize),
+            _ => None,
+        }
+    }
+}
+
+/// Forwards provider stream output to the frontend as Tauri events
+struct EventStreamSink<'a> {
+    app: &'a AppHandle,
+    request_id: &'a str,
+    cancel: CancellationToken,
+    /// Set once any content reached the frontend (the request can no longer be retried)
+    emitted: bool,
+    /// Swallow end-of-stream signals while several chunk responses share one stream
+    hold_done: bool,
+    /// Moves `<think>` blocks of the content to the reasoning stream
+    splitter: ThinkTagSplitter,
+}
+
+impl EventStreamSink<'_> {
+    fn emit_part(&self, part: StreamPart) {
+        let _ = match part {
+            StreamPart::Content(content) => self.app.emit(
+                "llm-stream-chunk",
+                StreamingChunk {
+                    request_id: self.request_id.to_string(),
+                    content,
+                    done: false,
+                },
+            ),
+            StreamPart::Reasoning(content) => self.app.emit(
+                "llm-stream-reasoning",
+                ReasoningChunk {
+                    request_id: self.request_id.to_string(),
+                    content,
+                },
+            ),
+        };
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ModelInfo {
-    pub id: String,
+impl StreamSink for EventStreamSink<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    fn on_content(&mut self, content: &str) {
+        self.emitted = true;
+        for part in self.splitter.push(content) {
+            self.emit_part(part);
+        }
+    }
+
+    fn on_reasoning(&mut self, reasoning: &str) {
+        self.emitted = true;
+        self.emit_part(StreamPart::Reasoning(reasoning.to_string()));
+    }
+
+    fn on_done(&mut self) {
+        // A continuation may carry on inside an open `<think>` block
+        if self.hold_done {
+            return;
+        }
+        for part in self.splitter.finish() {
+            self.emit_part(part);
+        }
+        let _ = self.app.emit(
+            "llm-stream-chunk",
+            StreamingChunk {
+                request_id: self.request_id.to_string(),
+                content: String::new(),
+                done: true,
+            },
+        );
+    }
 }
 
 /// LLM Service for API communication
 pub struct LlmService {
     config: Configuration,
-    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    /// Used to emit retry events for non-streaming requests
+    app: Option<AppHandle>,
+    /// Id of the request being processed, included in every event
+    request_id: String,
+    cancel: CancellationToken,
 }
 
 impl LlmService {
     pub fn new(config: Configuration) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(300))
-            .build()
-            .unwrap_or_default();
+        let retry_policy = RetryPolicy::from_config(&config);
+        Self {
+            config,
+            retry_policy,
+            app: None,
+            request_id: String::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
 
-        Self { config, client }
+    /// Emit events (e.g. retry notices) to the frontend through this app handle
+    pub fn with_app_handle(mut self, app: AppHandle) -> Self {
+        self.app = Some(app);
+        self
     }
 
-    /// Process an LLM request based on operation type
+    /// Tag events with a request id and stop streaming when the token is cancelled
+    pub fn with_request(
+        mut self,
+        request_id: impl Into<String>,
+        cancel: CancellationToken,
+    ) -> Self {
+        self.request_id = request_id.into();
+        self.cancel = cancel;
+        self
+    }
+
+    /// Wait before retrying a failed call, emitting an `llm-retry` event.
+    /// Gives the error back when it should not be retried, or `Cancelled` when the
+    /// request is cancelled during the wait.
+    async fn wait_before_retry(
+        &self,
+        app: Option<&AppHandle>,
+        error: ProviderError,
+        attempt: u32,
+    ) -> Result<(), ProviderError> {
+        let Some(delay) = self.retry_policy.next_delay(&error, attempt) else {
+            return Err(error);
+        };
+
+        if self.config.enable_debug_logging {
+            println!(
+                "Retry {}/{} in {}ms after: {}",
+                attempt + 1,
+                self.retry_policy.max_retries,
+                delay.as_millis(),
+                error
+            );
+        }
+
+        if let Some(app) = app {
+            let _ = app.emit(
+                "llm-retry",
+                RetryEvent {
+                    request_id: self.request_id.clone(),
+                    attempt: attempt + 1,
+                    max_retries: self.retry_policy.max_retries,
+                    delay_ms: delay.as_millis() as u64,
+                    error: error.to_string(),
+                },
+            );
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => Ok(()),
+            _ = self.cancel.cancelled() => Err(ProviderError::Cancelled),
+        }
+    }
+
+    /// Run a provider call, retrying transient failures according to the retry policy
+    async fn with_retries<T, F, Fut>(&self, mut call: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let error = match call().await {
+                Err(e) => e,
+                result => return result,
+            };
+            self.wait_before_retry(self.app.as_ref(), error, attempt)
+                .await?;
+            attempt += 1;
+        }
+    }
+
+    /// Create the provider for an endpoint profile
+    fn provider(&self, profile: &EndpointProfile) -> Box<dyn LlmProvider> {
+        if self.config.enable_debug_logging {
+            println!("Using endpoint profile: {} ({})", profile.name, profile.id);
+        }
+        create_provider(profile, &self.config)
+    }
+
+    /// Create the provider serving an operation, following per-operation and per-slot routing
+    fn provider_for(
+        &self,
+        operation_type: &str,
+        slot: ModelSlot,
+    ) -> Result<Box<dyn LlmProvider>, String> {
+        self.config
+            .resolve_profile(operation_type, slot)
+            .map(|profile| self.provider(profile))
+            .ok_or_else(|| {
+                "No endpoint profile configured. Please add one in Settings.".to_string()
+            })
+    }
+
+    /// Process an LLM request, giving up as soon as the request is cancelled
     pub async fn process_request(&self, request: LlmRequest) -> LlmResponse {
-        // Validate prompt length (security check - defense in depth)
-        if let Err(e) = validate_prompt_length(&request.prompt) {
-            return LlmResponse::error(e);
+        tokio::select! {
+            response = self.dispatch_request(request) => response,
+            _ = self.cancel.cancelled() => LlmResponse::error(ProviderError::Cancelled.to_string()),
         }
+    }
 
+    /// Process an LLM request based on operation type
+    async fn dispatch_request(&self, request: LlmRequest) -> LlmResponse {
         // Try to parse as built-in operation type
         if let Ok(op_type) = serde_json::from_value::<OperationType>(json!(request.operation_type))
         {
@@ -201,18 +619,23 @@ impl LlmService {
         }
     }
 
+    /// Model slot (and so the default endpoint profile) an operation uses
+    pub fn model_slot(operation_type: &str) -> ModelSlot {
+        match serde_json::from_value::<OperationType>(json!(operation_type)) {
+            Ok(OperationType::ImageGeneration) => ModelSlot::Image,
+            Ok(OperationType::SpeechToText) => ModelSlot::Audio,
+            Ok(OperationType::TextToSpeech) => ModelSlot::Tts,
+            // Other built-in operations and custom tasks are text-based
+            _ => ModelSlot::Llm,
+        }
+    }
+
     /// Process a streaming text request with real-time chunk emission
     pub async fn process_streaming_request(
         &self,
         request: &LlmRequest,
         app: &AppHandle,
-        cancel_flag: Arc<AtomicBool>,
     ) -> LlmResponse {
-        // Validate prompt length (security check - defense in depth)
-        if let Err(e) = validate_prompt_length(&request.prompt) {
-            return LlmResponse::error(e);
-        }
-
         // Only text operations support streaming
         if !Self::supports_streaming(&request.operation_type) {
             return LlmResponse::error(
@@ -220,452 +643,1236 @@ impl LlmService {
             );
         }
 
-        let operations = crate::operations::get_default_operations();
+        let thread = match Self::load_thread(request).await {
+            Ok(thread) => thread,
+            Err(e) => return LlmResponse::error(e),
+        };
+        let mut chat_request = match self
+            .build_chat_request(request, thread.as_ref(), Some(app))
+            .await
+        {
+            Ok(chat_request) => chat_request,
+            Err(e) => return LlmResponse::error(e),
+        };
 
-        // Try to find in default operations
-        let mut system_prompt = String::new();
+        let targets = match self.text_targets(&request.operation_type).await {
+            Ok(targets) => targets,
+            Err(e) => return LlmResponse::error(e),
+        };
 
-        // Check if it's a built-in operation
-        if let Ok(op_type) = serde_json::from_value::<OperationType>(json!(request.operation_type))
-        {
-            if let Some(op) = operations.iter().find(|op| op.operation_type == op_type) {
-                system_prompt = op.system_prompt.clone();
-            }
+        // Validate prompt length against the primary model (defense in depth).
+        // Long inputs of some operations are processed in chunks instead.
+        let context_window = self.context_window(targets[0].0, &chat_request.model);
+        if thread.is_some() {
+            self.trim_thread(&mut chat_request, context_window);
         }
+        let chunking = match validate_prompt_length(&chat_request, context_window) {
+            Ok(()) => None,
+            Err(e) => match Self::chunk_merge(request) {
+                Some(merge) => Some(merge),
+                None => return LlmResponse::error(Self::prompt_too_long(request, e)),
+            },
+        };
 
-        // If not found, check custom tasks
-        if system_prompt.is_empty() {
-            if let Ok(Some(task)) = CustomTasksManager::get(&request.operation_type) {
-                system_prompt = task.system_prompt.clone();
-            } else if system_prompt.is_empty() {
-                // Fallback or error if not found
-                return LlmResponse::error("Unknown operation type".to_string());
-            }
-        }
-        for (key, value) in &request.options {
-            system_prompt = system_prompt.replace(&format!("{{{}}}", key), value);
-        }
+        // Answers of tasks with a JSON output schema are validated against it afterwards
+        let structured = chat_request
+            .response_schema
+            .is_some()
+            .then(|| chat_request.clone());
+        let exchange = Self::exchange(request, thread, &chat_request, chunking.is_some());
+
+        let mut sink = EventStreamSink {
+            app,
+            request_id: &self.request_id,
+            cancel: self.cancel.clone(),
+            emitted: false,
+            hold_done: false,
+            splitter: ThinkTagSplitter::new(),
+        };
 
-        // Build user prompt
-        let user_prompt = if let Some(ref selected_text) = request.selected_text {
-            if !selected_text.is_empty() {
-                format!("{}\n\nText to process:\n{}", request.prompt, selected_text)
-            } else {
-                request.prompt.clone()
+        let result = match chunking {
+            Some(merge) => {
+                self.chat_in_chunks(
+                    request,
+                    chat_request,
+                    &targets,
+                    context_window,
+                    merge,
+                    Some(&mut sink),
+                )
+                .await
+            }
+            None => {
+                self.chat_with_fallback(chat_request, &targets, Some(&mut sink))
+                    .await
             }
-        } else {
-            request.prompt.clone()
         };
 
-        // Build request body with streaming enabled
-        let body = json!({
-            "model": self.config.llm_model,
-            "messages": [
-                {"role": "system", "content": system_prompt},
-                {"role": "user", "content": user_prompt}
-            ],
-            "max_tokens": 4096,
-            "temperature": 0.6,
-            "stream": true
-        });
-
-        let url = self.build_api_url("/chat/completions");
+        let response = match result {
+            Ok(answer) => match structured {
+                Some(chat_request) => {
+                    self.json_response(&request.operation_type, chat_request, &targets, answer)
+                        .await
+                }
+                None => self.text_response(&request.operation_type, answer).await,
+            },
+            Err(ProviderError::Cancelled) => {
+                let _ = app.emit(
+                    "llm-stream-cancelled",
+                    StreamCancelled {
+                        request_id: self.request_id.clone(),
+                    },
+                );
+                LlmResponse::error(ProviderError::Cancelled.to_string())
+            }
+            Err(e) => LlmResponse::error(e.to_string()),
+        };
+        self.record_exchange(exchange, response).await
+    }
 
-        if self.config.enable_debug_logging {
-            println!("--- LLM Streaming Request ---");
-            println!("Config API Base URL: {}", self.config.api_base_url);
-            println!("Final URL: POST {}", url);
-            println!("Model: {}", self.config.llm_model);
-            println!("-------------------");
+    /// Load the thread a request follows up, if any
+    async fn load_thread(request: &LlmRequest) -> Result<Option<Thread>, String> {
+        match request.thread_id {
+            Some(ref id) => ThreadManager::get(id)
+                .await?
+                .map(Some)
+                .ok_or_else(|| "This conversation is no longer available".to_string()),
+            None => Ok(None),
         }
+    }
 
-        let api_key = self.config.get_api_key();
-        if api_key.is_empty() {
-            return LlmResponse::error(
-                "API key is empty. Please configure your API key in settings.".to_string(),
+    /// Drop the oldest exchanges of a follow-up that does not fit the context window
+    fn trim_thread(&self, chat_request: &mut ChatRequest, context_window: u32) {
+        let max_input = max_input_tokens(context_window, response_budget(&chat_request.params));
+        let dropped = trim_to_fit(
+            &mut chat_request.messages,
+            &chat_request.model,
+            max_input as usize,
+        );
+        if dropped > 0 && self.config.enable_debug_logging {
+            println!(
+                "Dropped the {} oldest exchange(s) of the thread to fit the context window",
+                dropped
             );
         }
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
-                if self.config.enable_debug_logging {
-                    println!("--- LLM Streaming Response ---");
-                    println!("Status: {}", status);
-                }
+    /// The thread a request's exchange goes into (a new one unless it is a follow-up)
+    /// and the prompt it adds. Threads keep what the user typed or selected, not the
+    /// attached files, so requests with attachments or processed in chunks start none:
+    /// their input would not be there to follow up on.
+    fn exchange(
+        request: &LlmRequest,
+        thread: Option<Thread>,
+        chat_request: &ChatRequest,
+        chunked: bool,
+    ) -> Option<(Thread, String)> {
+        if thread.is_none() && (chunked || !request.attachments.is_empty()) {
+            return None;
+        }
+        let thread = thread.unwrap_or_else(|| {
+            Thread::new(&request.operation_type, &chat_request.messages[0].content)
+        });
+        Some((thread, Self::user_text(request)))
+    }
 
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-                    if self.config.enable_debug_logging {
-                        println!("Error Body: {}", error_text);
-                        println!("--------------------");
-                    }
-                    return LlmResponse::error(format!("API Error: {}", error_text));
-                }
+    /// Add a successful exchange to its thread and tag the response with the thread id
+    async fn record_exchange(
+        &self,
+        exchange: Option<(Thread, String)>,
+        response: LlmResponse,
+    ) -> LlmResponse {
+        let Some((mut thread, prompt)) = exchange.filter(|_| response.success) else {
+            return response;
+        };
+        // The conversation continues from the JSON of a structured answer, not its rendering
+        let answer = match response.json {
+            Some(ref json) => json.to_string(),
+            None => response.content.clone().unwrap_or_default(),
+        };
+        thread.push_exchange(&prompt, &answer);
+
+        let thread_id = thread.id.clone();
+        match ThreadManager::put(thread).await {
+            Ok(()) => LlmResponse {
+                thread_id: Some(thread_id),
+                ..response
+            },
+            // The answer matters more than being able to follow it up
+            Err(e) => {
+                eprintln!("[LlmService] Failed to save the thread: {}", e);
+                response
+            }
+        }
+    }
 
-                // Process the streaming response
-                let mut full_content = String::new();
-                let mut stream = resp.bytes_stream();
-                let mut buffer = String::new(); // Buffer for incomplete SSE lines
-
-                while let Some(chunk_result) = stream.next().await {
-                    // Check if cancelled
-                    if cancel_flag.load(Ordering::Relaxed) {
-                        // Emit cancelled event
-                        let _ = app.emit("llm-stream-cancelled", ());
-                        return LlmResponse::error("Request cancelled".to_string());
-                    }
+    /// Context window of a model: a configured override, Ollama's `num_ctx`,
+    /// the built-in table, then a conservative default
+    fn context_window(&self, profile: &EndpointProfile, model: &str) -> u32 {
+        if let Some(window) = self.config.context_windows.get(model.trim()) {
+            return *window;
+        }
+        if profile.provider == ProviderKind::Ollama && self.config.ollama_num_ctx > 0 {
+            return self.config.ollama_num_ctx;
+        }
+        known_context_window(model).unwrap_or(DEFAULT_CONTEXT_WINDOW)
+    }
 
-                    match chunk_result {
-                        Ok(chunk) => {
-                            let chunk_str = String::from_utf8_lossy(&chunk);
-                            buffer.push_str(&chunk_str);
-
-                            // Process complete lines from buffer
-                            while let Some(newline_pos) = buffer.find('\n') {
-                                let line = buffer[..newline_pos].trim_end_matches('\r').to_string();
-                                buffer = buffer[newline_pos + 1..].to_string();
-
-                                if line.starts_with("data: ") {
-                                    let data = &line[6..];
-
-                                    if data == "[DONE]" {
-                                        // Emit final done event
-                                        let _ = app.emit(
-                                            "llm-stream-chunk",
-                                            StreamingChunk {
-                                                content: String::new(),
-                                                done: true,
-                                            },
-                                        );
-                                        continue;
-                                    }
-
-                                    // Parse JSON data
-                                    if let Ok(json) = serde_json::from_str::<Value>(data) {
-                                        if let Some(content) =
-                                            json["choices"][0]["delta"]["content"].as_str()
-                                        {
-                                            full_content.push_str(content);
-
-                                            // Emit chunk event
-                                            let _ = app.emit(
-                                                "llm-stream-chunk",
-                                                StreamingChunk {
-                                                    content: content.to_string(),
-                                                    done: false,
-                                                },
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            if self.config.enable_debug_logging {
-                                println!("Stream error: {}", e);
-                            }
-                            return LlmResponse::error(format!("Stream error: {}", e));
-                        }
-                    }
-                }
+    /// Count the tokens of a prompt for the model serving an operation
+    pub fn count_prompt_tokens(&self, text: &str, operation_type: Option<&str>) -> TokenCount {
+        let model = self.config.llm_model.clone();
+        let params = operation_type
+            .and_then(TaskDefinition::resolve)
+            .map(|task| task.params)
+            .unwrap_or_default();
+        let context_window = match self
+            .config
+            .resolve_profile(operation_type.unwrap_or_default(), ModelSlot::Llm)
+        {
+            Some(profile) => self.context_window(profile, &model),
+            None => known_context_window(&model).unwrap_or(DEFAULT_CONTEXT_WINDOW),
+        };
 
-                if self.config.enable_debug_logging {
-                    println!("Full streamed content length: {}", full_content.len());
-                    println!("--------------------");
-                }
+        TokenCount {
+            tokens: count_tokens(text, &model),
+            encoding: Encoding::for_model(&model),
+            context_window,
+            max_input_tokens: max_input_tokens(context_window, response_budget(&params)),
+            supports_chunking: operation_type.and_then(ChunkMerge::for_operation).is_some(),
+            model,
+        }
+    }
 
-                let processed = process_llm_response(&full_content);
-                LlmResponse::success(processed)
-            }
-            Err(e) => {
+    /// The primary text model followed by the configured fallbacks, each paired with
+    /// the profile serving it. Fallbacks without a profile use the primary's profile.
+    /// Fallbacks on another profile whose budget is used up are left out.
+    async fn text_targets(
+        &self,
+        operation_type: &str,
+    ) -> Result<Vec<(&EndpointProfile, String)>, String> {
+        let primary = self
+            .config
+            .resolve_profile(operation_type, ModelSlot::Llm)
+            .ok_or_else(|| {
+                "No endpoint profile configured. Please add one in Settings.".to_string()
+            })?;
+
+        let mut targets = vec![(primary, self.config.llm_model.clone())];
+        for fallback in &self.config.fallback_models {
+            let profile = if fallback.profile_id.is_empty() {
+                Some(primary)
+            } else {
+                self.config.profile(&fallback.profile_id)
+            };
+            let Some(profile) = profile.filter(|_| !fallback.model.is_empty()) else {
+                continue;
+            };
+            // The primary profile's budget is checked before the request is made; other
+            // profiles are skipped once their budget is used up
+            if profile.id != primary.id && UsageManager::is_exhausted(profile).await? {
                 if self.config.enable_debug_logging {
-                    println!("Request Failed: {}", e);
-                    println!("--------------------");
+                    println!(
+                        "Skipping fallback {} of endpoint profile '{}': budget exhausted",
+                        fallback.model, profile.name
+                    );
                 }
-                LlmResponse::error(format!("Request failed: {}", e))
+                continue;
             }
+            targets.push((profile, fallback.model.clone()));
         }
+        Ok(targets)
     }
 
-    /// Build the correct API URL for the given endpoint path
-    fn build_api_url(&self, endpoint: &str) -> String {
-        let base = self.config.api_base_url.trim_end_matches('/');
+    /// Build the response to a text request and record its usage in the ledger
+    async fn text_response(&self, operation_type: &str, answer: ChatAnswer) -> LlmResponse {
+        let usage = answer.response.usage;
+        if let Some(usage) = usage {
+            let operation = TaskDefinition::resolve(operation_type)
+                .map(|task| task.name)
+                .unwrap_or_else(|| operation_type.to_string());
+            let record = UsageRecord::new(
+                &self.config,
+                answer.profile_id,
+                answer.model.clone(),
+                operation,
+                usage,
+            );
+            // The answer matters more than its bookkeeping
+            if let Err(e) = UsageManager::record(&record).await {
+                eprintln!("[LlmService] Failed to record usage: {}", e);
+            }
+        }
 
-        // Check if the base URL already ends with /v1 or similar API version
-        // If not, and it doesn't already contain the endpoint, add /v1
-        let needs_v1 = !base.ends_with("/v1")
-            && !base.ends_with("/v1/")
-            && !base.contains("/chat/")
-            && !base.contains("/images/")
-            && !base.contains("/audio/")
-            && !base.contains("/models");
+        if answer.response.truncated && self.config.enable_debug_logging {
+            println!("Response is still truncated after the continuation rounds");
+        }
 
-        if needs_v1 {
-            format!("{}/v1{}", base, endpoint)
-        } else {
-            format!("{}{}", base, endpoint)
+        // Reasoning from a dedicated field and from `<think>` blocks of the content
+        let reasoning = [
+            answer.response.reasoning.trim().to_string(),
+            think_blocks(&answer.response.content),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+        LlmResponse {
+            reasoning: Some(reasoning).filter(|reasoning| !reasoning.is_empty()),
+            incomplete: answer.response.truncated,
+            ..LlmResponse::success(process_llm_response(&answer.response.content))
+                .with_model(answer.model)
+                .with_usage(usage)
         }
     }
 
-    /// Process text-based requests (chat completions)
-    async fn process_text_request(&self, request: &LlmRequest) -> LlmResponse {
-        let operations = crate::operations::get_default_operations();
+    /// Build the response of a task with a JSON output schema: validate the answer
+    /// (asking the model that gave it for corrections), then render the JSON through
+    /// the task's template, or pretty-print it, for pasting
+    async fn json_response(
+        &self,
+        operation_type: &str,
+        chat_request: ChatRequest,
+        targets: &[(&EndpointProfile, String)],
+        mut answer: ChatAnswer,
+    ) -> LlmResponse {
+        let output = match TaskDefinition::resolve(operation_type).and_then(|t| t.json_output) {
+            Some(output) => output,
+            None => return self.text_response(operation_type, answer).await,
+        };
 
-        let mut system_prompt = String::new();
+        let profile = targets
+            .iter()
+            .map(|(profile, _)| *profile)
+            .find(|profile| profile.id == answer.profile_id)
+            .unwrap_or(targets[0].0);
+        let provider = self.provider(profile);
+        let mut chat_request = chat_request;
+        chat_request.model = answer.model.clone();
+
+        let result = self
+            .validated_json(
+                provider.as_ref(),
+                &output,
+                &chat_request,
+                &mut answer.response,
+            )
+            .await;
 
-        // Check if it's a built-in operation
-        if let Ok(op_type) = serde_json::from_value::<OperationType>(json!(request.operation_type))
-        {
-            if let Some(op) = operations.iter().find(|op| op.operation_type == op_type) {
-                system_prompt = op.system_prompt.clone();
+        // Usage is recorded whether or not the JSON ends up valid
+        let response = self.text_response(operation_type, answer).await;
+        match result {
+            Ok(value) => {
+                let content = match output.template.as_deref() {
+                    Some(template) if !template.trim().is_empty() => {
+                        render_template(template, &value)
+                    }
+                    _ => serde_json::to_string_pretty(&value).unwrap_or_default(),
+                };
+                LlmResponse {
+                    content: Some(content),
+                    json: Some(value),
+                    ..response
+                }
             }
+            Err(e) => LlmResponse {
+                success: false,
+                content: None,
+                error: Some(e),
+                ..response
+            },
         }
+    }
 
-        // If not found, check custom tasks
-        if system_prompt.is_empty() {
-            if let Ok(Some(task)) = CustomTasksManager::get(&request.operation_type) {
-                system_prompt = task.system_prompt.clone();
-            } else if system_prompt.is_empty() {
-                return LlmResponse::error("Unknown operation type".to_string());
+    /// Check a JSON answer against the output schema. While it does not match, the model
+    /// is shown the errors and asked for a corrected answer (not streamed), which replaces
+    /// the content of `response`; usage and reasoning accumulate.
+    async fn validated_json(
+        &self,
+        provider: &dyn LlmProvider,
+        output: &JsonOutput,
+        chat_request: &ChatRequest,
+        response: &mut ChatResponse,
+    ) -> Result<serde_json::Value, String> {
+        let validator = compile_schema(&output.schema)?;
+
+        let mut repairs = 0;
+        loop {
+            let error = match parse_answer(&response.content, &validator) {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            if repairs == MAX_JSON_REPAIRS {
+                return Err(format!(
+                    "The answer still does not match the output schema after {} corrections. {}",
+                    MAX_JSON_REPAIRS, error
+                ));
             }
-        }
-
-        // Build system prompt with options
-        for (key, value) in &request.options {
-            system_prompt = system_prompt.replace(&format!("{{{}}}", key), value);
-        }
-
-        // Build user prompt
-        let user_prompt = if let Some(ref selected_text) = request.selected_text {
-            if !selected_text.is_empty() {
-                format!("{}\n\nText to process:\n{}", request.prompt, selected_text)
-            } else {
-                request.prompt.clone()
+            repairs += 1;
+            if self.config.enable_debug_logging {
+                println!(
+                    "Answer does not match the output schema, asking for a correction ({}/{}): {}",
+                    repairs, MAX_JSON_REPAIRS, error
+                );
             }
-        } else {
-            request.prompt.clone()
-        };
 
-        // Build request body
-        let body = json!({
-            "model": self.config.llm_model,
-            "messages": [
-                {"role": "system", "content": system_prompt},
-                {"role": "user", "content": user_prompt}
-            ],
-            "max_tokens": 4096,
-            "temperature": 0.6
-        });
+            let mut repair = chat_request.clone();
+            repair
+                .messages
+                .push(ChatMessage::assistant(response.content.clone()));
+            repair.messages.push(ChatMessage::user(format!(
+                "{}\n\n{}",
+                JSON_REPAIR_INSTRUCTION, error
+            )));
+
+            let part = self
+                .chat_with_continuations(provider, &repair, None)
+                .await
+                .map_err(|e| e.to_string())?;
+            response.content = part.content;
+            response.reasoning.push_str(&part.reasoning);
+            response.usage = Usage::combine(response.usage, part.usage);
+            response.truncated = part.truncated;
+        }
+    }
 
-        let url = self.build_api_url("/chat/completions");
+    /// Send a chat request to each target in turn until one answers, returning the
+    /// response and the target that produced it. With a sink the request is streamed;
+    /// once content has reached the user there is no retry or fallback.
+    async fn chat_with_fallback(
+        &self,
+        mut chat_request: ChatRequest,
+        targets: &[(&EndpointProfile, String)],
+        mut sink: Option<&mut EventStreamSink<'_>>,
+    ) -> Result<ChatAnswer, ProviderError> {
+        let mut first_error = None;
+
+        for (profile, model) in targets {
+            let provider = self.provider(profile);
+            chat_request.model = model.clone();
+
+            let result = self
+                .chat_with_continuations(provider.as_ref(), &chat_request, sink.as_deref_mut())
+                .await;
+
+            let error = match result {
+                Ok(response) => {
+                    return Ok(ChatAnswer {
+                        response,
+                        profile_id: profile.id.clone(),
+                        model: model.clone(),
+                    })
+                }
+                Err(ProviderError::Cancelled) => return Err(ProviderError::Cancelled),
+                Err(e) => e,
+            };
 
-        if self.config.enable_debug_logging {
-            println!("--- LLM Request ---");
-            println!("Config API Base URL: {}", self.config.api_base_url);
-            println!("Final URL: POST {}", url);
-            println!("Model: {}", self.config.llm_model);
-            println!(
-                "API Key (first 8 chars): {}...",
-                self.config
-                    .get_api_key()
-                    .chars()
-                    .take(8)
-                    .collect::<String>()
-            );
-            println!(
-                "Body: {}",
-                serde_json::to_string_pretty(&body).unwrap_or_default()
-            );
-            println!("-------------------");
+            if self.config.enable_debug_logging {
+                println!("Model {} ({}) failed: {}", model, profile.name, error);
+            }
+            if sink.as_ref().is_some_and(|sink| sink.emitted) {
+                return Err(error);
+            }
+            // Report the primary model's error if every fallback fails too
+            first_error.get_or_insert(error);
         }
 
-        let api_key = self.config.get_api_key();
-        if api_key.is_empty() {
-            return LlmResponse::error(
-                "API key is empty. Please configure your API key in settings.".to_string(),
-            );
+        Err(first_error.unwrap_or_else(|| {
+            ProviderError::InvalidResponse("No text model configured".to_string())
+        }))
+    }
+
+    /// Send a chat request to one target (streamed when there is a sink) and, while the
+    /// response is cut off at the token limit, ask for the rest in continuation turns.
+    /// The parts are stitched together; `truncated` stays set if the last part was cut off too.
+    async fn chat_with_continuations(
+        &self,
+        provider: &dyn LlmProvider,
+        chat_request: &ChatRequest,
+        mut sink: Option<&mut EventStreamSink<'_>>,
+    ) -> Result<ChatResponse, ProviderError> {
+        // The frontend must not see the end of the stream before the continuations
+        let held = sink.as_ref().is_some_and(|sink| sink.hold_done);
+        if let Some(sink) = sink.as_deref_mut() {
+            sink.hold_done = true;
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await;
+        let result = async {
+            let mut response = self
+                .send_chat(provider, chat_request, sink.as_deref_mut())
+                .await?;
 
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
+            let mut round = 0;
+            while response.truncated && round < self.config.max_continuations {
+                round += 1;
                 if self.config.enable_debug_logging {
-                    println!("--- LLM Response ---");
-                    println!("Status: {}", status);
+                    println!(
+                        "Response truncated at the token limit, continuing ({}/{})",
+                        round, self.config.max_continuations
+                    );
                 }
 
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-                    if self.config.enable_debug_logging {
-                        println!("Error Body: {}", error_text);
-                        println!("--------------------");
+                let mut continuation = chat_request.clone();
+                continuation
+                    .messages
+                    .push(ChatMessage::assistant(response.content.clone()));
+                continuation
+                    .messages
+                    .push(ChatMessage::user(CONTINUE_INSTRUCTION));
+
+                match self
+                    .send_chat(provider, &continuation, sink.as_deref_mut())
+                    .await
+                {
+                    Ok(part) => {
+                        response.content.push_str(&part.content);
+                        response.reasoning.push_str(&part.reasoning);
+                        response.usage = Usage::combine(response.usage, part.usage);
+                        response.truncated = part.truncated;
                     }
-                    return LlmResponse::error(format!("API Error: {}", error_text));
-                }
-
-                let text = resp.text().await.unwrap_or_default();
-                if self.config.enable_debug_logging {
-                    println!("Body: {}", text);
-                    println!("--------------------");
-                }
-
-                let json: Result<Value, _> = serde_json::from_str(&text);
-                match json {
-                    Ok(data) => {
-                        if let Some(content) = data["choices"][0]["message"]["content"].as_str() {
-                            let processed = process_llm_response(content);
-                            LlmResponse::success(processed)
-                        } else {
-                            LlmResponse::error("No content in response".to_string())
+                    Err(ProviderError::Cancelled) => return Err(ProviderError::Cancelled),
+                    // Keep what we have; the response stays flagged as truncated
+                    Err(e) => {
+                        if self.config.enable_debug_logging {
+                            println!("Continuation failed: {}", e);
                         }
+                        break;
                     }
-                    Err(e) => LlmResponse::error(format!("Failed to parse response: {}", e)),
                 }
             }
-            Err(e) => {
-                if self.config.enable_debug_logging {
-                    println!("Request Failed: {}", e);
-                    println!("--------------------");
-                }
-                LlmResponse::error(format!("Request failed: {}", e))
+            Ok(response)
+        }
+        .await;
+
+        if let Some(sink) = sink {
+            sink.hold_done = held;
+            if result.is_ok() {
+                sink.on_done();
             }
         }
+        result
     }
 
-    /// Process image generation requests
-    async fn process_image_generation(&self, request: &LlmRequest) -> LlmResponse {
-        // Check if an image model is configured
-        if self.config.image_model.is_empty() {
-            return LlmResponse::error(
-                "No Image Generation model configured. Please select an Image model in Settings > Models."
-                    .to_string(),
-            );
+    /// Send a chat request with retries, streaming it when there is a sink
+    async fn send_chat(
+        &self,
+        provider: &dyn LlmProvider,
+        chat_request: &ChatRequest,
+        sink: Option<&mut EventStreamSink<'_>>,
+    ) -> Result<ChatResponse, ProviderError> {
+        if !chat_request.tools.is_empty() {
+            return self.chat_with_tools(provider, chat_request, sink).await;
         }
+        match sink {
+            Some(sink) => self.stream_with_retries(provider, chat_request, sink).await,
+            None => self.with_retries(|| provider.chat(chat_request)).await,
+        }
+    }
+
+    /// Send a chat request that offers tools: run the tools the model calls and send
+    /// back their results until it answers, for at most `max_tool_rounds` rounds.
+    /// Rounds are not streamed; the final answer is passed to the sink in one piece.
+    async fn chat_with_tools(
+        &self,
+        provider: &dyn LlmProvider,
+        chat_request: &ChatRequest,
+        sink: Option<&mut EventStreamSink<'_>>,
+    ) -> Result<ChatResponse, ProviderError> {
+        let mut request = chat_request.clone();
+        let mut usage = None;
+        let mut round = 0;
+
+        loop {
+            if sink.as_ref().is_some_and(|sink| sink.is_cancelled()) {
+                return Err(ProviderError::Cancelled);
+            }
+            let mut response = self.with_retries(|| provider.chat(&request)).await?;
+            usage = Usage::combine(usage, response.usage);
+
+            if response.tool_calls.is_empty() {
+                response.usage = usage;
+                if let Some(sink) = sink {
+                    if !response.reasoning.is_empty() {
+                        sink.on_reasoning(&response.reasoning);
+                    }
+                    sink.on_content(&response.content);
+                }
+                return Ok(response);
+            }
 
-        let size_string = request
-            .options
-            .get("size")
-            .map(|s| s.as_str())
-            .unwrap_or("512x512");
-        let size = extract_size_dimensions(size_string);
-        let quality = request
-            .options
-            .get("quality")
-            .map(|s| s.as_str())
-            .unwrap_or("standard");
-        let style = request
-            .options
-            .get("style")
-            .map(|s| s.as_str())
-            .unwrap_or("vivid");
-
-        let model = &self.config.image_model;
-
-        let body = json!({
-            "model": model,
-            "prompt": request.prompt,
-            "size": size,
-            "quality": quality,
-            "style": style,
-            "response_format": "url",
-            "n": 1
-        });
-
-        let url = self.build_api_url("/images/generations");
+            if round == self.config.max_tool_rounds {
+                return Err(ProviderError::InvalidResponse(format!(
+                    "The model was still calling tools after {} rounds",
+                    round
+                )));
+            }
+            round += 1;
+
+            let calls = std::mem::take(&mut response.tool_calls);
+            request
+                .messages
+                .push(ChatMessage::tool_calls(response.content, calls.clone()));
+            for call in &calls {
+                if self.config.enable_debug_logging {
+                    println!(
+                        "Tool call {}/{}: {}({})",
+                        round, self.config.max_tool_rounds, call.name, call.arguments
+                    );
+                }
+                if let Some(app) = sink.as_ref().map(|sink| sink.app).or(self.app.as_ref()) {
+                    let _ = app.emit(
+                        "llm-tool-call",
+                        ToolCallEvent {
+                            request_id: self.request_id.clone(),
+                            name: call.name.clone(),
+                            arguments: call.arguments.clone(),
+                            round,
+                        },
+                    );
+                }
+
+                let output = run_call(call, &request.tools, &self.config.tool_folders).await;
+                if self.config.enable_debug_logging {
+                    println!("Tool {} returned {} characters", call.name, output.len());
+                }
+                request
+                    .messages
+                    .push(ChatMessage::tool_result(call, output));
+            }
+        }
+    }
+
+    /// Stream a chat request, retrying transient failures while nothing has been emitted
+    async fn stream_with_retries(
+        &self,
+        provider: &dyn LlmProvider,
+        chat_request: &ChatRequest,
+        sink: &mut EventStreamSink<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        let mut attempt = 0;
+        loop {
+            let error = match provider.chat_stream(chat_request, sink).await {
+                Err(e) if !sink.emitted => e,
+                result => return result,
+            };
+            self.wait_before_retry(Some(sink.app), error, attempt)
+                .await?;
+            attempt += 1;
+        }
+    }
+
+    /// Process a request whose text does not fit the context window: split the text
+    /// into chunks, process each chunk (map), then merge the results (reduce).
+    /// Progress is reported through `llm-chunk-progress` events.
+    async fn chat_in_chunks(
+        &self,
+        request: &LlmRequest,
+        chat_request: ChatRequest,
+        targets: &[(&EndpointProfile, String)],
+        context_window: u32,
+        merge: ChunkMerge,
+        mut sink: Option<&mut EventStreamSink<'_>>,
+    ) -> Result<ChatAnswer, ProviderError> {
+        // Split the selected text if there is one; the prompt is then repeated as the instruction
+        let (prefix, text) = match request.selected_text {
+            Some(ref selected_text) if !selected_text.is_empty() => (
+                format!("{}\n\nText to process:\n", request.prompt),
+                selected_text.as_str(),
+            ),
+            _ => (String::new(), request.prompt.as_str()),
+        };
+
+        let model = chat_request.model.clone();
+        let overhead = count_message_tokens(
+            [chat_request.messages[0].content.as_str(), prefix.as_str()],
+            &model,
+        );
+        let response_budget = response_budget(&chat_request.params);
+        let input_budget =
+            (max_input_tokens(context_window, response_budget) as usize).saturating_sub(overhead);
+        let chunk_budget = match merge {
+            // A translation or rewrite is about as long as its input, so it must fit the response too
+            ChunkMerge::Concatenate => input_budget.min(response_budget as usize / 2),
+            ChunkMerge::Summarize => input_budget,
+        };
+        if chunk_budget < MIN_CHUNK_TOKENS {
+            return Err(ProviderError::InvalidResponse(
+                "Prompt too long: the instructions leave no room for the text in the model's context window."
+                    .to_string(),
+            ));
+        }
 
+        let chunks = split_into_chunks(text, &model, chunk_budget);
         if self.config.enable_debug_logging {
-            println!("--- Image Generation Request ---");
-            println!("Config API Base URL: {}", self.config.api_base_url);
-            println!("Final URL: POST {}", url);
+            println!("--- Chunked Request ---");
             println!(
-                "Body: {}",
-                serde_json::to_string_pretty(&body).unwrap_or_default()
+                "{} chunks of up to {} tokens ({:?})",
+                chunks.len(),
+                chunk_budget,
+                merge
             );
-            println!("------------------------------");
+            println!("-------------------");
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.config.get_api_key()),
-            )
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
-                if self.config.enable_debug_logging {
-                    println!("--- Image Generation Response ---");
-                    println!("Status: {}", status);
+        let app = sink
+            .as_ref()
+            .map(|sink| sink.app.clone())
+            .or_else(|| self.app.clone());
+
+        match merge {
+            ChunkMerge::Concatenate => {
+                let mut targets = targets.to_vec();
+                let mut outputs = Vec::with_capacity(chunks.len());
+                let mut reasoning = Vec::new();
+                let mut usage = None;
+                let mut truncated = false;
+                let mut answered_by = (String::new(), model);
+
+                if let Some(sink) = sink.as_deref_mut() {
+                    sink.hold_done = true;
                 }
-
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-                    if self.config.enable_debug_logging {
-                        println!("Error Body: {}", error_text);
-                        println!("-------------------------------");
+                for (index, chunk) in chunks.iter().enumerate() {
+                    if sink.as_ref().is_some_and(|sink| sink.is_cancelled()) {
+                        return Err(ProviderError::Cancelled);
                     }
-                    return LlmResponse::error(format!(
-                        "Image Generation Error (status {}): {}",
-                        status.as_u16(),
-                        if error_text.is_empty() {
-                            status.canonical_reason().unwrap_or("Unknown error")
-                        } else {
-                            &error_text
+                    self.emit_chunk_progress(
+                        app.as_ref(),
+                        index + 1,
+                        chunks.len(),
+                        ChunkStage::Map,
+                    );
+                    if let Some(sink) = sink.as_deref_mut() {
+                        if index > 0 {
+                            sink.on_content("\n\n");
                         }
-                    ));
-                }
+                        // Each chunk may be retried until its own content is streamed
+                        sink.emitted = false;
+                    }
 
-                let text = resp.text().await.unwrap_or_default();
-                if self.config.enable_debug_logging {
-                    println!("Body: {}", text);
-                    println!("-------------------------------");
+                    let chunk_request = Self::chunk_request(&chat_request, &prefix, chunk);
+                    let answer = self
+                        .chat_with_fallback(chunk_request, &targets, sink.as_deref_mut())
+                        .await?;
+                    // Keep the remaining chunks on the target that answered
+                    targets.retain(|(profile, model)| {
+                        profile.id == answer.profile_id && *model == answer.model
+                    });
+                    outputs.push(answer.response.content);
+                    if !answer.response.reasoning.is_empty() {
+                        reasoning.push(answer.response.reasoning);
+                    }
+                    usage = Usage::combine(usage, answer.response.usage);
+                    truncated |= answer.response.truncated;
+                    answered_by = (answer.profile_id, answer.model);
+                }
+                if let Some(sink) = sink {
+                    sink.hold_done = false;
+                    sink.on_done();
                 }
 
-                let json: Result<Value, _> = serde_json::from_str(&text);
-                match json {
-                    Ok(data) => {
-                        if let Some(url) = data["data"][0]["url"].as_str() {
-                            LlmResponse::image(url.to_string())
-                        } else {
-                            LlmResponse::error("No image URL in response".to_string())
-                        }
-                    }
-                    Err(e) => LlmResponse::error(format!("Failed to parse response: {}", e)),
+                let (profile_id, model) = answered_by;
+                Ok(ChatAnswer {
+                    response: ChatResponse {
+                        content: outputs.join("\n\n"),
+                        reasoning: reasoning.join("\n\n"),
+                        usage,
+                        truncated,
+                        tool_calls: Vec::new(),
+                    },
+                    profile_id,
+                    model,
+                })
+            }
+            ChunkMerge::Summarize => {
+                let (summaries, map_usage) = self
+                    .map_chunks(
+                        &chat_request,
+                        &prefix,
+                        &chunks,
+                        targets,
+                        ChunkStage::Map,
+                        sink.as_deref(),
+                    )
+                    .await?;
+
+                let reduce_prefix = format!("{}\n\n", REDUCE_INSTRUCTION);
+                let (chat_request_ref, reduce_prefix_ref) = (&chat_request, &reduce_prefix);
+                let progress_sink = sink.as_deref();
+                let (combined, reduce_usage) = Self::reduce_summaries(
+                    summaries,
+                    &reduce_prefix,
+                    &model,
+                    input_budget,
+                    chunk_budget,
+                    |groups| async move {
+                        self.map_chunks(
+                            chat_request_ref,
+                            reduce_prefix_ref,
+                            &groups,
+                            targets,
+                            ChunkStage::Reduce,
+                            progress_sink,
+                        )
+                        .await
+                    },
+                )
+                .await?;
+
+                self.emit_chunk_progress(app.as_ref(), 1, 1, ChunkStage::Reduce);
+                let reduce_request = Self::chunk_request(&chat_request, &reduce_prefix, &combined);
+                let mut answer = self
+                    .chat_with_fallback(reduce_request, targets, sink)
+                    .await?;
+                answer.response.usage = Usage::combine(
+                    Usage::combine(map_usage, reduce_usage),
+                    answer.response.usage,
+                );
+                Ok(answer)
+            }
+        }
+    }
+
+    /// Summarize groups of summaries, one round at a time, until they fit in
+    /// `input_budget` tokens after `prefix` and return them joined. Fails when they
+    /// still do not fit after `MAX_REDUCE_ROUNDS`, or when a round does not shorten them,
+    /// rather than sending a final request the model cannot take.
+    async fn reduce_summaries<F, Fut>(
+        summaries: Vec<String>,
+        prefix: &str,
+        model: &str,
+        input_budget: usize,
+        chunk_budget: usize,
+        mut summarize: F,
+    ) -> Result<(String, Option<Usage>), ProviderError>
+    where
+        F: FnMut(Vec<String>) -> Fut,
+        Fut: Future<Output = Result<(Vec<String>, Option<Usage>), ProviderError>>,
+    {
+        let prefix_tokens = count_tokens(prefix, model);
+        let mut combined = summaries.join("\n\n");
+        let mut tokens = count_tokens(&combined, model);
+        let mut usage = None;
+        let mut round = 0;
+        while tokens + prefix_tokens > input_budget {
+            if round == MAX_REDUCE_ROUNDS {
+                return Err(ProviderError::InvalidResponse(format!(
+                    "The text is too long to summarize: the partial summaries still do not fit the model's context window after {} rounds.",
+                    MAX_REDUCE_ROUNDS
+                )));
+            }
+
+            let groups = split_into_chunks(&combined, model, chunk_budget);
+            let (round_summaries, round_usage) = summarize(groups).await?;
+            usage = Usage::combine(usage, round_usage);
+            combined = round_summaries.join("\n\n");
+
+            let round_tokens = count_tokens(&combined, model);
+            if round_tokens >= tokens {
+                return Err(ProviderError::InvalidResponse(
+                    "The text is too long to summarize: summarizing the partial summaries does not make them shorter."
+                        .to_string(),
+                ));
+            }
+            tokens = round_tokens;
+            round += 1;
+        }
+        Ok((combined, usage))
+    }
+
+    /// Send every chunk without streaming and return the processed outputs in order,
+    /// with their combined usage. The sink (if any) is only used for cancellation and
+    /// progress events.
+    async fn map_chunks(
+        &self,
+        chat_request: &ChatRequest,
+        prefix: &str,
+        chunks: &[String],
+        targets: &[(&EndpointProfile, String)],
+        stage: ChunkStage,
+        sink: Option<&EventStreamSink<'_>>,
+    ) -> Result<(Vec<String>, Option<Usage>), ProviderError> {
+        let app = sink.map(|sink| sink.app).or(self.app.as_ref());
+        let mut outputs = Vec::with_capacity(chunks.len());
+        let mut usage = None;
+        for (index, chunk) in chunks.iter().enumerate() {
+            if sink.is_some_and(|sink| sink.is_cancelled()) {
+                return Err(ProviderError::Cancelled);
+            }
+            self.emit_chunk_progress(app, index + 1, chunks.len(), stage);
+
+            let chunk_request = Self::chunk_request(chat_request, prefix, chunk);
+            let answer = self
+                .chat_with_fallback(chunk_request, targets, None)
+                .await?;
+            outputs.push(process_llm_response(&answer.response.content));
+            usage = Usage::combine(usage, answer.response.usage);
+        }
+        Ok((outputs, usage))
+    }
+
+    /// Copy of a chat request whose user message is `prefix` followed by a chunk of text
+    fn chunk_request(chat_request: &ChatRequest, prefix: &str, chunk: &str) -> ChatRequest {
+        let mut chunk_request = chat_request.clone();
+        chunk_request.messages = vec![
+            chat_request.messages[0].clone(),
+            ChatMessage::user(format!("{}{}", prefix, chunk)),
+        ];
+        chunk_request
+    }
+
+    /// Report chunked processing progress to the frontend
+    fn emit_chunk_progress(
+        &self,
+        app: Option<&AppHandle>,
+        current: usize,
+        total: usize,
+        stage: ChunkStage,
+    ) {
+        if self.config.enable_debug_logging {
+            println!("Chunk {}/{} ({:?})", current, total, stage);
+        }
+        if let Some(app) = app {
+            let _ = app.emit(
+                "llm-chunk-progress",
+                ChunkProgress {
+                    request_id: self.request_id.clone(),
+                    current,
+                    total,
+                    stage,
+                },
+            );
+        }
+    }
+
+    /// How a request too long for the context window is processed in chunks, if it can be.
+    /// Requests with images or attached files are never split, as every chunk would need them.
+    fn chunk_merge(request: &LlmRequest) -> Option<ChunkMerge> {
+        ChunkMerge::for_operation(&request.operation_type).filter(|_| {
+            request.images.is_empty()
+                && request.attachments.is_empty()
+                && request.thread_id.is_none()
+        })
+    }
+
+    /// Error for a prompt over the context limit, pointing at the attached files if there are any
+    fn prompt_too_long(request: &LlmRequest, error: String) -> String {
+        if request.attachments.is_empty() {
+            error
+        } else {
+            format!("{} Attach fewer or shorter files.", error)
+        }
+    }
+
+    /// Resolve the system prompt (built-in operation or custom task) and build the chat request.
+    /// Generation parameters in `request.options` override those of the operation.
+    /// A follow-up keeps the system prompt of its thread and sends the thread's exchanges first.
+    /// Attached files and images are loaded here; the clipboard needs an app handle.
+    async fn build_chat_request(
+        &self,
+        request: &LlmRequest,
+        thread: Option<&Thread>,
+        app: Option<&AppHandle>,
+    ) -> Result<ChatRequest, String> {
+        let task = TaskDefinition::resolve(&request.operation_type)
+            .filter(|task| !task.system_prompt.is_empty())
+            .ok_or_else(|| "Unknown operation type".to_string())?;
+
+        // Build system prompt with options
+        let mut system_prompt = task.system_prompt;
+        for (key, value) in &request.options {
+            system_prompt = system_prompt.replace(&format!("{{{}}}", key), value);
+        }
+
+        // Options the operation defines itself are never read as parameter overrides
+        let overrides: HashMap<String, String> = request
+            .options
+            .iter()
+            .filter(|(key, _)| !task.option_keys.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let params = task
+            .params
+            .merged(&GenerationParams::from_options(&overrides)?);
+
+        let mut user_prompt = Self::user_text(request);
+
+        // Attached files follow the prompt as labelled blocks
+        let attachments = load_attachments(&request.attachments).await?;
+        if !attachments.is_empty() {
+            if self.config.enable_debug_logging {
+                println!("Attached {} file(s) to the request", attachments.len());
+            }
+            user_prompt = format!("{}\n\n{}", user_prompt, attachment_blocks(&attachments));
+        }
+
+        let images = load_images(&request.images, app).await?;
+        if self.config.enable_debug_logging && !images.is_empty() {
+            println!("Attached {} image(s) to the request", images.len());
+        }
+
+        let mut messages = match thread {
+            Some(thread) => {
+                let mut messages = vec![ChatMessage::system(thread.system_prompt())];
+                messages.extend(thread.history());
+                messages
+            }
+            None => vec![ChatMessage::system(system_prompt)],
+        };
+        messages.push(ChatMessage::user(user_prompt).with_images(images));
+
+        Ok(ChatRequest {
+            model: self.config.llm_model.clone(),
+            messages,
+            params,
+            response_schema: task
+                .json_output
+                .map(|output| ResponseSchema::new(&task.name, output.schema)),
+            tools: task.tools.iter().map(|tool| tool.definition()).collect(),
+        })
+    }
+
+    /// The prompt followed by the selected text, if any
+    fn user_text(request: &LlmRequest) -> String {
+        match request.selected_text {
+            Some(ref selected_text) if !selected_text.is_empty() => {
+                format!("{}\n\nText to process:\n{}", request.prompt, selected_text)
+            }
+            _ => request.prompt.clone(),
+        }
+    }
+
+    /// Process text-based requests (chat completions)
+    async fn process_text_request(&self, request: &LlmRequest) -> LlmResponse {
+        let thread = match Self::load_thread(request).await {
+            Ok(thread) => thread,
+            Err(e) => return LlmResponse::error(e),
+        };
+        let mut chat_request = match self
+            .build_chat_request(request, thread.as_ref(), self.app.as_ref())
+            .await
+        {
+            Ok(chat_request) => chat_request,
+            Err(e) => return LlmResponse::error(e),
+        };
+
+        let targets = match self.text_targets(&request.operation_type).await {
+            Ok(targets) => targets,
+            Err(e) => return LlmResponse::error(e),
+        };
+
+        // Validate prompt length against the primary model (defense in depth).
+        // Long inputs of some operations are processed in chunks instead.
+        let context_window = self.context_window(targets[0].0, &chat_request.model);
+        if thread.is_some() {
+            self.trim_thread(&mut chat_request, context_window);
+        }
+        let chunking = match validate_prompt_length(&chat_request, context_window) {
+            Ok(()) => None,
+            Err(e) => match Self::chunk_merge(request) {
+                Some(merge) => Some(merge),
+                None => return LlmResponse::error(Self::prompt_too_long(request, e)),
+            },
+        };
+
+        // Answers of tasks with a JSON output schema are validated against it afterwards
+        let structured = chat_request
+            .response_schema
+            .is_some()
+            .then(|| chat_request.clone());
+        let exchange = Self::exchange(request, thread, &chat_request, chunking.is_some());
+
+        let result = match chunking {
+            Some(merge) => {
+                self.chat_in_chunks(request, chat_request, &targets, context_window, merge, None)
+                    .await
+            }
+            None => self.chat_with_fallback(chat_request, &targets, None).await,
+        };
+
+        let response = match result {
+            Ok(answer) => match structured {
+                Some(chat_request) => {
+                    self.json_response(&request.operation_type, chat_request, &targets, answer)
+                        .await
                 }
+                None => self.text_response(&request.operation_type, answer).await,
+            },
+            Err(e) => LlmResponse::error(e.to_string()),
+        };
+        self.record_exchange(exchange, response).await
+    }
+
+    /// Process image generation requests: a new image from the prompt, or (by the `mode`
+    /// option) an edit or a variation of the first attached image
+    async fn process_image_generation(&self, request: &LlmRequest) -> LlmResponse {
+        // Check if an image model is configured
+        if self.config.image_model.is_empty() {
+            return LlmResponse::error(
+                "No Image Generation model configured. Please select an Image model in Settings > Models."
+                    .to_string(),
+            );
+        }
+
+        // Options saved for another image model fall back to what this one accepts
+        let capabilities = ImageCapabilities::for_model(&self.config.image_model);
+        let size_string = request
+            .options
+            .get("size")
+            .map(|s| s.as_str())
+            .unwrap_or(capabilities.default_size);
+        let size = capabilities.size(&extract_size_dimensions(size_string));
+        let mode = request
+            .options
+            .get("mode")
+            .map(|s| s.as_str())
+            .unwrap_or("generate");
+        let n = request
+            .options
+            .get("n")
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or(1)
+            .clamp(1, capabilities.max_images);
+
+        if !capabilities.modes().contains(&mode) {
+            return LlmResponse::error(format!(
+                "{} does not support image {}s. Choose another mode or image model.",
+                self.config.image_model, mode
+            ));
+        }
+
+        let provider = match self.provider_for(&request.operation_type, ModelSlot::Image) {
+            Ok(provider) => provider,
+            Err(e) => return LlmResponse::error(e),
+        };
+
+        let params = ImageParams {
+            capabilities,
+            size,
+            n,
+        };
+        let result = match mode {
+            "edit" => self.edit_image(request, provider.as_ref(), params).await,
+            "variation" => self.vary_image(request, provider.as_ref(), params).await,
+            _ => {
+                self.generate_image(request, provider.as_ref(), params)
+                    .await
             }
-            Err(e) => {
+        };
+        match result.and_then(|response| self.save_images(&response.images)) {
+            Ok(paths) => LlmResponse::images(paths).with_model(&self.config.image_model),
+            Err(e) => LlmResponse::error(e),
+        }
+    }
+
+    /// Save generated images to the media folder, returning their paths
+    fn save_images(&self, images: &[Vec<u8>]) -> Result<Vec<String>, String> {
+        images
+            .iter()
+            .map(|bytes| {
+                let format = match image::guess_format(bytes) {
+                    Ok(ImageFormat::Jpeg) => "jpg",
+                    Ok(ImageFormat::WebP) => "webp",
+                    Ok(ImageFormat::Gif) => "gif",
+                    _ => "png",
+                };
+                let path = HistoryManager::save_image(bytes, format)?;
                 if self.config.enable_debug_logging {
-                    println!("Request Failed: {}", e);
-                    println!("-------------------------------");
+                    println!("Saved image to: {}", path);
                 }
-                LlmResponse::error(format!("Request failed: {}", e))
-            }
+                Ok(path)
+            })
+            .collect()
+    }
+
+    async fn generate_image(
+        &self,
+        request: &LlmRequest,
+        provider: &dyn LlmProvider,
+        params: ImageParams,
+    ) -> Result<ImageResponse, String> {
+        let option = |key: &str| request.options.get(key).map(|s| s.as_str());
+
+        let image_request = ImageRequest {
+            model: self.config.image_model.clone(),
+            prompt: request.prompt.clone(),
+            size: params.size.clone(),
+            quality: params.capabilities.quality(option("quality")),
+            style: params.capabilities.style(option("style")),
+            n: params.n,
+            response_format: params.response_format(),
+        };
+
+        self.with_retries(|| provider.generate_image(&image_request))
+            .await
+            .map_err(|e| e.describe("Image Generation Error"))
+    }
+
+    async fn edit_image(
+        &self,
+        request: &LlmRequest,
+        provider: &dyn LlmProvider,
+        params: ImageParams,
+    ) -> Result<ImageResponse, String> {
+        if request.prompt.trim().is_empty() {
+            return Err("Describe the change to make to the image".to_string());
         }
+        let (image, mask) = self.input_image(request, request.mask.as_ref()).await?;
+
+        let edit_request = ImageEditRequest {
+            model: self.config.image_model.clone(),
+            prompt: request.prompt.clone(),
+            image,
+            mask,
+            size: params.size.clone(),
+            n: params.n,
+            response_format: params.response_format(),
+        };
+
+        self.with_retries(|| provider.edit_image(&edit_request))
+            .await
+            .map_err(|e| e.describe("Image Edit Error"))
+    }
+
+    async fn vary_image(
+        &self,
+        request: &LlmRequest,
+        provider: &dyn LlmProvider,
+        params: ImageParams,
+    ) -> Result<ImageResponse, String> {
+        let (image, _) = self.input_image(request, None).await?;
+
+        let variation_request = ImageVariationRequest {
+            model: self.config.image_model.clone(),
+            image,
+            size: params.size.clone(),
+            n: params.n,
+            response_format: params.response_format(),
+        };
+
+        self.with_retries(|| provider.vary_image(&variation_request))
+            .await
+            .map_err(|e| e.describe("Image Variation Error"))
+    }
+
+    /// The input image of an edit or variation (and the mask of an edit), as PNG
+    async fn input_image(
+        &self,
+        request: &LlmRequest,
+        mask: Option<&ImageInput>,
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), String> {
+        let image = request
+            .images
+            .first()
+            .ok_or_else(|| "Attach the image to edit or vary".to_string())?;
+        let (image, mask) = load_edit_images(image, mask, self.app.as_ref()).await?;
+        if self.config.enable_debug_logging {
+            println!(
+                "Input image: {} bytes, mask: {}",
+                image.len(),
+                mask.as_ref().map_or(0, |mask| mask.len())
+            );
+        }
+        Ok((image, mask))
     }
 
     /// Process speech-to-text (audio transcription) requests
@@ -683,13 +1890,30 @@ impl LlmService {
             );
         }
 
-        let model = &self.config.audio_model;
-
         let language = request
             .options
             .get("language")
             .map(|s| s.as_str())
-            .unwrap_or("auto");
+            .filter(|language| *language != "auto" && !language.is_empty())
+            .map(|language| language.to_string());
+
+        let provider = match self.provider_for(&request.operation_type, ModelSlot::Audio) {
+            Ok(provider) => provider,
+            Err(e) => return LlmResponse::error(e),
+        };
+
+        // Files over the upload limit are split into segments
+        let file_size = std::fs::metadata(audio_path).map_or(0, |m| m.len());
+        if file_size > MAX_UPLOAD_BYTES {
+            return match self
+                .transcribe_in_segments(audio_path, language, provider.as_ref())
+                .await
+            {
+                Ok(transcript) => LlmResponse::success(normalize_transcription(&transcript))
+                    .with_model(&self.config.audio_model),
+                Err(e) => LlmResponse::error(e),
+            };
+        }
 
         // Read file for multipart upload
         let file_bytes = match std::fs::read(audio_path) {
@@ -703,93 +1927,87 @@ impl LlmService {
             .unwrap_or("audio.mp3")
             .to_string();
 
-        let file_part = multipart::Part::bytes(file_bytes)
-            .file_name(file_name)
-            .mime_str("audio/mpeg")
-            .unwrap();
-
-        let mut form = multipart::Form::new()
-            .part("file", file_part)
-            .text("model", model.to_string())
-            .text("response_format", "text");
+        let transcription_request = TranscriptionRequest {
+            model: self.config.audio_model.clone(),
+            file_bytes,
+            file_name,
+            language,
+        };
 
-        if language != "auto" && !language.is_empty() {
-            form = form.text("language", language.to_string());
+        match self
+            .with_retries(|| provider.transcribe(&transcription_request))
+            .await
+        {
+            Ok(transcript) => LlmResponse::success(normalize_transcription(&transcript))
+                .with_model(&self.config.audio_model),
+            Err(e) => LlmResponse::error(e.describe("Transcription Error")),
         }
+    }
 
-        let url = self.build_api_url("/audio/transcriptions");
+    /// Transcribe a file over the upload limit: decode it, split it into segments (at
+    /// pauses where possible), transcribe a few segments at a time and join the texts in
+    /// order. Progress is reported through `llm-chunk-progress` events.
+    async fn transcribe_in_segments(
+        &self,
+        audio_path: &str,
+        language: Option<String>,
+        provider: &dyn LlmProvider,
+    ) -> Result<String, String> {
+        // The whole recording is held as 16 kHz samples (about 115 MB per hour)
+        let path = audio_path.to_string();
+        let cancel = self.cancel.clone();
+        let samples = tokio::task::spawn_blocking(move || decode_mono(Path::new(&path), &cancel))
+            .await
+            .map_err(|e| format!("Failed to decode audio: {}", e))??;
 
+        let segments = split_segments(&samples, (MAX_SEGMENT_SECONDS * SAMPLE_RATE) as usize);
+        let total = segments.len();
         if self.config.enable_debug_logging {
-            println!("--- Speech to Text Request ---");
-            println!("Config API Base URL: {}", self.config.api_base_url);
-            println!("Final URL: POST {}", url);
-            println!("Model: {}", model);
-            println!("(Multipart form data not logged)");
-            println!("------------------------------");
-        }
-
-        let response = self
-            .client
-            .post(&url)
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.config.get_api_key()),
-            )
-            .multipart(form)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
-                if self.config.enable_debug_logging {
-                    println!("--- Speech to Text Response ---");
-                    println!("Status: {}", status);
-                }
-
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-                    if self.config.enable_debug_logging {
-                        println!("Error Body: {}", error_text);
-                        println!("-------------------------------");
-                    }
-                    return LlmResponse::error(format!(
-                        "Transcription Error (status {}): {}",
-                        status.as_u16(),
-                        if error_text.is_empty() {
-                            status.canonical_reason().unwrap_or("Unknown error")
-                        } else {
-                            &error_text
-                        }
-                    ));
-                }
-
-                let text = resp.text().await.unwrap_or_default();
-                if self.config.enable_debug_logging {
-                    println!("Body: {}", text);
-                    println!("-------------------------------");
-                }
-
-                // Try to parse as JSON first (some APIs return JSON)
-                if let Ok(json) = serde_json::from_str::<Value>(&text) {
-                    if let Some(transcript) = json["text"].as_str() {
-                        let normalized = normalize_transcription(transcript);
-                        return LlmResponse::success(normalized);
-                    }
-                }
-
-                // Otherwise treat as plain text
-                let normalized = normalize_transcription(&text);
-                LlmResponse::success(normalized)
-            }
-            Err(e) => {
-                if self.config.enable_debug_logging {
-                    println!("Request Failed: {}", e);
-                    println!("-------------------------------");
+            println!(
+                "Transcribing {} in {} segments ({} s of audio)",
+                audio_path,
+                total,
+                samples.len() / SAMPLE_RATE as usize
+            );
+        }
+        self.emit_chunk_progress(self.app.as_ref(), 0, total, ChunkStage::Transcribe);
+
+        // WAV files are encoded as segments are started, not all up front
+        let samples = &samples;
+        let language = &language;
+        let mut transcriptions = stream::iter(segments.into_iter().enumerate())
+            .map(|(index, range)| async move {
+                // Segments not started yet are never uploaded once the request is cancelled
+                if self.cancel.is_cancelled() {
+                    return (index, Err(ProviderError::Cancelled));
                 }
-                LlmResponse::error(format!("Request failed: {}", e))
-            }
+                let transcription_request = TranscriptionRequest {
+                    model: self.config.audio_model.clone(),
+                    file_bytes: encode_wav(&samples[range]),
+                    file_name: format!("part-{}.wav", index + 1),
+                    language: language.clone(),
+                };
+                let result = self
+                    .with_retries(|| provider.transcribe(&transcription_request))
+                    .await;
+                (index, result)
+            })
+            .buffer_unordered(MAX_PARALLEL_TRANSCRIPTIONS);
+
+        let mut parts = vec![String::new(); total];
+        let mut done = 0;
+        while let Some((index, result)) = transcriptions.next().await {
+            parts[index] = result.map_err(|e| {
+                e.describe(&format!(
+                    "Transcription Error (part {}/{})",
+                    index + 1,
+                    total
+                ))
+            })?;
+            done += 1;
+            self.emit_chunk_progress(self.app.as_ref(), done, total, ChunkStage::Transcribe);
         }
+        Ok(parts.join(" "))
     }
 
     /// Process text-to-speech requests
@@ -834,152 +2052,304 @@ impl LlmService {
             .map(|s| s.as_str())
             .unwrap_or("pt");
 
-        let body = json!({
-            "model": model,
-            "input": request.prompt,
-            "voice": voice,
-            "response_format": format,
-            "speed": speed,
-            "language": language
-        });
-
-        let url = self.build_api_url("/audio/speech");
+        let speech_request = SpeechRequest {
+            model: model.to_string(),
+            input: request.prompt.clone(),
+            voice: voice.to_string(),
+            format: format.to_string(),
+            speed,
+            language: language.to_string(),
+        };
 
-        if self.config.enable_debug_logging {
-            println!("--- Text to Speech Request ---");
-            println!("Config API Base URL: {}", self.config.api_base_url);
-            println!("Final URL: POST {}", url);
-            println!(
-                "Body: {}",
-                serde_json::to_string_pretty(&body).unwrap_or_default()
-            );
-            println!("------------------------------");
-        }
+        let provider = match self.provider_for(&request.operation_type, ModelSlot::Tts) {
+            Ok(provider) => provider,
+            Err(e) => return LlmResponse::error(e),
+        };
 
-        let response = self
-            .client
-            .post(&url)
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.config.get_api_key()),
-            )
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await;
+        let bytes = match self.with_retries(|| provider.speech(&speech_request)).await {
+            Ok(bytes) => bytes,
+            Err(e) => return LlmResponse::error(e.describe("TTS Error")),
+        };
 
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
+        // Save audio to media folder and return file path
+        match HistoryManager::save_audio(&bytes, format) {
+            Ok(file_path) => {
                 if self.config.enable_debug_logging {
-                    println!("--- Text to Speech Response ---");
-                    println!("Status: {}", status);
-                }
-
-                if !status.is_success() {
-                    let error_text = resp.text().await.unwrap_or_default();
-                    if self.config.enable_debug_logging {
-                        println!("Error Body: {}", error_text);
-                        println!("-------------------------------");
-                    }
-                    return LlmResponse::error(format!(
-                        "TTS Error (status {}): {}",
-                        status.as_u16(),
-                        if error_text.is_empty() {
-                            status.canonical_reason().unwrap_or("Unknown error")
-                        } else {
-                            &error_text
-                        }
-                    ));
-                }
-
-                match resp.bytes().await {
-                    Ok(bytes) => {
-                        if self.config.enable_debug_logging {
-                            println!("Received {} bytes of audio data", bytes.len());
-                            println!("-------------------------------");
-                        }
-                        // Save audio to media folder and return file path
-                        match HistoryManager::save_audio(&bytes, format) {
-                            Ok(file_path) => {
-                                if self.config.enable_debug_logging {
-                                    println!("Saved audio to: {}", file_path);
-                                }
-                                LlmResponse::audio(file_path, format.to_string())
-                            }
-                            Err(e) => {
-                                if self.config.enable_debug_logging {
-                                    println!("Failed to save audio: {}", e);
-                                }
-                                LlmResponse::error(format!("Failed to save audio: {}", e))
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        if self.config.enable_debug_logging {
-                            println!("Failed to read audio data: {}", e);
-                            println!("-------------------------------");
-                        }
-                        LlmResponse::error(format!("Failed to read audio data: {}", e))
-                    }
+                    println!("Saved audio to: {}", file_path);
                 }
+                LlmResponse::audio(file_path, format.to_string()).with_model(model)
             }
             Err(e) => {
                 if self.config.enable_debug_logging {
-                    println!("Request Failed: {}", e);
-                    println!("-------------------------------");
+                    println!("Failed to save audio: {}", e);
                 }
-                LlmResponse::error(format!("Request failed: {}", e))
+                LlmResponse::error(format!("Failed to save audio: {}", e))
             }
         }
     }
 
-    /// Fetch available models from API
-    pub async fn get_models(&self) -> Result<ModelsResponse, String> {
-        let url = self.build_api_url("/models");
+    /// Fetch available models from a profile (the default profile if `profile_id` is None)
+    pub async fn get_models(&self, profile_id: Option<&str>) -> Result<Vec<String>, String> {
+        let profile = self.config.profile_or_default(profile_id)?;
+        self.provider(profile)
+            .list_models()
+            .await
+            .map_err(|e| match e {
+                ProviderError::Api { status, body, .. } => {
+                    format!("Failed to fetch models ({}): {}", status, body)
+                }
+                other => other.to_string(),
+            })
+    }
 
-        if self.config.enable_debug_logging {
-            println!("--- Fetching Models ---");
-            println!("Config API Base URL: {}", self.config.api_base_url);
-            println!("Final URL: GET {}", url);
-            println!("-----------------------");
-        }
-
-        let response = self
-            .client
-            .get(&url)
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.config.get_api_key()),
-            )
-            .send()
+    /// Test API connection
+    pub async fn test_connection(&self, profile_id: Option<&str>) -> Result<(), String> {
+        self.get_models(profile_id).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Provider answering with scripted responses and recording the requests it receives
+    struct ScriptedProvider {
+        responses: Mutex<Vec<ChatResponse>>,
+        requests: Mutex<Vec<ChatRequest>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(parts: &[(&str, bool)]) -> Self {
+            let responses = parts
+                .iter()
+                .map(|(content, truncated)| ChatResponse {
+                    content: content.to_string(),
+                    reasoning: String::new(),
+                    usage: Some(Usage::new(10, 5)),
+                    truncated: *truncated,
+                    tool_calls: Vec::new(),
+                })
+                .collect();
+            Self {
+                responses: Mutex::new(responses),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for ScriptedProvider {
+        fn name(&self) -> &'static str {
+            "Scripted"
+        }
+
+        async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+
+        async fn chat_stream(
+            &self,
+            request: &ChatRequest,
+            _sink: &mut dyn StreamSink,
+        ) -> Result<ChatResponse, ProviderError> {
+            self.chat(request).await
+        }
+
+        async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn chat_request() -> ChatRequest {
+        ChatRequest {
+            model: "gpt-4o-mini".to_string(),
+            messages: vec![
+                ChatMessage::system("Translate to Portuguese."),
+                ChatMessage::user("A long document"),
+            ],
+            params: GenerationParams::default(),
+            response_schema: None,
+            tools: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncated_response_is_continued_and_stitched() {
+        let service = LlmService::new(Configuration {
+            max_continuations: 2,
+            ..Default::default()
+        });
+        let provider = ScriptedProvider::new(&[
+            ("Part one, ", true),
+            ("part two, ", true),
+            ("part three", true),
+        ]);
+
+        let response = service
+            .chat_with_continuations(&provider, &chat_request(), None)
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .unwrap();
+        assert_eq!(response.content, "Part one, part two, part three");
+        assert!(response.truncated, "still cut off after the last round");
+        assert_eq!(response.usage, Some(Usage::new(30, 15)));
+
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let last = &requests[2].messages;
+        assert_eq!(last.len(), 4);
+        assert_eq!(last[2].role, "assistant");
+        assert_eq!(last[2].content, "Part one, part two, ");
+        assert_eq!(last[3].content, CONTINUE_INSTRUCTION);
+    }
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            if self.config.enable_debug_logging {
-                println!("--- Models Response Error ---");
-                println!("Status: {}", status);
-                println!("Error: {}", error_text);
-                println!("-----------------------------");
-            }
-            return Err(format!(
-                "Failed to fetch models ({}): {}",
-                status, error_text
-            ));
+    #[tokio::test]
+    async fn test_invalid_json_is_sent_back_for_correction() {
+        let service = LlmService::new(Configuration::default());
+        let output = JsonOutput {
+            schema: json!({
+                "type": "object",
+                "properties": {"due": {"type": "string"}},
+                "required": ["due"]
+            }),
+            template: None,
+        };
+        let first_answer = || ChatResponse {
+            content: "```json\n{\"due\": 5}\n```".to_string(),
+            reasoning: String::new(),
+            usage: Some(Usage::new(10, 5)),
+            truncated: false,
+            tool_calls: Vec::new(),
+        };
+
+        let provider = ScriptedProvider::new(&[
+            (r#"{"date": "Friday"}"#, false),
+            (r#"{"due": "Friday"}"#, false),
+        ]);
+        let mut response = first_answer();
+        let value = service
+            .validated_json(&provider, &output, &chat_request(), &mut response)
+            .await
+            .unwrap();
+        assert_eq!(value, json!({"due": "Friday"}));
+        assert_eq!(response.usage, Some(Usage::new(30, 15)));
+
+        {
+            let requests = provider.requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            let first = &requests[0].messages;
+            assert_eq!(first[2].content, "```json\n{\"due\": 5}\n```");
+            assert!(first[3].content.starts_with(JSON_REPAIR_INSTRUCTION));
+            assert!(first[3].content.contains("/due: 5 is not of type"));
+            // Each correction replaces the previous answer instead of piling up turns
+            assert_eq!(requests[1].messages.len(), 4);
+            assert_eq!(requests[1].messages[2].content, r#"{"date": "Friday"}"#);
         }
 
-        response
-            .json::<ModelsResponse>()
+        let provider = ScriptedProvider::new(&[("{}", false), ("{}", false)]);
+        let error = service
+            .validated_json(&provider, &output, &chat_request(), &mut first_answer())
             .await
-            .map_err(|e| format!("Failed to parse models: {}", e))
+            .unwrap_err();
+        assert!(error.contains("after 2 corrections"), "{}", error);
     }
 
-    /// Test API connection
-    pub async fn test_connection(&self) -> Result<(), String> {
-        self.get_models().await?;
-        Ok(())
+    #[tokio::test]
+    async fn test_tool_calls_are_run_until_the_model_answers() {
+        let service = LlmService::new(Configuration {
+            max_tool_rounds: 1,
+            ..Default::default()
+        });
+        let calculation = || ChatResponse {
+            tool_calls: vec![crate::providers::ToolCall {
+                id: "call_0".to_string(),
+                name: "calculator".to_string(),
+                arguments: json!({"expression": "19 * 23"}),
+                signature: None,
+            }],
+            usage: Some(Usage::new(10, 5)),
+            ..Default::default()
+        };
+        let mut request = chat_request();
+        request.tools = vec![Tool::Calculator.definition()];
+
+        let provider = ScriptedProvider::new(&[("19 × 23 = 437", false)]);
+        provider.responses.lock().unwrap().insert(0, calculation());
+        let response = service
+            .chat_with_continuations(&provider, &request, None)
+            .await
+            .unwrap();
+        assert_eq!(response.content, "19 × 23 = 437");
+        assert_eq!(response.usage, Some(Usage::new(20, 10)));
+        {
+            let requests = provider.requests.lock().unwrap();
+            let last = &requests[1].messages;
+            assert_eq!(last.len(), 4);
+            assert_eq!(last[2].tool_calls[0].name, "calculator");
+            assert_eq!(last[3].role, "tool");
+            assert_eq!(last[3].content, "437");
+        }
+
+        // The model keeps calling tools past the cap
+        let provider = ScriptedProvider::new(&[]);
+        provider
+            .responses
+            .lock()
+            .unwrap()
+            .extend([calculation(), calculation()]);
+        let error = service
+            .chat_with_continuations(&provider, &request, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("after 1 rounds"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_summaries_that_do_not_shrink_are_not_sent() {
+        let long_summary = "The report covers many separate topics in detail. ".repeat(8);
+        let provider = ScriptedProvider::new(&[(long_summary.as_str(), false); 8]);
+        let summaries = vec![long_summary.clone(), long_summary.clone()];
+        let groups = split_into_chunks(&summaries.join("\n\n"), "gpt-4o-mini", 100).len();
+
+        let provider_ref = &provider;
+        let result = LlmService::reduce_summaries(
+            summaries,
+            "Combine these summaries.\n\n",
+            "gpt-4o-mini",
+            100,
+            100,
+            |groups| async move {
+                let mut outputs = Vec::new();
+                for group in groups {
+                    let mut request = chat_request();
+                    request.messages[1] = ChatMessage::user(group);
+                    outputs.push(provider_ref.chat(&request).await?.content);
+                }
+                Ok((outputs, None))
+            },
+        )
+        .await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("does not make them shorter"), "{}", error);
+        // One round was tried, and no final request was sent
+        assert_eq!(provider.requests.lock().unwrap().len(), groups);
+    }
+
+    #[tokio::test]
+    async fn test_complete_response_is_not_continued() {
+        let service = LlmService::new(Configuration::default());
+        let provider = ScriptedProvider::new(&[("Done.", false)]);
+
+        let response = service
+            .chat_with_continuations(&provider, &chat_request(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Done.");
+        assert!(!response.truncated);
+        assert_eq!(provider.requests.lock().unwrap().len(), 1);
     }
 }
@@ -4,6 +4,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::image_models::{size_label, ImageCapabilities};
+use crate::providers::GenerationParams;
+
 /// Operation type enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -52,6 +55,9 @@ pub struct Operation {
     pub description: String,
     pub system_prompt: String,
     pub options: Vec<OperationOption>,
+    /// Generation parameters; unset fields are not sent to the provider
+    #[serde(default)]
+    pub params: GenerationParams,
 }
 
 /// Get default system prompts for all operation types
@@ -237,8 +243,13 @@ Provide unicode symbols and/or emojis for representing the following:"#
     prompts
 }
 
-/// Get default operations with their configurations
+/// Get default operations with their configurations, for any image model
 pub fn get_default_operations() -> Vec<Operation> {
+    get_operations("")
+}
+
+/// Get the operations with the Image Generation options the image model accepts
+pub fn get_operations(image_model: &str) -> Vec<Operation> {
     let system_prompts = get_default_system_prompts();
     vec![
         Operation {
@@ -249,6 +260,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("GeneralChat")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![],
         },
         Operation {
@@ -259,6 +271,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("EmailReply")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![
                 OperationOption {
                     key: "tone".to_string(),
@@ -292,53 +305,13 @@ pub fn get_default_operations() -> Vec<Operation> {
         Operation {
             operation_type: OperationType::ImageGeneration,
             name: "🖼️ Image Generation".to_string(),
-            description: "Generate images with AI".to_string(),
+            description: "Generate, edit or vary images with AI".to_string(),
             system_prompt: system_prompts
                 .get("ImageGeneration")
                 .cloned()
                 .unwrap_or_default(),
-            options: vec![
-                OperationOption {
-                    key: "size".to_string(),
-                    name: "Image Size".to_string(),
-                    option_type: OptionType::Select,
-                    values: vec![
-                        "512x512 (1:1 Square)".to_string(),
-                        "768x768 (1:1 Square)".to_string(),
-                        "1024x1024 (1:1 Square)".to_string(),
-                        "512x768 (2:3 Portrait)".to_string(),
-                        "768x1152 (2:3 Portrait)".to_string(),
-                        "832x1248 (2:3 Portrait)".to_string(),
-                        "896x1344 (2:3 Portrait)".to_string(),
-                        "768x512 (3:2 Landscape)".to_string(),
-                        "1152x768 (3:2 Landscape)".to_string(),
-                        "1248x832 (3:2 Landscape)".to_string(),
-                        "1344x896 (3:2 Landscape)".to_string(),
-                        "768x1024 (3:4 Portrait)".to_string(),
-                        "936x1248 (3:4 Portrait)".to_string(),
-                        "1024x768 (4:3 Landscape)".to_string(),
-                        "1248x936 (4:3 Landscape)".to_string(),
-                    ],
-                    default_value: "512x768 (2:3 Portrait)".to_string(),
-                    required: true,
-                },
-                OperationOption {
-                    key: "quality".to_string(),
-                    name: "Quality".to_string(),
-                    option_type: OptionType::Select,
-                    values: vec!["standard".to_string(), "hd".to_string()],
-                    default_value: "hd".to_string(),
-                    required: false,
-                },
-                OperationOption {
-                    key: "style".to_string(),
-                    name: "Style".to_string(),
-                    option_type: OptionType::Select,
-                    values: vec!["vivid".to_string(), "natural".to_string()],
-                    default_value: "vivid".to_string(),
-                    required: false,
-                },
-            ],
+            params: GenerationParams::default(),
+            options: image_generation_options(ImageCapabilities::for_model(image_model)),
         },
         Operation {
             operation_type: OperationType::SpeechToText,
@@ -348,6 +321,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("SpeechToText")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![OperationOption {
                 key: "language".to_string(),
                 name: "Language (optional)".to_string(),
@@ -379,6 +353,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("TextRewrite")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![OperationOption {
                 key: "tone".to_string(),
                 name: "Writing Tone".to_string(),
@@ -403,6 +378,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("TextSummarization")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![
                 OperationOption {
                     key: "length".to_string(),
@@ -439,6 +415,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("TextToSpeech")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![
                 OperationOption {
                     key: "voice".to_string(),
@@ -532,6 +509,11 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("TextTranslation")
                 .cloned()
                 .unwrap_or_default(),
+            // Low temperature keeps translations close to the original
+            params: GenerationParams {
+                temperature: Some(0.2),
+                ..Default::default()
+            },
             options: vec![OperationOption {
                 key: "language".to_string(),
                 name: "Target Language".to_string(),
@@ -564,6 +546,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("UnicodeSymbols")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![],
         },
         Operation {
@@ -574,6 +557,7 @@ pub fn get_default_operations() -> Vec<Operation> {
                 .get("WhatsAppResponse")
                 .cloned()
                 .unwrap_or_default(),
+            params: GenerationParams::default(),
             options: vec![
                 OperationOption {
                     key: "tone".to_string(),
@@ -606,3 +590,90 @@ pub fn get_default_operations() -> Vec<Operation> {
         },
     ]
 }
+
+/// Image Generation options: mode, size and count, plus quality and style when the
+/// model takes them
+fn image_generation_options(capabilities: &ImageCapabilities) -> Vec<OperationOption> {
+    let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+    let mut options = vec![
+        OperationOption {
+            key: "mode".to_string(),
+            name: "Mode".to_string(),
+            option_type: OptionType::Select,
+            values: strings(&capabilities.modes()),
+            default_value: "generate".to_string(),
+            required: true,
+        },
+        OperationOption {
+            key: "size".to_string(),
+            name: "Image Size".to_string(),
+            option_type: OptionType::Select,
+            values: capabilities.sizes.iter().map(|s| size_label(s)).collect(),
+            default_value: size_label(capabilities.default_size),
+            required: true,
+        },
+        OperationOption {
+            key: "n".to_string(),
+            name: "Number of Images".to_string(),
+            option_type: OptionType::Select,
+            values: (1..=capabilities.max_images)
+                .map(|n| n.to_string())
+                .collect(),
+            default_value: "1".to_string(),
+            required: false,
+        },
+    ];
+    if let Some(quality) = capabilities.quality(None) {
+        options.push(OperationOption {
+            key: "quality".to_string(),
+            name: "Quality".to_string(),
+            option_type: OptionType::Select,
+            values: strings(capabilities.qualities),
+            default_value: quality,
+            required: false,
+        });
+    }
+    if let Some(style) = capabilities.style(None) {
+        options.push(OperationOption {
+            key: "style".to_string(),
+            name: "Style".to_string(),
+            option_type: OptionType::Select,
+            values: strings(capabilities.styles),
+            default_value: style,
+            required: false,
+        });
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_params() {
+        let operations = get_default_operations();
+        let params = |operation_type: OperationType| {
+            operations
+                .iter()
+                .find(|op| op.operation_type == operation_type)
+                .map(|op| op.params.clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            params(OperationType::TextTranslation).temperature,
+            Some(0.2)
+        );
+        // Other operations leave every parameter to the provider
+        assert_eq!(
+            params(OperationType::TextRewrite),
+            GenerationParams::default()
+        );
+        assert_eq!(
+            params(OperationType::GeneralChat),
+            GenerationParams::default()
+        );
+    }
+}
@@ -0,0 +1,594 @@
+//! Anthropic provider
+//! Talks to the native Messages API (`/v1/messages`)
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+    check_status, for_each_sse_data, ChatMessage, ChatRequest, ChatResponse, LlmProvider,
+    ProviderError, StreamSink, ToolCall, Usage,
+};
+
+/// API version sent in the `anthropic-version` header
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Response budget when none is configured; the Messages API requires one
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Models response from API
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+}
+
+/// Provider for the Anthropic Messages API
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    debug_logging: bool,
+}
+
+impl AnthropicProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: String,
+        api_key: String,
+        debug_logging: bool,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            debug_logging,
+        }
+    }
+
+    /// Build the API URL, adding the /v1 prefix unless the base URL already has it
+    fn build_api_url(&self, endpoint: &str) -> String {
+        let base = self.base_url.trim_end_matches('/');
+        if base.ends_with("/v1") {
+            format!("{}{}", base, endpoint)
+        } else {
+            format!("{}/v1{}", base, endpoint)
+        }
+    }
+
+    /// Message content: plain text, or content blocks with the images before the text
+    /// and the tool calls after it
+    fn message_content(message: &ChatMessage) -> Value {
+        if let Some(ref call) = message.tool_call {
+            return json!([{"type": "tool_result", "tool_use_id": call.id, "content": message.content}]);
+        }
+        if !message.tool_calls.is_empty() {
+            let text = Some(json!({"type": "text", "text": message.content}))
+                .filter(|_| !message.content.is_empty());
+            let blocks: Vec<Value> = text
+                .into_iter()
+                .chain(message.tool_calls.iter().map(|call| {
+                    json!({"type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments})
+                }))
+                .collect();
+            return json!(blocks);
+        }
+        if message.images.is_empty() {
+            return json!(message.content);
+        }
+        let mut blocks: Vec<Value> = message
+            .images
+            .iter()
+            .map(|image| {
+                json!({
+                    "type": "image",
+                    "source": {"type": "base64", "media_type": image.media_type, "data": image.data}
+                })
+            })
+            .collect();
+        blocks.push(json!({"type": "text", "text": message.content}));
+        json!(blocks)
+    }
+
+    /// Build a Messages API body. System messages are hoisted into the top-level
+    /// `system` field since the API does not accept them in `messages`.
+    fn messages_body(request: &ChatRequest, stream: bool) -> Value {
+        // No native structured output: the schema goes into the system prompt
+        let system = request
+            .messages
+            .iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.clone())
+            .chain(request.response_schema.as_ref().map(|s| s.instruction()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        // Tool results are user turns; the results of one round share a single turn
+        let mut messages: Vec<Value> = Vec::new();
+        for message in request.messages.iter().filter(|m| m.role != "system") {
+            let content = Self::message_content(message);
+            if message.tool_call.is_some() {
+                if let Some(last) = messages.last_mut() {
+                    if last["content"][0]["type"] == "tool_result" {
+                        last["content"]
+                            .as_array_mut()
+                            .unwrap()
+                            .extend(content.as_array().cloned().unwrap_or_default());
+                        continue;
+                    }
+                }
+                messages.push(json!({"role": "user", "content": content}));
+            } else {
+                messages.push(json!({"role": message.role, "content": content}));
+            }
+        }
+
+        // The Messages API requires `max_tokens`; the other parameters are optional.
+        // `seed` and `reasoning_effort` have no Anthropic equivalent and are not sent.
+        let params = &request.params;
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages,
+            "max_tokens": params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+        });
+        if let Some(temperature) = params.temperature {
+            // Anthropic's range is 0-1
+            body["temperature"] = json!(temperature.min(1.0));
+        }
+        if let Some(top_p) = params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if !params.stop.is_empty() {
+            body["stop_sequences"] = json!(params.stop);
+        }
+        if !system.is_empty() {
+            body["system"] = json!(system);
+        }
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters
+                    })
+                })
+                .collect();
+            body["tools"] = json!(tools);
+        }
+        if stream {
+            body["stream"] = json!(true);
+        }
+        params.apply_extra_body(&mut body);
+        body
+    }
+
+    /// Input and output tokens of a `usage` object. Cached prompt tokens are
+    /// reported separately and counted as input.
+    fn usage(usage: &Value) -> Option<(u32, u32)> {
+        if !usage.is_object() {
+            return None;
+        }
+        let input = [
+            "input_tokens",
+            "cache_creation_input_tokens",
+            "cache_read_input_tokens",
+        ]
+        .iter()
+        .filter_map(|key| usage[*key].as_u64())
+        .sum::<u64>();
+        let output = usage["output_tokens"].as_u64().unwrap_or(0);
+        Some((input as u32, output as u32))
+    }
+
+    /// Send a request with the Anthropic auth headers and check the response status
+    async fn send(
+        &self,
+        builder: reqwest::RequestBuilder,
+        label: &str,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = builder
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .send()
+            .await
+            .map_err(|e| {
+                if self.debug_logging {
+                    println!("Request Failed: {}", e);
+                    println!("--------------------");
+                }
+                ProviderError::Request(e.to_string())
+            })?;
+
+        check_status(response, label, self.debug_logging).await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+        let body = Self::messages_body(request, false);
+        let url = self.build_api_url("/messages");
+
+        if self.debug_logging {
+            println!("--- Anthropic Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!(
+                "Body: {}",
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            println!("-------------------");
+        }
+
+        if self.api_key.is_empty() {
+            return Err(ProviderError::MissingApiKey);
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Anthropic")
+            .await?;
+
+        let text = resp.text().await.unwrap_or_default();
+        if self.debug_logging {
+            println!("Body: {}", text);
+            println!("--------------------");
+        }
+
+        let data: Value = serde_json::from_str(&text).map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })?;
+
+        // Concatenate all text blocks; tool_use blocks become tool calls
+        let content: String = data["content"]
+            .as_array()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|b| b["type"] == "text")
+                    .filter_map(|b| b["text"].as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let tool_calls: Vec<ToolCall> = data["content"]
+            .as_array()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|b| b["type"] == "tool_use")
+                    .map(|b| ToolCall {
+                        id: b["id"].as_str().unwrap_or_default().to_string(),
+                        name: b["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: b["input"].clone(),
+                        signature: None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if content.is_empty() && tool_calls.is_empty() {
+            return Err(ProviderError::InvalidResponse(
+                "No content in response".to_string(),
+            ));
+        }
+
+        // Extended thinking comes in its own blocks
+        let reasoning = data["content"]
+            .as_array()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|b| b["type"] == "thinking")
+                    .filter_map(|b| b["thinking"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            })
+            .unwrap_or_default();
+
+        Ok(ChatResponse {
+            content,
+            reasoning,
+            usage: Self::usage(&data["usage"]).map(|(input, output)| Usage::new(input, output)),
+            truncated: data["stop_reason"] == "max_tokens",
+            tool_calls,
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        sink: &mut dyn StreamSink,
+    ) -> Result<ChatResponse, ProviderError> {
+        let body = Self::messages_body(request, true);
+        let url = self.build_api_url("/messages");
+
+        if self.debug_logging {
+            println!("--- Anthropic Streaming Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!("-------------------");
+        }
+
+        if self.api_key.is_empty() {
+            return Err(ProviderError::MissingApiKey);
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Anthropic Streaming")
+            .await?;
+
+        let mut full_content = String::new();
+        let mut full_reasoning = String::new();
+        let mut usage: Option<(u32, u32)> = None;
+        let mut truncated = false;
+        for_each_sse_data(resp, sink, self.debug_logging, |data, sink| {
+            let Ok(json) = serde_json::from_str::<Value>(data) else {
+                return Ok(());
+            };
+
+            match json["type"].as_str() {
+                // Input tokens come with the start event; `message_delta` carries the running output count
+                Some("message_start") => usage = Self::usage(&json["message"]["usage"]),
+                Some("message_delta") => {
+                    if let Some((input, output)) = Self::usage(&json["usage"]) {
+                        let (start_input, _) = usage.unwrap_or_default();
+                        usage = Some((input.max(start_input), output));
+                    }
+                    truncated = json["delta"]["stop_reason"] == "max_tokens";
+                }
+                Some("content_block_delta") => {
+                    if let Some(text) = json["delta"]["text"].as_str() {
+                        full_content.push_str(text);
+                        sink.on_content(text);
+                    }
+                    if let Some(thinking) = json["delta"]["thinking"].as_str() {
+                        full_reasoning.push_str(thinking);
+                        sink.on_reasoning(thinking);
+                    }
+                }
+                Some("message_stop") => sink.on_done(),
+                _ => {}
+            }
+            Ok(())
+        })
+        .await?;
+
+        if self.debug_logging {
+            println!("Full streamed content length: {}", full_content.len());
+            println!("--------------------");
+        }
+
+        Ok(ChatResponse {
+            content: full_content,
+            reasoning: full_reasoning,
+            usage: usage.map(|(input, output)| Usage::new(input, output)),
+            truncated,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        let url = self.build_api_url("/models");
+
+        if self.debug_logging {
+            println!("--- Fetching Models ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: GET {}", url);
+            println!("-----------------------");
+        }
+
+        let resp = self.send(self.client.get(&url), "Models").await?;
+
+        let models = resp.json::<ModelsResponse>().await.map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse models: {}", e))
+        })?;
+
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{mock_server, CollectSink};
+    use crate::providers::{GenerationParams, ImageAttachment, ResponseSchema, ToolCall};
+
+    fn test_request() -> ChatRequest {
+        ChatRequest {
+            model: "claude-test".to_string(),
+            messages: vec![ChatMessage::system("Be brief."), ChatMessage::user("Hello")],
+            params: GenerationParams {
+                max_tokens: Some(256),
+                temperature: Some(0.6),
+                ..Default::default()
+            },
+            response_schema: None,
+            tools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_system_prompt_is_hoisted() {
+        let body = AnthropicProvider::messages_body(&test_request(), false);
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_response_schema_becomes_an_instruction() {
+        let mut request = test_request();
+        request.response_schema = Some(ResponseSchema::new(
+            "fields",
+            json!({"type": "object", "required": ["subject"]}),
+        ));
+
+        let body = AnthropicProvider::messages_body(&request, false);
+        let system = body["system"].as_str().unwrap();
+        assert!(system.starts_with("Be brief.\n\nRespond only with a JSON value"));
+        assert!(system.contains("\"required\": [\n    \"subject\"\n  ]"));
+    }
+
+    #[test]
+    fn test_tool_results_of_a_round_share_a_user_turn() {
+        let calls: Vec<ToolCall> = ["a", "b"]
+            .iter()
+            .map(|id| ToolCall {
+                id: id.to_string(),
+                name: "date_time".to_string(),
+                arguments: json!({}),
+                signature: None,
+            })
+            .collect();
+        let mut request = test_request();
+        request
+            .messages
+            .push(ChatMessage::tool_calls("Checking.", calls.clone()));
+        request
+            .messages
+            .push(ChatMessage::tool_result(&calls[0], "Monday"));
+        request
+            .messages
+            .push(ChatMessage::tool_result(&calls[1], "Tuesday"));
+
+        let body = AnthropicProvider::messages_body(&request, false);
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[1]["content"][0],
+            json!({"type": "text", "text": "Checking."})
+        );
+        assert_eq!(messages[1]["content"][2]["type"], "tool_use");
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(
+            messages[2]["content"],
+            json!([
+                {"type": "tool_result", "tool_use_id": "a", "content": "Monday"},
+                {"type": "tool_result", "tool_use_id": "b", "content": "Tuesday"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_images_precede_the_text() {
+        let mut request = test_request();
+        request.messages[1] =
+            ChatMessage::user("What is this?").with_images(vec![ImageAttachment {
+                media_type: "image/jpeg".to_string(),
+                data: "/9j/4AAQ".to_string(),
+            }]);
+
+        let body = AnthropicProvider::messages_body(&request, false);
+        let content = &body["messages"][0]["content"];
+        assert_eq!(content[0]["type"], "image");
+        assert_eq!(content[0]["source"]["media_type"], "image/jpeg");
+        assert_eq!(content[0]["source"]["data"], "/9j/4AAQ");
+        assert_eq!(content[1], json!({"type": "text", "text": "What is this?"}));
+    }
+
+    #[test]
+    fn test_params_mapping() {
+        let mut request = test_request();
+        request.params = GenerationParams {
+            stop: vec!["END".to_string()],
+            seed: Some(7),
+            ..Default::default()
+        };
+        let body = AnthropicProvider::messages_body(&request, false);
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(body["stop_sequences"], json!(["END"]));
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("seed").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_against_mock_server() {
+        let sse = "event: message_start\n\
+                   data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"usage\":{\"input_tokens\":12,\"cache_read_input_tokens\":3,\"output_tokens\":1}}}\n\n\
+                   event: content_block_delta\n\
+                   data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n\
+                   event: content_block_delta\n\
+                   data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo!\"}}\n\n\
+                   event: message_delta\n\
+                   data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":5}}\n\n\
+                   event: message_stop\n\
+                   data: {\"type\":\"message_stop\"}\n\n";
+        let (base_url, server) = mock_server(sse, "text/event-stream").await;
+
+        let provider = AnthropicProvider::new(
+            reqwest::Client::new(),
+            base_url,
+            "sk-ant-test".to_string(),
+            false,
+        );
+        let mut sink = CollectSink::default();
+        let response = provider
+            .chat_stream(&test_request(), &mut sink)
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Hello!");
+        assert_eq!(response.usage, Some(Usage::new(15, 5)));
+        assert_eq!(sink.chunks, vec!["Hel", "lo!"]);
+        assert!(sink.done);
+
+        let raw_request = server.await.unwrap().to_ascii_lowercase();
+        assert!(raw_request.starts_with("post /v1/messages"));
+        assert!(raw_request.contains("x-api-key: sk-ant-test"));
+        assert!(raw_request.contains("anthropic-version: 2023-06-01"));
+        assert!(raw_request.contains("\"system\":\"be brief.\""));
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_event() {
+        let sse = "event: error\n\
+                   data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n";
+        let (base_url, _server) = mock_server(sse, "text/event-stream").await;
+
+        let provider = AnthropicProvider::new(
+            reqwest::Client::new(),
+            base_url,
+            "sk-ant-test".to_string(),
+            false,
+        );
+        let mut sink = CollectSink::default();
+        let err = provider
+            .chat_stream(&test_request(), &mut sink)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Stream error: overloaded_error: Overloaded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_streaming_against_mock_server() {
+        let body = r#"{"id":"msg_1","type":"message","content":[{"type":"text","text":"Hi there"}],"stop_reason":"end_turn"}"#;
+        let (base_url, _server) = mock_server(body, "application/json").await;
+
+        let provider = AnthropicProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-ant-test".to_string(),
+            false,
+        );
+        let response = provider.chat(&test_request()).await.unwrap();
+        assert_eq!(response.content, "Hi there");
+    }
+}
@@ -0,0 +1,600 @@
+//! Google Gemini provider
+//! Talks to the Generative Language API (`generateContent` / `streamGenerateContent`)
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+    check_status, for_each_sse_data, sse, ChatMessage, ChatRequest, ChatResponse, GenerationParams,
+    LlmProvider, ProviderError, StreamSink, ToolCall, Usage,
+};
+
+/// Upper bound on `models` pages fetched when listing models
+const MAX_MODEL_PAGES: usize = 10;
+
+/// Models response from API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelsResponse {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelInfo {
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+/// Provider for the Google Gemini API
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    debug_logging: bool,
+}
+
+impl GeminiProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: String,
+        api_key: String,
+        debug_logging: bool,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            debug_logging,
+        }
+    }
+
+    /// Build the API URL, adding the /v1beta version unless the base URL already has one.
+    /// The API key is passed separately as a query parameter so it never shows up in logs.
+    fn build_api_url(&self, endpoint: &str) -> String {
+        let base = self.base_url.trim_end_matches('/');
+        let has_version = base.ends_with("/v1") || base.ends_with("/v1beta");
+        if has_version {
+            format!("{}{}", base, endpoint)
+        } else {
+            format!("{}/v1beta{}", base, endpoint)
+        }
+    }
+
+    /// Build the `models/{model}:{method}` URL; accepts model ids with or without the `models/` prefix
+    fn model_url(&self, model: &str, method: &str) -> String {
+        let model = model.strip_prefix("models/").unwrap_or(model);
+        self.build_api_url(&format!("/models/{}:{}", model, method))
+    }
+
+    /// Build a generateContent body. System messages become `systemInstruction`,
+    /// and the assistant role is mapped to Gemini's `model` role.
+    fn generate_body(request: &ChatRequest) -> Value {
+        let system = request
+            .messages
+            .iter()
+            .filter(|m| m.role == "system")
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        // Function responses are user turns; the responses of one round share a single turn
+        let mut contents: Vec<Value> = Vec::new();
+        for message in request.messages.iter().filter(|m| m.role != "system") {
+            let parts = Self::parts(message);
+            if message.tool_call.is_some() {
+                if let Some(last) = contents.last_mut() {
+                    if last["parts"][0].get("functionResponse").is_some() {
+                        last["parts"].as_array_mut().unwrap().extend(parts);
+                        continue;
+                    }
+                }
+            }
+            let role = if message.role == "assistant" {
+                "model"
+            } else {
+                "user"
+            };
+            contents.push(json!({"role": role, "parts": parts}));
+        }
+
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": Self::generation_config(&request.params)
+        });
+        if let Some(ref schema) = request.response_schema {
+            body["generationConfig"]["responseMimeType"] = json!("application/json");
+            body["generationConfig"]["responseJsonSchema"] = schema.schema.clone();
+        }
+        if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    let mut declaration =
+                        json!({"name": tool.name, "description": tool.description});
+                    // Gemini rejects object schemas without properties
+                    if tool.parameters["properties"]
+                        .as_object()
+                        .is_some_and(|properties| !properties.is_empty())
+                    {
+                        declaration["parameters"] = tool.parameters.clone();
+                    }
+                    declaration
+                })
+                .collect();
+            body["tools"] = json!([{"functionDeclarations": declarations}]);
+        }
+        if !system.is_empty() {
+            body["systemInstruction"] = json!({"parts": [{"text": system}]});
+        }
+        request.params.apply_extra_body(&mut body);
+        body
+    }
+
+    /// Parts of a message: text and images, function calls, or a function response
+    fn parts(message: &ChatMessage) -> Vec<Value> {
+        if let Some(ref call) = message.tool_call {
+            return vec![json!({
+                "functionResponse": {"name": call.name, "response": {"result": message.content}}
+            })];
+        }
+
+        let mut parts = Vec::new();
+        if !message.content.is_empty() || message.tool_calls.is_empty() {
+            parts.push(json!({"text": message.content}));
+        }
+        parts.extend(message.images.iter().map(
+            |image| json!({"inlineData": {"mimeType": image.media_type, "data": image.data}}),
+        ));
+        parts.extend(message.tool_calls.iter().map(|call| {
+            let mut part = json!({"functionCall": {"name": call.name, "args": call.arguments}});
+            if let Some(ref signature) = call.signature {
+                part["thoughtSignature"] = json!(signature);
+            }
+            part
+        }));
+        parts
+    }
+
+    /// Function calls of a response, numbered since Gemini gives them no ids
+    fn tool_calls(data: &Value) -> Vec<ToolCall> {
+        data["candidates"][0]["content"]["parts"]
+            .as_array()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter(|p| p.get("functionCall").is_some())
+                    .enumerate()
+                    .map(|(index, p)| ToolCall {
+                        id: format!("call_{}", index),
+                        name: p["functionCall"]["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        arguments: p["functionCall"]["args"].clone(),
+                        signature: p["thoughtSignature"].as_str().map(str::to_string),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Map generation parameters to `generationConfig`; the reasoning effort
+    /// becomes a thinking budget, with thought summaries included
+    fn generation_config(params: &GenerationParams) -> Value {
+        let mut config = json!({});
+        if let Some(temperature) = params.temperature {
+            config["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            config["topP"] = json!(top_p);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            config["maxOutputTokens"] = json!(max_tokens);
+        }
+        if !params.stop.is_empty() {
+            config["stopSequences"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            config["seed"] = json!(seed);
+        }
+        let thinking_budget = match params.reasoning_effort.as_deref() {
+            Some("minimal") => Some(128),
+            Some("low") => Some(1024),
+            Some("medium") => Some(8192),
+            Some("high") => Some(24576),
+            _ => None,
+        };
+        if let Some(budget) = thinking_budget {
+            // Thought summaries are only returned when asked for
+            config["thinkingConfig"] = json!({"thinkingBudget": budget, "includeThoughts": true});
+        }
+        config
+    }
+
+    /// Concatenate `candidates[0].content.parts[].text`, either the answer parts
+    /// or the thought summaries (`thought: true`)
+    fn candidate_text(data: &Value, thoughts: bool) -> String {
+        data["candidates"][0]["content"]["parts"]
+            .as_array()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter(|p| p["thought"].as_bool().unwrap_or(false) == thoughts)
+                    .filter_map(|p| p["text"].as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parse `usageMetadata`; thinking tokens are billed as output
+    fn usage(data: &Value) -> Option<Usage> {
+        let metadata = data.get("usageMetadata").filter(|m| m.is_object())?;
+        let output = ["candidatesTokenCount", "thoughtsTokenCount"]
+            .iter()
+            .filter_map(|key| metadata[*key].as_u64())
+            .sum::<u64>();
+        Some(Usage::new(
+            metadata["promptTokenCount"].as_u64().unwrap_or(0) as u32,
+            output as u32,
+        ))
+    }
+
+    /// Whether the candidate stopped at `maxOutputTokens`
+    fn hit_token_limit(data: &Value) -> bool {
+        data["candidates"][0]["finishReason"] == "MAX_TOKENS"
+    }
+
+    /// Send a request with the API key query parameter and check the response status
+    async fn send(
+        &self,
+        builder: reqwest::RequestBuilder,
+        label: &str,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = builder
+            .query(&[("key", &self.api_key)])
+            .send()
+            .await
+            .map_err(|e| {
+                // reqwest errors include the URL; strip it so the key is not leaked
+                let e = e.without_url();
+                if self.debug_logging {
+                    println!("Request Failed: {}", e);
+                    println!("--------------------");
+                }
+                ProviderError::Request(e.to_string())
+            })?;
+
+        check_status(response, label, self.debug_logging).await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+        let body = Self::generate_body(request);
+        let url = self.model_url(&request.model, "generateContent");
+
+        if self.debug_logging {
+            println!("--- Gemini Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!(
+                "Body: {}",
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            println!("-------------------");
+        }
+
+        if self.api_key.is_empty() {
+            return Err(ProviderError::MissingApiKey);
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Gemini")
+            .await?;
+
+        let text = resp.text().await.unwrap_or_default();
+        if self.debug_logging {
+            println!("Body: {}", text);
+            println!("--------------------");
+        }
+
+        let data: Value = serde_json::from_str(&text).map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })?;
+
+        if let Some(message) = sse::error_message(&data) {
+            return Err(ProviderError::Stream(message));
+        }
+
+        let content = Self::candidate_text(&data, false);
+        let tool_calls = Self::tool_calls(&data);
+        if content.is_empty() && tool_calls.is_empty() {
+            // Blocked prompts come back without candidates but with a block reason
+            if let Some(reason) = data["promptFeedback"]["blockReason"].as_str() {
+                return Err(ProviderError::InvalidResponse(format!(
+                    "Prompt blocked by Gemini: {}",
+                    reason
+                )));
+            }
+            return Err(ProviderError::InvalidResponse(
+                "No content in response".to_string(),
+            ));
+        }
+
+        Ok(ChatResponse {
+            content,
+            reasoning: Self::candidate_text(&data, true),
+            usage: Self::usage(&data),
+            truncated: Self::hit_token_limit(&data),
+            tool_calls,
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        sink: &mut dyn StreamSink,
+    ) -> Result<ChatResponse, ProviderError> {
+        let body = Self::generate_body(request);
+        let url = self.model_url(&request.model, "streamGenerateContent");
+
+        if self.debug_logging {
+            println!("--- Gemini Streaming Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}?alt=sse", url);
+            println!("Model: {}", request.model);
+            println!("-------------------");
+        }
+
+        if self.api_key.is_empty() {
+            return Err(ProviderError::MissingApiKey);
+        }
+
+        let resp = self
+            .send(
+                self.client.post(&url).query(&[("alt", "sse")]).json(&body),
+                "Gemini Streaming",
+            )
+            .await?;
+
+        let mut full_content = String::new();
+        let mut full_reasoning = String::new();
+        let mut usage = None;
+        let mut truncated = false;
+        for_each_sse_data(resp, sink, self.debug_logging, |data, sink| {
+            let Ok(json) = serde_json::from_str::<Value>(data) else {
+                return Ok(());
+            };
+
+            let thoughts = Self::candidate_text(&json, true);
+            if !thoughts.is_empty() {
+                full_reasoning.push_str(&thoughts);
+                sink.on_reasoning(&thoughts);
+            }
+            let text = Self::candidate_text(&json, false);
+            if !text.is_empty() {
+                full_content.push_str(&text);
+                sink.on_content(&text);
+            }
+            // Every chunk carries the running totals; the last one is final
+            usage = Self::usage(&json).or(usage);
+            truncated |= Self::hit_token_limit(&json);
+            Ok(())
+        })
+        .await?;
+
+        // Gemini has no explicit end-of-stream marker; the stream closing is the signal
+        sink.on_done();
+
+        if self.debug_logging {
+            println!("Full streamed content length: {}", full_content.len());
+            println!("--------------------");
+        }
+
+        Ok(ChatResponse {
+            content: full_content,
+            reasoning: full_reasoning,
+            usage,
+            truncated,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        let url = self.build_api_url("/models");
+
+        if self.debug_logging {
+            println!("--- Fetching Models ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: GET {}", url);
+            println!("-----------------------");
+        }
+
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        for _ in 0..MAX_MODEL_PAGES {
+            let mut builder = self.client.get(&url).query(&[("pageSize", "1000")]);
+            if let Some(ref token) = page_token {
+                builder = builder.query(&[("pageToken", token)]);
+            }
+
+            let resp = self.send(builder, "Models").await?;
+            let page = resp.json::<ModelsResponse>().await.map_err(|e| {
+                ProviderError::InvalidResponse(format!("Failed to parse models: {}", e))
+            })?;
+
+            models.extend(
+                page.models
+                    .into_iter()
+                    .filter(|m| {
+                        m.supported_generation_methods.is_empty()
+                            || m.supported_generation_methods
+                                .iter()
+                                .any(|method| method == "generateContent")
+                    })
+                    .map(|m| m.name.trim_start_matches("models/").to_string()),
+            );
+
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
+        }
+
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{mock_server, CollectSink};
+    use crate::providers::ChatMessage;
+
+    fn test_request() -> ChatRequest {
+        ChatRequest {
+            model: "models/gemini-2.0-flash".to_string(),
+            messages: vec![ChatMessage::system("Be brief."), ChatMessage::user("Hello")],
+            params: GenerationParams {
+                max_tokens: Some(256),
+                reasoning_effort: Some("low".to_string()),
+                extra_body: Some(json!({"generationConfig": {"topK": 40}})),
+                ..Default::default()
+            },
+            response_schema: None,
+            tools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_generate_body() {
+        let body = GeminiProvider::generate_body(&test_request());
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(body["contents"][0]["role"], "user");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Hello");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            1024
+        );
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"]["includeThoughts"],
+            true
+        );
+        assert_eq!(body["generationConfig"]["topK"], 40);
+        assert!(body["generationConfig"].get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_function_calls_and_responses() {
+        let calls: Vec<ToolCall> = ["a", "b"]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| ToolCall {
+                id: format!("call_{}", index),
+                name: name.to_string(),
+                arguments: json!({"x": index}),
+                signature: (index == 0).then(|| "sig".to_string()),
+            })
+            .collect();
+        let mut request = test_request();
+        request
+            .messages
+            .push(ChatMessage::tool_calls("", calls.clone()));
+        request
+            .messages
+            .push(ChatMessage::tool_result(&calls[0], "1"));
+        request
+            .messages
+            .push(ChatMessage::tool_result(&calls[1], "2"));
+
+        let body = GeminiProvider::generate_body(&request);
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(
+            contents[1]["parts"][0],
+            json!({"functionCall": {"name": "a", "args": {"x": 0}}, "thoughtSignature": "sig"})
+        );
+        // Both responses go back in one user turn
+        assert_eq!(contents[2]["role"], "user");
+        assert_eq!(
+            contents[2]["parts"][1],
+            json!({"functionResponse": {"name": "b", "response": {"result": "2"}}})
+        );
+
+        let calls = GeminiProvider::tool_calls(&json!({"candidates": [{"content": {"parts": [
+            {"text": "Checking."},
+            {"functionCall": {"name": "a", "args": {"x": 0}}, "thoughtSignature": "sig"}
+        ]}}]}));
+        assert_eq!(calls[0].id, "call_0");
+        assert_eq!(calls[0].signature.as_deref(), Some("sig"));
+    }
+
+    #[test]
+    fn test_model_url() {
+        let provider = GeminiProvider::new(
+            reqwest::Client::new(),
+            "https://generativelanguage.googleapis.com".to_string(),
+            "key".to_string(),
+            false,
+        );
+        assert_eq!(
+            provider.model_url("models/gemini-2.0-flash", "generateContent"),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streaming_against_mock_server() {
+        let sse = "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hel\"}],\"role\":\"model\"}}]}\r\n\r\n\
+                   data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"lo!\"}],\"role\":\"model\"},\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"promptTokenCount\":9,\"candidatesTokenCount\":2,\"thoughtsTokenCount\":4,\"totalTokenCount\":15}}\r\n\r\n";
+        let (base_url, server) = mock_server(sse, "text/event-stream").await;
+
+        let provider =
+            GeminiProvider::new(reqwest::Client::new(), base_url, "g-key".to_string(), false);
+        let mut sink = CollectSink::default();
+        let response = provider
+            .chat_stream(&test_request(), &mut sink)
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Hello!");
+        assert_eq!(response.usage, Some(Usage::new(9, 6)));
+        assert_eq!(sink.chunks, vec!["Hel", "lo!"]);
+        assert!(sink.done);
+
+        let raw_request = server.await.unwrap();
+        let request_line = raw_request.lines().next().unwrap();
+        assert!(request_line.starts_with(
+            "POST /v1beta/models/gemini-2.0-flash:streamGenerateContent?alt=sse&key=g-key"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_filters_generate_content() {
+        let body = r#"{"models":[
+            {"name":"models/gemini-2.0-flash","supportedGenerationMethods":["generateContent","countTokens"]},
+            {"name":"models/text-embedding-004","supportedGenerationMethods":["embedContent"]}
+        ]}"#;
+        let (base_url, _server) = mock_server(body, "application/json").await;
+
+        let provider =
+            GeminiProvider::new(reqwest::Client::new(), base_url, "g-key".to_string(), false);
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models, vec!["gemini-2.0-flash"]);
+    }
+}
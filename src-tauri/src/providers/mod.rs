@@ -0,0 +1,720 @@
+//! Provider abstraction for AI Anywhere
+//! Defines the backend trait used by LlmService and the provider-neutral request types
+
+mod anthropic;
+mod gemini;
+mod ollama;
+mod openai;
+mod params;
+mod reasoning;
+mod retry;
+mod sse;
+#[cfg(test)]
+mod test_support;
+
+pub use anthropic::AnthropicProvider;
+pub use gemini::GeminiProvider;
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiProvider;
+pub use params::GenerationParams;
+pub use reasoning::{think_blocks, StreamPart, ThinkTagSplitter};
+pub use retry::RetryPolicy;
+pub use sse::{SseDecoder, SseEvent};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+use crate::config::{Configuration, EndpointProfile, ProviderKind};
+
+/// An image sent along with a chat message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageAttachment {
+    /// MIME type, e.g. `image/png`
+    pub media_type: String,
+    /// Base64-encoded image bytes
+    pub data: String,
+}
+
+impl ImageAttachment {
+    /// `data:` URI, the form OpenAI-compatible APIs take in `image_url`
+    pub fn data_uri(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+/// A tool the model may call, described by a JSON Schema of its arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+/// A tool call requested by the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    /// Call id to answer with (generated for providers without ids)
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+    /// Opaque value the provider needs back with the call (Gemini's thought signature)
+    pub signature: Option<String>,
+}
+
+/// A single chat message
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+    /// Images for multimodal models (user messages only)
+    pub images: Vec<ImageAttachment>,
+    /// Tools called by the assistant in this turn
+    pub tool_calls: Vec<ToolCall>,
+    /// For `tool` messages: the call this message is the result of
+    pub tool_call: Option<ToolCall>,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", content.into())
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content.into())
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content.into())
+    }
+
+    /// Attach images to the message
+    pub fn with_images(mut self, images: Vec<ImageAttachment>) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Assistant turn that called tools (with any text it produced alongside)
+    pub fn tool_calls(content: impl Into<String>, calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls: calls,
+            ..Self::assistant(content)
+        }
+    }
+
+    /// Result of a tool call
+    pub fn tool_result(call: &ToolCall, content: impl Into<String>) -> Self {
+        Self {
+            tool_call: Some(call.clone()),
+            ..Self::new("tool", content.into())
+        }
+    }
+}
+
+/// Provider-neutral chat completion request
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub params: GenerationParams,
+    /// JSON Schema the answer must match, if structured output is requested
+    pub response_schema: Option<ResponseSchema>,
+    /// Tools the model may call
+    pub tools: Vec<ToolDefinition>,
+}
+
+/// JSON Schema for structured output. Providers with native support send it as a
+/// response format; the others add `instruction()` to the system prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseSchema {
+    /// Schema name, limited to the characters OpenAI accepts
+    pub name: String,
+    pub schema: Value,
+}
+
+impl ResponseSchema {
+    pub fn new(name: &str, schema: Value) -> Self {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(64)
+            .collect();
+        Self {
+            name: if name.is_empty() {
+                "response".to_string()
+            } else {
+                name
+            },
+            schema,
+        }
+    }
+
+    /// System prompt addition asking for JSON matching the schema
+    pub fn instruction(&self) -> String {
+        format!(
+            "Respond only with a JSON value that matches this JSON Schema, without code fences \
+             or any other text:\n{}",
+            serde_json::to_string_pretty(&self.schema).unwrap_or_default()
+        )
+    }
+}
+
+/// Result of a (streaming or non-streaming) chat completion
+#[derive(Debug, Clone, Default)]
+pub struct ChatResponse {
+    /// Raw assistant content (not yet post-processed)
+    pub content: String,
+    /// Reasoning sent in a dedicated field or block (`reasoning_content`, thinking blocks, ...)
+    pub reasoning: String,
+    /// Token usage, if the provider reported it
+    pub usage: Option<Usage>,
+    /// Generation stopped at the token limit, so the content is cut short
+    pub truncated: bool,
+    /// Tools the model called instead of (or besides) answering
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Token usage of a chat completion as reported by the provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+impl Usage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    /// Sum of two usages of the same request (e.g. its chunks); unknown if both are
+    pub fn combine(a: Option<Usage>, b: Option<Usage>) -> Option<Usage> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(Usage {
+                prompt_tokens: a.prompt_tokens + b.prompt_tokens,
+                completion_tokens: a.completion_tokens + b.completion_tokens,
+                total_tokens: a.total_tokens + b.total_tokens,
+            }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Image generation request
+#[derive(Debug, Clone)]
+pub struct ImageRequest {
+    pub model: String,
+    pub prompt: String,
+    pub size: String,
+    /// Not sent when None, as not every model accepts it
+    pub quality: Option<String>,
+    /// Not sent when None, as not every model accepts it
+    pub style: Option<String>,
+    /// Number of images to generate
+    pub n: u32,
+    /// "b64_json", or None for models that only answer in base64 and reject the parameter
+    pub response_format: Option<String>,
+}
+
+/// Image edit request: the prompt describes the change, made where the mask (or the
+/// image itself when there is no mask) is transparent
+#[derive(Debug, Clone)]
+pub struct ImageEditRequest {
+    pub model: String,
+    pub prompt: String,
+    /// PNG-encoded input image
+    pub image: Vec<u8>,
+    /// PNG-encoded mask, the same size as the image
+    pub mask: Option<Vec<u8>>,
+    pub size: String,
+    /// Number of images to generate
+    pub n: u32,
+    /// As in `ImageRequest`
+    pub response_format: Option<String>,
+}
+
+/// Image variation request
+#[derive(Debug, Clone)]
+pub struct ImageVariationRequest {
+    pub model: String,
+    /// PNG-encoded input image
+    pub image: Vec<u8>,
+    pub size: String,
+    /// Number of images to generate
+    pub n: u32,
+    /// As in `ImageRequest`
+    pub response_format: Option<String>,
+}
+
+/// Image generation result
+#[derive(Debug, Clone)]
+pub struct ImageResponse {
+    /// Encoded bytes of each generated image
+    pub images: Vec<Vec<u8>>,
+}
+
+/// Audio transcription request
+#[derive(Debug, Clone)]
+pub struct TranscriptionRequest {
+    pub model: String,
+    pub file_bytes: Vec<u8>,
+    pub file_name: String,
+    /// Language hint; `None` lets the provider auto-detect
+    pub language: Option<String>,
+}
+
+/// Text-to-speech request
+#[derive(Debug, Clone)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub input: String,
+    pub voice: String,
+    pub format: String,
+    pub speed: f32,
+    pub language: String,
+}
+
+/// Receiver for incremental streaming output
+pub trait StreamSink: Send {
+    /// Checked by providers between network chunks; returning true aborts the stream
+    fn is_cancelled(&self) -> bool;
+
+    /// Called for every content delta
+    fn on_content(&mut self, content: &str);
+
+    /// Called for every reasoning delta sent in a dedicated field
+    fn on_reasoning(&mut self, _reasoning: &str) {}
+
+    /// Called once when the provider signals the end of the stream
+    fn on_done(&mut self);
+}
+
+/// Errors returned by providers
+#[derive(Debug, Clone)]
+pub enum ProviderError {
+    /// No API key configured for a provider that requires one
+    MissingApiKey,
+    /// The API answered with a non-success status
+    Api {
+        status: StatusCode,
+        body: String,
+        /// Parsed `Retry-After` header, if the server sent one
+        retry_after: Option<Duration>,
+    },
+    /// The request could not be sent (connection, timeout, ...)
+    Request(String),
+    /// The response stream failed mid-way
+    Stream(String),
+    /// The response could not be interpreted
+    InvalidResponse(String),
+    /// The user cancelled the request
+    Cancelled,
+    /// The provider does not implement this capability
+    Unsupported(String),
+}
+
+impl ProviderError {
+    /// Format the error for an operation, e.g. "TTS Error (status 500): ..."
+    /// Non-API errors keep their default message.
+    pub fn describe(&self, label: &str) -> String {
+        match self {
+            ProviderError::Api { status, body, .. } => format!(
+                "{} (status {}): {}",
+                label,
+                status.as_u16(),
+                if body.is_empty() {
+                    status.canonical_reason().unwrap_or("Unknown error")
+                } else {
+                    body
+                }
+            ),
+            other => other.to_string(),
+        }
+    }
+
+    /// Whether the failure is transient (rate limited or a server error)
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ProviderError::Api { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            _ => false,
+        }
+    }
+
+    /// How long the server asked us to wait before retrying
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ProviderError::Api { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::MissingApiKey => write!(
+                f,
+                "API key is empty. Please configure your API key in settings."
+            ),
+            ProviderError::Api { body, .. } => write!(f, "API Error: {}", body),
+            ProviderError::Request(e) => write!(f, "Request failed: {}", e),
+            ProviderError::Stream(e) => write!(f, "Stream error: {}", e),
+            ProviderError::InvalidResponse(msg) => write!(f, "{}", msg),
+            ProviderError::Cancelled => write!(f, "Request cancelled"),
+            ProviderError::Unsupported(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// An AI backend. Every capability except chat and model listing is optional.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Human-readable provider name (used in logs and errors)
+    fn name(&self) -> &'static str;
+
+    /// Non-streaming chat completion
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProviderError>;
+
+    /// Streaming chat completion; deltas are pushed to `sink` as they arrive
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        sink: &mut dyn StreamSink,
+    ) -> Result<ChatResponse, ProviderError>;
+
+    /// Generate an image from a text prompt
+    async fn generate_image(
+        &self,
+        _request: &ImageRequest,
+    ) -> Result<ImageResponse, ProviderError> {
+        Err(self.unsupported("Image generation"))
+    }
+
+    /// Edit an image as described by a prompt
+    async fn edit_image(
+        &self,
+        _request: &ImageEditRequest,
+    ) -> Result<ImageResponse, ProviderError> {
+        Err(self.unsupported("Image editing"))
+    }
+
+    /// Generate a variation of an image
+    async fn vary_image(
+        &self,
+        _request: &ImageVariationRequest,
+    ) -> Result<ImageResponse, ProviderError> {
+        Err(self.unsupported("Image variations"))
+    }
+
+    /// Transcribe an audio file to text
+    async fn transcribe(&self, _request: &TranscriptionRequest) -> Result<String, ProviderError> {
+        Err(self.unsupported("Speech-to-Text"))
+    }
+
+    /// Synthesize speech, returning the encoded audio bytes
+    async fn speech(&self, _request: &SpeechRequest) -> Result<Vec<u8>, ProviderError> {
+        Err(self.unsupported("Text-to-Speech"))
+    }
+
+    /// List model identifiers available on this endpoint
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError>;
+
+    /// Build the error returned by unimplemented capabilities
+    fn unsupported(&self, capability: &str) -> ProviderError {
+        ProviderError::Unsupported(format!(
+            "{} is not supported by the {} provider",
+            capability,
+            self.name()
+        ))
+    }
+}
+
+/// Build an HTTP client for a profile, sending its extra headers with every request.
+/// Headers with invalid names or values are skipped.
+pub fn build_client(profile: &EndpointProfile, debug_logging: bool) -> reqwest::Client {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &profile.headers {
+        match (
+            HeaderName::from_bytes(name.trim().as_bytes()),
+            HeaderValue::from_str(value.trim()),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => {
+                if debug_logging {
+                    println!(
+                        "Skipping invalid header '{}' in profile '{}'",
+                        name, profile.name
+                    );
+                }
+            }
+        }
+    }
+
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .default_headers(headers)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Create the provider for an endpoint profile.
+/// Provider-specific settings (e.g. Ollama options) come from the configuration.
+pub fn create_provider(profile: &EndpointProfile, config: &Configuration) -> Box<dyn LlmProvider> {
+    let debug_logging = config.enable_debug_logging;
+    let client = build_client(profile, debug_logging);
+    let base_url = profile.api_base_url.clone();
+    let api_key = profile.get_api_key();
+
+    match profile.provider {
+        ProviderKind::OpenAi => Box::new(OpenAiProvider::new(
+            client,
+            base_url,
+            api_key,
+            debug_logging,
+        )),
+        ProviderKind::Anthropic => Box::new(AnthropicProvider::new(
+            client,
+            base_url,
+            api_key,
+            debug_logging,
+        )),
+        ProviderKind::Ollama => Box::new(OllamaProvider::new(
+            client,
+            base_url,
+            api_key,
+            config.ollama_keep_alive.clone(),
+            config.ollama_num_ctx,
+            debug_logging,
+        )),
+        ProviderKind::Gemini => Box::new(GeminiProvider::new(
+            client,
+            base_url,
+            api_key,
+            debug_logging,
+        )),
+    }
+}
+
+/// Check the response status, turning non-success responses into `ProviderError::Api`.
+/// `label` is used for debug logging (e.g. "LLM", "Image Generation").
+pub(crate) async fn check_status(
+    response: reqwest::Response,
+    label: &str,
+    debug_logging: bool,
+) -> Result<reqwest::Response, ProviderError> {
+    let status = response.status();
+    if debug_logging {
+        println!("--- {} Response ---", label);
+        println!("Status: {}", status);
+    }
+
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(retry::parse_retry_after);
+
+    let body = response.text().await.unwrap_or_default();
+    if debug_logging {
+        println!("Error Body: {}", body);
+        println!("--------------------");
+    }
+    Err(ProviderError::Api {
+        status,
+        body,
+        retry_after,
+    })
+}
+
+/// Read a Server-Sent Events body and call `handle` with the data of every event.
+/// `error` events and `{"error":...}` payloads sent mid-stream fail the stream.
+/// Cancellation is checked between network chunks.
+pub(crate) async fn for_each_sse_data<F>(
+    response: reqwest::Response,
+    sink: &mut dyn StreamSink,
+    debug_logging: bool,
+    mut handle: F,
+) -> Result<(), ProviderError>
+where
+    F: FnMut(&str, &mut dyn StreamSink) -> Result<(), ProviderError> + Send,
+{
+    let mut stream = response.bytes_stream();
+    let mut decoder = SseDecoder::new();
+
+    let mut dispatch = |event: SseEvent, sink: &mut dyn StreamSink| {
+        if let Some(message) = sse::stream_error(&event) {
+            if debug_logging {
+                println!("Stream error event: {}", event.data);
+            }
+            return Err(ProviderError::Stream(message));
+        }
+        handle(&event.data, sink)
+    };
+
+    while let Some(chunk_result) = stream.next().await {
+        if sink.is_cancelled() {
+            return Err(ProviderError::Cancelled);
+        }
+
+        let chunk = chunk_result.map_err(|e| {
+            if debug_logging {
+                println!("Stream error: {}", e);
+            }
+            ProviderError::Stream(e.to_string())
+        })?;
+
+        for event in decoder.feed(&chunk) {
+            dispatch(event, sink)?;
+        }
+    }
+
+    // The last event may not be followed by a blank line
+    if let Some(event) = decoder.finish() {
+        dispatch(event, sink)?;
+    }
+
+    Ok(())
+}
+
+/// Read a newline-delimited JSON body and call `handle` with every non-empty line.
+/// Lines are split on raw bytes so multibyte characters spanning chunks stay intact.
+pub(crate) async fn for_each_ndjson_line<F>(
+    response: reqwest::Response,
+    sink: &mut dyn StreamSink,
+    debug_logging: bool,
+    mut handle: F,
+) -> Result<(), ProviderError>
+where
+    F: FnMut(&str, &mut dyn StreamSink) -> Result<(), ProviderError> + Send,
+{
+    let mut stream = response.bytes_stream();
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk_result) = stream.next().await {
+        if sink.is_cancelled() {
+            return Err(ProviderError::Cancelled);
+        }
+
+        let chunk = chunk_result.map_err(|e| {
+            if debug_logging {
+                println!("Stream error: {}", e);
+            }
+            ProviderError::Stream(e.to_string())
+        })?;
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(newline_pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline_pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() {
+                handle(line, sink)?;
+            }
+        }
+    }
+
+    // The last object may not be newline-terminated
+    let rest = String::from_utf8_lossy(&buffer);
+    let rest = rest.trim();
+    if !rest.is_empty() {
+        handle(rest, sink)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{mock_server, CollectSink};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_profile_headers_are_sent() {
+        let (base_url, server) = mock_server(r#"{"data":[]}"#, "application/json").await;
+
+        let mut profile = EndpointProfile {
+            api_base_url: format!("{}/v1", base_url),
+            plaintext_api_key: Some("sk-test".to_string()),
+            ..Default::default()
+        };
+        profile.headers.insert(
+            "HTTP-Referer".to_string(),
+            "https://example.com".to_string(),
+        );
+        profile
+            .headers
+            .insert("bad header".to_string(), "ignored".to_string());
+
+        let provider = create_provider(&profile, &Configuration::default());
+        provider.list_models().await.unwrap();
+
+        let raw_request = server.await.unwrap().to_ascii_lowercase();
+        assert!(raw_request.contains("http-referer: https://example.com"));
+        assert!(raw_request.contains("authorization: bearer sk-test"));
+        assert!(!raw_request.contains("ignored"));
+    }
+
+    #[tokio::test]
+    async fn test_mid_stream_error_payload_fails_the_stream() {
+        let sse = "data: {\"choices\":[{\"delta\":{\"content\":\"Olá\"}}]}\r\n\r\n\
+                   : keep-alive\r\n\r\n\
+                   data: {\"error\":{\"message\":\"Upstream timeout\",\"type\":\"server_error\"}}\r\n\r\n";
+        let (base_url, _server) = mock_server(sse, "text/event-stream").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let request = ChatRequest {
+            model: "gpt-4o-mini".to_string(),
+            messages: vec![ChatMessage::user("Hi")],
+            params: GenerationParams::default(),
+            response_schema: None,
+            tools: Vec::new(),
+        };
+        let mut sink = CollectSink::default();
+        let err = provider.chat_stream(&request, &mut sink).await.unwrap_err();
+
+        assert_eq!(sink.chunks, vec!["Olá"]);
+        assert_eq!(
+            err.to_string(),
+            "Stream error: server_error: Upstream timeout"
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! Ollama provider
+//! Talks to a local Ollama server through its native API (`/api/chat`, `/api/tags`, `/api/pull`)
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    check_status, for_each_ndjson_line, ChatRequest, ChatResponse, LlmProvider, ProviderError,
+    StreamSink, ToolCall, Usage,
+};
+
+/// Tags (local models) response from `/api/tags`
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagInfo {
+    name: String,
+}
+
+/// Progress update while pulling a model (emitted to the frontend)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullProgress {
+    pub model: String,
+    pub status: String,
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// Provider for a native Ollama server
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    base_url: String,
+    /// Optional bearer token (only needed behind an authenticating proxy)
+    api_key: String,
+    keep_alive: String,
+    num_ctx: u32,
+    debug_logging: bool,
+}
+
+impl OllamaProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: String,
+        api_key: String,
+        keep_alive: String,
+        num_ctx: u32,
+        debug_logging: bool,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            keep_alive,
+            num_ctx,
+            debug_logging,
+        }
+    }
+
+    /// Build the API URL. A trailing `/v1` (the OpenAI shim path) is dropped.
+    fn build_api_url(&self, endpoint: &str) -> String {
+        let base = self.base_url.trim_end_matches('/');
+        let base = base.strip_suffix("/v1").unwrap_or(base);
+        format!("{}{}", base, endpoint)
+    }
+
+    /// Build an `/api/chat` body
+    fn chat_body(&self, request: &ChatRequest, stream: bool) -> Value {
+        let messages: Vec<Value> = request
+            .messages
+            .iter()
+            .map(|m| {
+                let mut message = json!({"role": m.role, "content": m.content});
+                // Ollama takes bare base64 images next to the text
+                if !m.images.is_empty() {
+                    message["images"] =
+                        json!(m.images.iter().map(|image| &image.data).collect::<Vec<_>>());
+                }
+                // Tool calls carry no ids; results name the tool instead
+                if !m.tool_calls.is_empty() {
+                    let calls: Vec<Value> = m
+                        .tool_calls
+                        .iter()
+                        .map(|call| {
+                            json!({"function": {"name": call.name, "arguments": call.arguments}})
+                        })
+                        .collect();
+                    message["tool_calls"] = json!(calls);
+                }
+                if let Some(ref call) = m.tool_call {
+                    message["tool_name"] = json!(call.name);
+                }
+                message
+            })
+            .collect();
+
+        let params = &request.params;
+        let mut options = json!({});
+        if let Some(temperature) = params.temperature {
+            options["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = params.top_p {
+            options["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            options["num_predict"] = json!(max_tokens);
+        }
+        if !params.stop.is_empty() {
+            options["stop"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            options["seed"] = json!(seed);
+        }
+        if self.num_ctx > 0 {
+            options["num_ctx"] = json!(self.num_ctx);
+        }
+
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages,
+            "stream": stream,
+            "options": options
+        });
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters
+                        }
+                    })
+                })
+                .collect();
+            body["tools"] = json!(tools);
+        }
+        if !self.keep_alive.is_empty() {
+            // Ollama accepts either a duration string ("5m") or a number of seconds (-1 = forever)
+            body["keep_alive"] = match self.keep_alive.parse::<i64>() {
+                Ok(seconds) => json!(seconds),
+                Err(_) => json!(self.keep_alive),
+            };
+        }
+        if let Some(ref effort) = params.reasoning_effort {
+            // Ollama only switches thinking on or off
+            body["think"] = json!(effort != "minimal");
+        }
+        if let Some(ref schema) = request.response_schema {
+            // Structured outputs: `format` takes the schema itself
+            body["format"] = schema.schema.clone();
+        }
+        params.apply_extra_body(&mut body);
+        body
+    }
+
+    /// Tool calls of a response message, numbered since Ollama gives them no ids
+    fn tool_calls(message: &Value) -> Vec<ToolCall> {
+        message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .enumerate()
+                    .map(|(index, call)| ToolCall {
+                        id: format!("call_{}", index),
+                        name: call["function"]["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        arguments: call["function"]["arguments"].clone(),
+                        signature: None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Token counts of the final (`done`) message
+    fn usage(data: &Value) -> Option<Usage> {
+        let completion = data["eval_count"].as_u64()?;
+        // Omitted when the whole prompt was served from the KV cache
+        let prompt = data["prompt_eval_count"].as_u64().unwrap_or(0);
+        Some(Usage::new(prompt as u32, completion as u32))
+    }
+
+    /// Send a request (with the optional bearer token) and check the response status
+    async fn send(
+        &self,
+        builder: reqwest::RequestBuilder,
+        label: &str,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let builder = if self.api_key.is_empty() {
+            builder
+        } else {
+            builder.header("Authorization", format!("Bearer {}", self.api_key))
+        };
+
+        let response = builder.send().await.map_err(|e| {
+            if self.debug_logging {
+                println!("Request Failed: {}", e);
+                println!("--------------------");
+            }
+            ProviderError::Request(format!("{} (is Ollama running at {}?)", e, self.base_url))
+        })?;
+
+        check_status(response, label, self.debug_logging).await
+    }
+
+    /// Pull a model, reporting progress for every status line Ollama sends
+    pub async fn pull_model<F>(&self, model: &str, mut on_progress: F) -> Result<(), ProviderError>
+    where
+        F: FnMut(PullProgress) + Send,
+    {
+        let url = self.build_api_url("/api/pull");
+        let body = json!({"model": model, "stream": true});
+
+        if self.debug_logging {
+            println!("--- Ollama Pull Request ---");
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", model);
+            println!("---------------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Ollama Pull")
+            .await?;
+
+        // Pulls are not cancellable from the UI; use a sink that never cancels
+        let mut sink = NoopSink;
+        for_each_ndjson_line(resp, &mut sink, self.debug_logging, |line, _| {
+            let Ok(json) = serde_json::from_str::<Value>(line) else {
+                return Ok(());
+            };
+            if let Some(error) = json["error"].as_str() {
+                return Err(ProviderError::Stream(error.to_string()));
+            }
+            on_progress(PullProgress {
+                model: model.to_string(),
+                status: json["status"].as_str().unwrap_or_default().to_string(),
+                completed: json["completed"].as_u64(),
+                total: json["total"].as_u64(),
+            });
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Sink used where no streaming output is forwarded
+struct NoopSink;
+
+impl StreamSink for NoopSink {
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    fn on_content(&mut self, _content: &str) {}
+
+    fn on_done(&mut self) {}
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+        let body = self.chat_body(request, false);
+        let url = self.build_api_url("/api/chat");
+
+        if self.debug_logging {
+            println!("--- Ollama Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!(
+                "Body: {}",
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            println!("-------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Ollama")
+            .await?;
+
+        let text = resp.text().await.unwrap_or_default();
+        if self.debug_logging {
+            println!("Body: {}", text);
+            println!("--------------------");
+        }
+
+        let data: Value = serde_json::from_str(&text).map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })?;
+
+        if let Some(error) = data["error"].as_str() {
+            return Err(ProviderError::InvalidResponse(error.to_string()));
+        }
+
+        match data["message"]["content"].as_str() {
+            Some(content) => Ok(ChatResponse {
+                content: content.to_string(),
+                // Thinking models report their reasoning separately when `think` is enabled
+                reasoning: data["message"]["thinking"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                usage: Self::usage(&data),
+                truncated: data["done_reason"] == "length",
+                tool_calls: Self::tool_calls(&data["message"]),
+            }),
+            None => Err(ProviderError::InvalidResponse(
+                "No content in response".to_string(),
+            )),
+        }
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        sink: &mut dyn StreamSink,
+    ) -> Result<ChatResponse, ProviderError> {
+        let body = self.chat_body(request, true);
+        let url = self.build_api_url("/api/chat");
+
+        if self.debug_logging {
+            println!("--- Ollama Streaming Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!("-------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Ollama Streaming")
+            .await?;
+
+        let mut full_content = String::new();
+        let mut full_reasoning = String::new();
+        let mut usage = None;
+        let mut truncated = false;
+        for_each_ndjson_line(resp, sink, self.debug_logging, |line, sink| {
+            let Ok(json) = serde_json::from_str::<Value>(line) else {
+                return Ok(());
+            };
+
+            if let Some(error) = json["error"].as_str() {
+                return Err(ProviderError::Stream(error.to_string()));
+            }
+
+            if let Some(thinking) = json["message"]["thinking"].as_str() {
+                if !thinking.is_empty() {
+                    full_reasoning.push_str(thinking);
+                    sink.on_reasoning(thinking);
+                }
+            }
+
+            if let Some(content) = json["message"]["content"].as_str() {
+                if !content.is_empty() {
+                    full_content.push_str(content);
+                    sink.on_content(content);
+                }
+            }
+
+            if json["done"].as_bool().unwrap_or(false) {
+                usage = Self::usage(&json);
+                truncated = json["done_reason"] == "length";
+                sink.on_done();
+            }
+            Ok(())
+        })
+        .await?;
+
+        if self.debug_logging {
+            println!("Full streamed content length: {}", full_content.len());
+            println!("--------------------");
+        }
+
+        Ok(ChatResponse {
+            content: full_content,
+            reasoning: full_reasoning,
+            usage,
+            truncated,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        let url = self.build_api_url("/api/tags");
+
+        if self.debug_logging {
+            println!("--- Fetching Models ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: GET {}", url);
+            println!("-----------------------");
+        }
+
+        let resp = self.send(self.client.get(&url), "Models").await?;
+
+        let tags = resp.json::<TagsResponse>().await.map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse models: {}", e))
+        })?;
+
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{mock_server, CollectSink};
+    use crate::providers::{ChatMessage, GenerationParams};
+
+    fn provider(base_url: String) -> OllamaProvider {
+        OllamaProvider::new(
+            reqwest::Client::new(),
+            base_url,
+            String::new(),
+            "10m".to_string(),
+            8192,
+            false,
+        )
+    }
+
+    fn test_request() -> ChatRequest {
+        ChatRequest {
+            model: "llama3".to_string(),
+            messages: vec![ChatMessage::system("Be brief."), ChatMessage::user("Olá")],
+            params: GenerationParams {
+                max_tokens: Some(256),
+                seed: Some(42),
+                ..Default::default()
+            },
+            response_schema: None,
+            tools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_openai_shim_suffix_is_dropped() {
+        let provider = provider("http://localhost:11434/v1/".to_string());
+        assert_eq!(
+            provider.build_api_url("/api/chat"),
+            "http://localhost:11434/api/chat"
+        );
+    }
+
+    #[test]
+    fn test_chat_body_options() {
+        let body = provider(String::new()).chat_body(&test_request(), true);
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["keep_alive"], "10m");
+        assert_eq!(body["options"]["num_ctx"], 8192);
+        assert_eq!(body["options"]["num_predict"], 256);
+        assert_eq!(body["options"]["seed"], 42);
+        assert!(body["options"].get("temperature").is_none());
+        assert!(body.get("think").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_streaming_against_mock_server() {
+        let ndjson = "{\"message\":{\"role\":\"assistant\",\"content\":\"Olá\"},\"done\":false}\n\
+                      {\"message\":{\"role\":\"assistant\",\"content\":\", mundo\"},\"done\":false}\n\
+                      {\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"prompt_eval_count\":11,\"eval_count\":4}";
+        let (base_url, server) = mock_server(ndjson, "application/x-ndjson").await;
+
+        let mut sink = CollectSink::default();
+        let response = provider(base_url)
+            .chat_stream(&test_request(), &mut sink)
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Olá, mundo");
+        assert_eq!(response.usage, Some(Usage::new(11, 4)));
+        assert_eq!(sink.chunks, vec!["Olá", ", mundo"]);
+        assert!(sink.done);
+
+        let raw_request = server.await.unwrap();
+        assert!(raw_request.starts_with("POST /api/chat"));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_from_tags() {
+        let tags =
+            r#"{"models":[{"name":"llama3:latest","size":1},{"name":"qwen2.5:7b","size":2}]}"#;
+        let (base_url, _server) = mock_server(tags, "application/json").await;
+
+        let models = provider(base_url).list_models().await.unwrap();
+        assert_eq!(models, vec!["llama3:latest", "qwen2.5:7b"]);
+    }
+}
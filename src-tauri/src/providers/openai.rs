@@ -0,0 +1,1008 @@
+//! OpenAI-compatible provider
+//! Talks to any endpoint implementing the OpenAI REST API (OpenAI, OpenRouter, LiteLLM, vLLM, ...)
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::multipart;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{
+    check_status, for_each_sse_data, ChatMessage, ChatRequest, ChatResponse, ImageEditRequest,
+    ImageRequest, ImageResponse, ImageVariationRequest, LlmProvider, ProviderError, SpeechRequest,
+    StreamSink, ToolCall, TranscriptionRequest, Usage,
+};
+use crate::tokens::normalize_model;
+
+/// Models response from API
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+}
+
+/// Provider for OpenAI-compatible endpoints
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    debug_logging: bool,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: String,
+        api_key: String,
+        debug_logging: bool,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            debug_logging,
+        }
+    }
+
+    /// Build the correct API URL for the given endpoint path
+    fn build_api_url(&self, endpoint: &str) -> String {
+        let base = self.base_url.trim_end_matches('/');
+
+        // Check if the base URL already ends with /v1 or similar API version
+        // If not, and it doesn't already contain the endpoint, add /v1
+        let needs_v1 = !base.ends_with("/v1")
+            && !base.ends_with("/v1/")
+            && !base.contains("/chat/")
+            && !base.contains("/images/")
+            && !base.contains("/audio/")
+            && !base.contains("/models");
+
+        if needs_v1 {
+            format!("{}/v1{}", base, endpoint)
+        } else {
+            format!("{}{}", base, endpoint)
+        }
+    }
+
+    /// Message content: plain text, or a content array when images are attached
+    fn message_content(message: &ChatMessage) -> Value {
+        if message.images.is_empty() {
+            return json!(message.content);
+        }
+        let mut parts = vec![json!({"type": "text", "text": message.content})];
+        parts.extend(
+            message
+                .images
+                .iter()
+                .map(|image| json!({"type": "image_url", "image_url": {"url": image.data_uri()}})),
+        );
+        json!(parts)
+    }
+
+    /// A message in the chat completions format, including tool calls and results
+    fn message(message: &ChatMessage) -> Value {
+        if let Some(ref call) = message.tool_call {
+            return json!({"role": "tool", "tool_call_id": call.id, "content": message.content});
+        }
+        let mut value = json!({"role": message.role, "content": Self::message_content(message)});
+        if !message.tool_calls.is_empty() {
+            let calls: Vec<Value> = message
+                .tool_calls
+                .iter()
+                .map(|call| {
+                    json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {"name": call.name, "arguments": call.arguments.to_string()}
+                    })
+                })
+                .collect();
+            value["tool_calls"] = json!(calls);
+        }
+        value
+    }
+
+    /// Tool calls of a response message; their arguments arrive as a JSON string
+    fn tool_calls(message: &Value) -> Vec<ToolCall> {
+        message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .map(|call| ToolCall {
+                        id: call["id"].as_str().unwrap_or_default().to_string(),
+                        name: call["function"]["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        arguments: call["function"]["arguments"]
+                            .as_str()
+                            .and_then(|arguments| serde_json::from_str(arguments).ok())
+                            .unwrap_or_else(|| json!({})),
+                        signature: None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Build a chat completions body
+    fn chat_body(request: &ChatRequest, stream: bool) -> Value {
+        let messages: Vec<Value> = request.messages.iter().map(Self::message).collect();
+
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages
+        });
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters
+                        }
+                    })
+                })
+                .collect();
+            body["tools"] = json!(tools);
+        }
+
+        let params = &request.params;
+        let reasoning_model = Self::is_reasoning_model(&request.model);
+        // Reasoning models reject sampling parameters
+        if !reasoning_model {
+            if let Some(temperature) = params.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            if let Some(top_p) = params.top_p {
+                body["top_p"] = json!(top_p);
+            }
+        }
+        if let Some(max_tokens) = params.max_tokens {
+            // Reasoning models reject `max_tokens` in favor of `max_completion_tokens`
+            let key = if reasoning_model {
+                "max_completion_tokens"
+            } else {
+                "max_tokens"
+            };
+            body[key] = json!(max_tokens);
+        }
+        if !params.stop.is_empty() {
+            body["stop"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            body["seed"] = json!(seed);
+        }
+        if let Some(ref effort) = params.reasoning_effort {
+            body["reasoning_effort"] = json!(effort);
+        }
+        if let Some(ref schema) = request.response_schema {
+            // Not strict: strict mode rejects common schemas, and the answer is validated anyway
+            body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {"name": schema.name, "schema": schema.schema, "strict": false}
+            });
+        }
+        if stream {
+            body["stream"] = json!(true);
+            body["stream_options"] = json!({"include_usage": true});
+        }
+        params.apply_extra_body(&mut body);
+        body
+    }
+
+    /// Parse the `usage` object of a response or final stream chunk
+    fn usage(data: &Value) -> Option<Usage> {
+        let usage = data.get("usage").filter(|u| u.is_object())?;
+        Some(Usage::new(
+            usage["prompt_tokens"].as_u64()? as u32,
+            usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
+        ))
+    }
+
+    /// Reasoning of a message or delta: `reasoning_content` (DeepSeek, vLLM) or `reasoning` (OpenRouter)
+    fn reasoning(message: &Value) -> Option<&str> {
+        message["reasoning_content"]
+            .as_str()
+            .or_else(|| message["reasoning"].as_str())
+    }
+
+    /// o-series and GPT-5 models, which take `max_completion_tokens` and no sampling parameters
+    fn is_reasoning_model(model: &str) -> bool {
+        let model = normalize_model(model);
+        ["o1", "o3", "o4", "gpt-5"]
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+    }
+
+    /// PNG file part of an image edit or variation form
+    fn png_part(bytes: &[u8], file_name: &str) -> Result<multipart::Part, ProviderError> {
+        multipart::Part::bytes(bytes.to_vec())
+            .file_name(file_name.to_string())
+            .mime_str("image/png")
+            .map_err(|e| ProviderError::Request(e.to_string()))
+    }
+
+    /// MIME type of an audio upload, from its file extension
+    fn audio_mime(file_name: &str) -> &'static str {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "mp3" | "mpga" | "mpeg" => "audio/mpeg",
+            "wav" => "audio/wav",
+            "m4a" | "mp4" => "audio/mp4",
+            "ogg" => "audio/ogg",
+            "webm" => "audio/webm",
+            "flac" => "audio/flac",
+            "aac" => "audio/aac",
+            "wma" => "audio/x-ms-wma",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// Read the images of an image generation, edit or variation response. Images given
+    /// as a URL are downloaded at once, as signed URLs expire quickly.
+    async fn image_response(
+        &self,
+        resp: reqwest::Response,
+    ) -> Result<ImageResponse, ProviderError> {
+        let text = resp.text().await.unwrap_or_default();
+        if self.debug_logging {
+            // Base64 images make the body too large to print
+            println!("Body: {} bytes", text.len());
+            println!("-------------------------------");
+        }
+
+        let data: Value = serde_json::from_str(&text).map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })?;
+
+        let mut images = Vec::new();
+        for item in data["data"].as_array().into_iter().flatten() {
+            if let Some(encoded) = item["b64_json"].as_str() {
+                let bytes = STANDARD.decode(encoded).map_err(|e| {
+                    ProviderError::InvalidResponse(format!("Invalid base64 image: {}", e))
+                })?;
+                images.push(bytes);
+            } else if let Some(url) = item["url"].as_str() {
+                images.push(self.download_image(url).await?);
+            }
+        }
+
+        if images.is_empty() {
+            return Err(ProviderError::InvalidResponse(
+                "No image in response".to_string(),
+            ));
+        }
+        Ok(ImageResponse { images })
+    }
+
+    /// Download a generated image (signed URLs need no API key)
+    async fn download_image(&self, url: &str) -> Result<Vec<u8>, ProviderError> {
+        if self.debug_logging {
+            println!("Downloading image: {}", url);
+        }
+
+        let resp = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Request(format!("Failed to download image: {}", e)))?;
+        let resp = check_status(resp, "Image Download", self.debug_logging).await?;
+
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| ProviderError::Request(format!("Failed to read image: {}", e)))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Post an image edit or variation form
+    async fn send_image_form(
+        &self,
+        endpoint: &str,
+        form: multipart::Form,
+        label: &str,
+        model: &str,
+    ) -> Result<ImageResponse, ProviderError> {
+        let url = self.build_api_url(endpoint);
+
+        if self.debug_logging {
+            println!("--- {} Request ---", label);
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", model);
+            println!("(Multipart form data not logged)");
+            println!("------------------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).multipart(form), label)
+            .await?;
+        self.image_response(resp).await
+    }
+
+    /// Send a request with the bearer token and check the response status
+    async fn send(
+        &self,
+        builder: reqwest::RequestBuilder,
+        label: &str,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = builder
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await
+            .map_err(|e| {
+                if self.debug_logging {
+                    println!("Request Failed: {}", e);
+                    println!("--------------------");
+                }
+                ProviderError::Request(e.to_string())
+            })?;
+
+        check_status(response, label, self.debug_logging).await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible"
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ProviderError> {
+        let body = Self::chat_body(request, false);
+        let url = self.build_api_url("/chat/completions");
+
+        if self.debug_logging {
+            println!("--- LLM Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!(
+                "API Key (first 8 chars): {}...",
+                self.api_key.chars().take(8).collect::<String>()
+            );
+            println!(
+                "Body: {}",
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            println!("-------------------");
+        }
+
+        if self.api_key.is_empty() {
+            return Err(ProviderError::MissingApiKey);
+        }
+
+        let resp = self.send(self.client.post(&url).json(&body), "LLM").await?;
+
+        let text = resp.text().await.unwrap_or_default();
+        if self.debug_logging {
+            println!("Body: {}", text);
+            println!("--------------------");
+        }
+
+        let data: Value = serde_json::from_str(&text).map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse response: {}", e))
+        })?;
+
+        let message = &data["choices"][0]["message"];
+        let tool_calls = Self::tool_calls(message);
+        let content = match message["content"].as_str() {
+            Some(content) => content,
+            // Content is null when the model only calls tools
+            None if !tool_calls.is_empty() => "",
+            None => {
+                return Err(ProviderError::InvalidResponse(
+                    "No content in response".to_string(),
+                ))
+            }
+        };
+
+        Ok(ChatResponse {
+            content: content.to_string(),
+            reasoning: Self::reasoning(message).unwrap_or_default().to_string(),
+            usage: Self::usage(&data),
+            truncated: data["choices"][0]["finish_reason"] == "length",
+            tool_calls,
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        sink: &mut dyn StreamSink,
+    ) -> Result<ChatResponse, ProviderError> {
+        let body = Self::chat_body(request, true);
+        let url = self.build_api_url("/chat/completions");
+
+        if self.debug_logging {
+            println!("--- LLM Streaming Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!("-------------------");
+        }
+
+        if self.api_key.is_empty() {
+            return Err(ProviderError::MissingApiKey);
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "LLM Streaming")
+            .await?;
+
+        let mut full_content = String::new();
+        let mut full_reasoning = String::new();
+        let mut usage = None;
+        let mut truncated = false;
+        for_each_sse_data(resp, sink, self.debug_logging, |data, sink| {
+            if data == "[DONE]" {
+                sink.on_done();
+                return Ok(());
+            }
+
+            if let Ok(json) = serde_json::from_str::<Value>(data) {
+                if let Some(reasoning) = Self::reasoning(&json["choices"][0]["delta"]) {
+                    full_reasoning.push_str(reasoning);
+                    sink.on_reasoning(reasoning);
+                }
+                if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
+                    full_content.push_str(content);
+                    sink.on_content(content);
+                }
+                if json["choices"][0]["finish_reason"] == "length" {
+                    truncated = true;
+                }
+                // Sent in a final chunk with empty `choices` (requested via `stream_options`)
+                usage = Self::usage(&json).or(usage);
+            }
+            Ok(())
+        })
+        .await?;
+
+        if self.debug_logging {
+            println!("Full streamed content length: {}", full_content.len());
+            println!("--------------------");
+        }
+
+        Ok(ChatResponse {
+            content: full_content,
+            reasoning: full_reasoning,
+            usage,
+            truncated,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    async fn generate_image(&self, request: &ImageRequest) -> Result<ImageResponse, ProviderError> {
+        let mut body = json!({
+            "model": request.model,
+            "prompt": request.prompt,
+            "size": request.size,
+            "n": request.n
+        });
+        // Left out unless the model accepts them
+        if let Some(ref quality) = request.quality {
+            body["quality"] = json!(quality);
+        }
+        if let Some(ref style) = request.style {
+            body["style"] = json!(style);
+        }
+        if let Some(ref format) = request.response_format {
+            body["response_format"] = json!(format);
+        }
+
+        let url = self.build_api_url("/images/generations");
+
+        if self.debug_logging {
+            println!("--- Image Generation Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!(
+                "Body: {}",
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            println!("------------------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Image Generation")
+            .await?;
+        self.image_response(resp).await
+    }
+
+    async fn edit_image(&self, request: &ImageEditRequest) -> Result<ImageResponse, ProviderError> {
+        let mut form = multipart::Form::new()
+            .part("image", Self::png_part(&request.image, "image.png")?)
+            .text("model", request.model.clone())
+            .text("prompt", request.prompt.clone())
+            .text("size", request.size.clone())
+            .text("n", request.n.to_string());
+
+        if let Some(ref mask) = request.mask {
+            form = form.part("mask", Self::png_part(mask, "mask.png")?);
+        }
+        if let Some(ref format) = request.response_format {
+            form = form.text("response_format", format.clone());
+        }
+
+        self.send_image_form("/images/edits", form, "Image Edit", &request.model)
+            .await
+    }
+
+    async fn vary_image(
+        &self,
+        request: &ImageVariationRequest,
+    ) -> Result<ImageResponse, ProviderError> {
+        let mut form = multipart::Form::new()
+            .part("image", Self::png_part(&request.image, "image.png")?)
+            .text("model", request.model.clone())
+            .text("size", request.size.clone())
+            .text("n", request.n.to_string());
+
+        if let Some(ref format) = request.response_format {
+            form = form.text("response_format", format.clone());
+        }
+
+        self.send_image_form(
+            "/images/variations",
+            form,
+            "Image Variation",
+            &request.model,
+        )
+        .await
+    }
+
+    async fn transcribe(&self, request: &TranscriptionRequest) -> Result<String, ProviderError> {
+        let file_part = multipart::Part::bytes(request.file_bytes.clone())
+            .file_name(request.file_name.clone())
+            .mime_str(Self::audio_mime(&request.file_name))
+            .map_err(|e| ProviderError::Request(e.to_string()))?;
+
+        let mut form = multipart::Form::new()
+            .part("file", file_part)
+            .text("model", request.model.clone())
+            .text("response_format", "text");
+
+        if let Some(ref language) = request.language {
+            form = form.text("language", language.clone());
+        }
+
+        let url = self.build_api_url("/audio/transcriptions");
+
+        if self.debug_logging {
+            println!("--- Speech to Text Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!("Model: {}", request.model);
+            println!("(Multipart form data not logged)");
+            println!("------------------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).multipart(form), "Speech to Text")
+            .await?;
+
+        let text = resp.text().await.unwrap_or_default();
+        if self.debug_logging {
+            println!("Body: {}", text);
+            println!("-------------------------------");
+        }
+
+        // Try to parse as JSON first (some APIs return JSON)
+        if let Ok(json) = serde_json::from_str::<Value>(&text) {
+            if let Some(transcript) = json["text"].as_str() {
+                return Ok(transcript.to_string());
+            }
+        }
+
+        // Otherwise treat as plain text
+        Ok(text)
+    }
+
+    async fn speech(&self, request: &SpeechRequest) -> Result<Vec<u8>, ProviderError> {
+        let body = json!({
+            "model": request.model,
+            "input": request.input,
+            "voice": request.voice,
+            "response_format": request.format,
+            "speed": request.speed,
+            "language": request.language
+        });
+
+        let url = self.build_api_url("/audio/speech");
+
+        if self.debug_logging {
+            println!("--- Text to Speech Request ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: POST {}", url);
+            println!(
+                "Body: {}",
+                serde_json::to_string_pretty(&body).unwrap_or_default()
+            );
+            println!("------------------------------");
+        }
+
+        let resp = self
+            .send(self.client.post(&url).json(&body), "Text to Speech")
+            .await?;
+
+        let bytes = resp.bytes().await.map_err(|e| {
+            if self.debug_logging {
+                println!("Failed to read audio data: {}", e);
+                println!("-------------------------------");
+            }
+            ProviderError::InvalidResponse(format!("Failed to read audio data: {}", e))
+        })?;
+
+        if self.debug_logging {
+            println!("Received {} bytes of audio data", bytes.len());
+            println!("-------------------------------");
+        }
+
+        Ok(bytes.to_vec())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        let url = self.build_api_url("/models");
+
+        if self.debug_logging {
+            println!("--- Fetching Models ---");
+            println!("Config API Base URL: {}", self.base_url);
+            println!("Final URL: GET {}", url);
+            println!("-----------------------");
+        }
+
+        let resp = self.send(self.client.get(&url), "Models").await?;
+
+        let models = resp.json::<ModelsResponse>().await.map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse models: {}", e))
+        })?;
+
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::test_support::{mock_server, CollectSink};
+    use crate::providers::{GenerationParams, ImageAttachment, ResponseSchema, ToolDefinition};
+
+    fn test_request(model: &str, params: GenerationParams) -> ChatRequest {
+        ChatRequest {
+            model: model.to_string(),
+            messages: vec![ChatMessage::user("Hello")],
+            params,
+            response_schema: None,
+            tools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_chat_body_omits_unset_params() {
+        let body = OpenAiProvider::chat_body(
+            &test_request("gpt-4o-mini", GenerationParams::default()),
+            false,
+        );
+        assert_eq!(
+            body,
+            json!({"model": "gpt-4o-mini", "messages": [{"role": "user", "content": "Hello"}]})
+        );
+    }
+
+    #[test]
+    fn test_chat_body_maps_params() {
+        let params = GenerationParams {
+            temperature: Some(0.5),
+            max_tokens: Some(300),
+            stop: vec!["END".to_string()],
+            seed: Some(7),
+            reasoning_effort: Some("low".to_string()),
+            extra_body: Some(json!({"provider": {"sort": "price"}})),
+            ..Default::default()
+        };
+
+        let body = OpenAiProvider::chat_body(&test_request("openai/o4-mini", params.clone()), true);
+        assert_eq!(body["max_completion_tokens"], 300);
+        assert!(body.get("max_tokens").is_none());
+        assert_eq!(body["stop"], json!(["END"]));
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["reasoning_effort"], "low");
+        assert_eq!(body["provider"]["sort"], "price");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["stream_options"]["include_usage"], true);
+
+        let body = OpenAiProvider::chat_body(&test_request("gpt-4o", params), false);
+        assert_eq!(body["max_tokens"], 300);
+        assert_eq!(body["temperature"], 0.5);
+        assert!(body.get("top_p").is_none());
+    }
+
+    #[test]
+    fn test_reasoning_models_get_no_sampling_params() {
+        let params = GenerationParams {
+            temperature: Some(0.2),
+            top_p: Some(0.9),
+            ..Default::default()
+        };
+        for model in ["o3-mini", "openai/gpt-5"] {
+            let body = OpenAiProvider::chat_body(&test_request(model, params.clone()), false);
+            assert!(body.get("temperature").is_none());
+            assert!(body.get("top_p").is_none());
+        }
+
+        let body = OpenAiProvider::chat_body(&test_request("gpt-4.1", params), false);
+        assert_eq!(body["top_p"], json!(0.9f32));
+    }
+
+    #[test]
+    fn test_response_schema_is_sent_as_response_format() {
+        let mut request = test_request("gpt-4o", GenerationParams::default());
+        request.response_schema = Some(ResponseSchema::new(
+            "Email fields",
+            json!({"type": "object", "properties": {"subject": {"type": "string"}}}),
+        ));
+
+        let body = OpenAiProvider::chat_body(&request, false);
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(
+            body["response_format"]["json_schema"]["name"],
+            "Email_fields"
+        );
+        assert_eq!(
+            body["response_format"]["json_schema"]["schema"]["properties"]["subject"]["type"],
+            "string"
+        );
+        // Nothing is added to the prompt
+        assert_eq!(body["messages"][0]["content"], "Hello");
+    }
+
+    #[test]
+    fn test_images_are_sent_as_data_uris() {
+        let mut request = test_request("gpt-4o", GenerationParams::default());
+        request.messages = vec![ChatMessage::user("Describe this").with_images(vec![
+            ImageAttachment {
+                media_type: "image/png".to_string(),
+                data: "iVBORw0KGgo=".to_string(),
+            },
+        ])];
+
+        let body = OpenAiProvider::chat_body(&request, false);
+        assert_eq!(
+            body["messages"][0]["content"],
+            json!([
+                {"type": "text", "text": "Describe this"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_round_trip() {
+        let body = r#"{"choices":[{"message":{"role":"assistant","content":null,"tool_calls":[{"id":"call_1","type":"function","function":{"name":"calculator","arguments":"{\"expression\":\"2+2\"}"}}]},"finish_reason":"tool_calls"}]}"#;
+        let (base_url, server) = mock_server(body, "application/json").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let mut request = test_request("gpt-4o", GenerationParams::default());
+        request.tools = vec![ToolDefinition {
+            name: "calculator".to_string(),
+            description: "Evaluate an expression".to_string(),
+            parameters: json!({"type": "object"}),
+        }];
+        let response = provider.chat(&request).await.unwrap();
+
+        assert_eq!(response.content, "");
+        let call = &response.tool_calls[0];
+        assert_eq!(call.id, "call_1");
+        assert_eq!(call.arguments, json!({"expression": "2+2"}));
+        let sent = server.await.unwrap();
+        let sent: Value = serde_json::from_str(sent.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(sent["tools"][0]["function"]["name"], "calculator");
+
+        // The call and its result are sent back in the next round
+        request
+            .messages
+            .push(ChatMessage::tool_calls("", response.tool_calls.clone()));
+        request.messages.push(ChatMessage::tool_result(call, "4"));
+        let body = OpenAiProvider::chat_body(&request, false);
+        assert_eq!(
+            body["messages"][1]["tool_calls"][0]["function"]["arguments"],
+            "{\"expression\":\"2+2\"}"
+        );
+        assert_eq!(
+            body["messages"][2],
+            json!({"role": "tool", "tool_call_id": "call_1", "content": "4"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamed_usage_and_finish_reason() {
+        let sse = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}],\"usage\":null}\n\n\
+                   data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}],\"usage\":null}\n\n\
+                   data: {\"choices\":[],\"usage\":{\"prompt_tokens\":8,\"completion_tokens\":1,\"total_tokens\":9}}\n\n\
+                   data: [DONE]\n\n";
+        let (base_url, _server) = mock_server(sse, "text/event-stream").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let mut sink = CollectSink::default();
+        let response = provider
+            .chat_stream(
+                &test_request("gpt-4o-mini", GenerationParams::default()),
+                &mut sink,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Hi");
+        assert_eq!(response.usage, Some(Usage::new(8, 1)));
+        assert!(response.truncated);
+        assert!(sink.done);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_reasoning_content() {
+        let sse = "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"Think\"}}]}\n\n\
+                   data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"ing\",\"content\":null}}]}\n\n\
+                   data: {\"choices\":[{\"delta\":{\"content\":\"Answer\"}}]}\n\n\
+                   data: [DONE]\n\n";
+        let (base_url, _server) = mock_server(sse, "text/event-stream").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let mut sink = CollectSink::default();
+        let response = provider
+            .chat_stream(
+                &test_request("deepseek-reasoner", GenerationParams::default()),
+                &mut sink,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Answer");
+        assert_eq!(response.reasoning, "Thinking");
+        assert_eq!(sink.chunks, vec!["Answer"]);
+        assert_eq!(sink.reasoning, vec!["Think", "ing"]);
+    }
+
+    #[tokio::test]
+    async fn test_image_edit_is_sent_as_multipart() {
+        let body = r#"{"data":[{"b64_json":"ZWRpdGVk"}]}"#;
+        let (base_url, server) = mock_server(body, "application/json").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let image = provider
+            .edit_image(&ImageEditRequest {
+                model: "dall-e-2".to_string(),
+                prompt: "Add a red hat".to_string(),
+                image: b"image-bytes".to_vec(),
+                mask: Some(b"mask-bytes".to_vec()),
+                size: "512x512".to_string(),
+                n: 1,
+                response_format: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(image.images, vec![b"edited".to_vec()]);
+        let sent = server.await.unwrap();
+        assert!(sent.starts_with("POST /v1/images/edits "), "{}", sent);
+        assert!(sent.contains("multipart/form-data; boundary="));
+        assert!(sent.contains("name=\"image\"; filename=\"image.png\""));
+        assert!(sent.contains("name=\"mask\"; filename=\"mask.png\""));
+        assert!(sent.contains("Add a red hat"));
+        assert!(!sent.contains("name=\"response_format\""));
+    }
+
+    #[tokio::test]
+    async fn test_audio_is_uploaded_with_its_content_type() {
+        let (base_url, server) = mock_server("Hello there.", "text/plain").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let transcript = provider
+            .transcribe(&TranscriptionRequest {
+                model: "whisper-1".to_string(),
+                file_bytes: b"RIFF-bytes".to_vec(),
+                file_name: "part-1.wav".to_string(),
+                language: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(transcript, "Hello there.");
+        let sent = server.await.unwrap().to_lowercase();
+        assert!(
+            sent.starts_with("post /v1/audio/transcriptions "),
+            "{}",
+            sent
+        );
+        assert!(
+            sent.contains("filename=\"part-1.wav\"\r\ncontent-type: audio/wav"),
+            "{}",
+            sent
+        );
+        assert_eq!(OpenAiProvider::audio_mime("Meeting.M4A"), "audio/mp4");
+        assert_eq!(OpenAiProvider::audio_mime("voice.mp3"), "audio/mpeg");
+    }
+
+    #[tokio::test]
+    async fn test_generated_images_are_decoded_or_downloaded() {
+        let (image_url, download) = mock_server("png-bytes", "image/png").await;
+        let body = format!(
+            r#"{{"data":[{{"b64_json":"Zmlyc3Q="}},{{"url":"{}/img/2.png?sig=abc"}}]}}"#,
+            image_url
+        );
+        let (base_url, server) = mock_server(body, "application/json").await;
+
+        let provider = OpenAiProvider::new(
+            reqwest::Client::new(),
+            format!("{}/v1", base_url),
+            "sk-test".to_string(),
+            false,
+        );
+        let response = provider
+            .generate_image(&ImageRequest {
+                model: "dall-e-3".to_string(),
+                prompt: "A lighthouse".to_string(),
+                size: "1024x1024".to_string(),
+                quality: Some("standard".to_string()),
+                style: None,
+                n: 2,
+                response_format: Some("b64_json".to_string()),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.images,
+            vec![b"first".to_vec(), b"png-bytes".to_vec()]
+        );
+        let sent = server.await.unwrap();
+        let sent: Value = serde_json::from_str(sent.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(sent["n"], 2);
+        assert_eq!(sent["response_format"], "b64_json");
+        assert_eq!(sent["quality"], "standard");
+        assert!(sent.get("style").is_none());
+        // Signed URLs are fetched without the API key
+        let download = download.await.unwrap();
+        assert!(download.starts_with("GET /img/2.png?sig=abc "));
+        assert!(!download.contains("Bearer"));
+    }
+}
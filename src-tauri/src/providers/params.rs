@@ -0,0 +1,274 @@
+//! Generation parameters for chat completions
+//! Set per operation or custom task and overridable per request; unset parameters are never sent
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Accepted `reasoning_effort` values
+const REASONING_EFFORTS: [&str; 4] = ["minimal", "low", "medium", "high"];
+
+/// Sampling and output parameters of a chat completion.
+/// Fields left as `None` (or empty) are omitted so the provider's own defaults apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Response length limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Stop sequences
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// One of [`REASONING_EFFORTS`], for models that think before answering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    /// JSON object merged into the provider's request body, for parameters we do not model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_body: Option<Value>,
+}
+
+impl GenerationParams {
+    /// Parameters with every field set in `overrides` replacing ours.
+    /// Extra bodies are merged rather than replaced.
+    pub fn merged(&self, overrides: &GenerationParams) -> GenerationParams {
+        let extra_body = match (&self.extra_body, &overrides.extra_body) {
+            (Some(base), Some(extra)) => {
+                let mut merged = base.clone();
+                merge_json(&mut merged, extra);
+                Some(merged)
+            }
+            (base, extra) => extra.clone().or_else(|| base.clone()),
+        };
+
+        GenerationParams {
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            stop: if overrides.stop.is_empty() {
+                self.stop.clone()
+            } else {
+                overrides.stop.clone()
+            },
+            seed: overrides.seed.or(self.seed),
+            reasoning_effort: overrides
+                .reasoning_effort
+                .clone()
+                .or_else(|| self.reasoning_effort.clone()),
+            extra_body,
+        }
+    }
+
+    /// Read per-request overrides from `LlmRequest.options`: `temperature`, `topP`, `maxTokens`,
+    /// `stop` (a JSON array or one sequence per line), `seed`, `reasoningEffort` and
+    /// `extraBody` (a JSON object). Other keys and empty values are ignored.
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self, String> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid value for '{}': {}", key, value))
+        }
+
+        let mut params = GenerationParams::default();
+        for (key, value) in options {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match key.as_str() {
+                "temperature" => params.temperature = Some(parse(key, value)?),
+                "topP" => params.top_p = Some(parse(key, value)?),
+                "maxTokens" => params.max_tokens = Some(parse(key, value)?),
+                "seed" => params.seed = Some(parse(key, value)?),
+                "reasoningEffort" => params.reasoning_effort = Some(value.to_lowercase()),
+                "stop" => {
+                    params.stop = if value.starts_with('[') {
+                        serde_json::from_str(value)
+                            .map_err(|e| format!("Invalid value for 'stop': {}", e))?
+                    } else {
+                        value
+                            .lines()
+                            .filter(|line| !line.trim().is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    }
+                }
+                "extraBody" => {
+                    params.extra_body = Some(
+                        serde_json::from_str(value)
+                            .map_err(|e| format!("Invalid value for 'extraBody': {}", e))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Check that every set parameter is within the range providers accept
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(format!(
+                    "Temperature must be between 0 and 2 (got {})",
+                    temperature
+                ));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(format!("Top P must be between 0 and 1 (got {})", top_p));
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err("Max tokens must be greater than 0".to_string());
+        }
+        if self.stop.iter().any(|s| s.is_empty()) {
+            return Err("Stop sequences cannot be empty".to_string());
+        }
+        if let Some(ref effort) = self.reasoning_effort {
+            if !REASONING_EFFORTS.contains(&effort.as_str()) {
+                return Err(format!(
+                    "Reasoning effort must be one of: {} (got '{}')",
+                    REASONING_EFFORTS.join(", "),
+                    effort
+                ));
+            }
+        }
+        if let Some(ref extra_body) = self.extra_body {
+            if !extra_body.is_object() {
+                return Err("Extra body must be a JSON object".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge the extra body into a finished provider request body
+    pub(crate) fn apply_extra_body(&self, body: &mut Value) {
+        if let Some(ref extra_body) = self.extra_body {
+            merge_json(body, extra_body);
+        }
+    }
+}
+
+/// Recursively merge `patch` into `target`: objects are merged key by key,
+/// anything else (including `null`) replaces the existing value
+fn merge_json(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_unset_params_are_not_serialized() {
+        let params = GenerationParams {
+            temperature: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            json!({"temperature": 0.5})
+        );
+
+        // Old task files without params deserialize to the defaults
+        let params: GenerationParams = serde_json::from_str("{}").unwrap();
+        assert_eq!(params, GenerationParams::default());
+    }
+
+    #[test]
+    fn test_overrides_from_options() {
+        let params = GenerationParams::from_options(&options(&[
+            ("temperature", "0.9"),
+            ("maxTokens", "512"),
+            ("stop", "END\n\n###"),
+            ("seed", ""),
+            ("reasoningEffort", "High"),
+            ("extraBody", r#"{"top_k": 40}"#),
+            ("language", "Portuguese"),
+        ]))
+        .unwrap();
+
+        assert_eq!(params.temperature, Some(0.9));
+        assert_eq!(params.max_tokens, Some(512));
+        assert_eq!(params.stop, vec!["END", "###"]);
+        assert_eq!(params.seed, None);
+        assert_eq!(params.reasoning_effort.as_deref(), Some("high"));
+        assert_eq!(params.extra_body, Some(json!({"top_k": 40})));
+
+        let params = GenerationParams::from_options(&options(&[("stop", r#"["\n\n"]"#)])).unwrap();
+        assert_eq!(params.stop, vec!["\n\n"]);
+    }
+
+    #[test]
+    fn test_invalid_params_are_rejected() {
+        for (key, value) in [
+            ("temperature", "hot"),
+            ("temperature", "2.5"),
+            ("topP", "1.5"),
+            ("maxTokens", "0"),
+            ("reasoningEffort", "extreme"),
+            ("extraBody", "[1, 2]"),
+        ] {
+            assert!(
+                GenerationParams::from_options(&options(&[(key, value)])).is_err(),
+                "{} = {}",
+                key,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_merged_prefers_overrides() {
+        let base = GenerationParams {
+            temperature: Some(0.6),
+            max_tokens: Some(1024),
+            stop: vec!["END".to_string()],
+            extra_body: Some(json!({"options": {"top_k": 40, "min_p": 0.05}})),
+            ..Default::default()
+        };
+        let overrides = GenerationParams {
+            temperature: Some(0.0),
+            extra_body: Some(json!({"options": {"top_k": 10}})),
+            ..Default::default()
+        };
+
+        let merged = base.merged(&overrides);
+        assert_eq!(merged.temperature, Some(0.0));
+        assert_eq!(merged.max_tokens, Some(1024));
+        assert_eq!(merged.stop, vec!["END"]);
+        assert_eq!(
+            merged.extra_body,
+            Some(json!({"options": {"top_k": 10, "min_p": 0.05}}))
+        );
+    }
+}
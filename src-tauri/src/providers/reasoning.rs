@@ -0,0 +1,150 @@
+//! Reasoning output of thinking models
+//! Splits `<think>...</think>` blocks out of streamed content, even when a tag is cut between chunks
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// A piece of streamed model output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamPart {
+    /// Answer text
+    Content(String),
+    /// Text inside a `<think>` block
+    Reasoning(String),
+}
+
+/// Incremental splitter: feed it content deltas, get answer and reasoning parts back.
+/// Tags are matched case-insensitively, like the post-processing that strips them.
+#[derive(Debug, Default)]
+pub struct ThinkTagSplitter {
+    in_reasoning: bool,
+    /// End of the input that may be the beginning of the next tag
+    pending: String,
+}
+
+impl ThinkTagSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Split a delta, holding back a trailing partial tag until the next one arrives
+    pub fn push(&mut self, text: &str) -> Vec<StreamPart> {
+        self.pending.push_str(text);
+
+        let mut parts = Vec::new();
+        loop {
+            let tag = if self.in_reasoning {
+                CLOSE_TAG
+            } else {
+                OPEN_TAG
+            };
+            // Tags are ASCII, so lowercasing keeps byte offsets
+            let lowercase = self.pending.to_ascii_lowercase();
+            match lowercase.find(tag) {
+                Some(position) => {
+                    let before: String = self.pending.drain(..position).collect();
+                    self.emit(&mut parts, before);
+                    self.pending.drain(..tag.len());
+                    self.in_reasoning = !self.in_reasoning;
+                }
+                None => {
+                    let partial = (1..tag.len())
+                        .rev()
+                        .find(|&len| lowercase.ends_with(&tag[..len]))
+                        .unwrap_or(0);
+                    let ready: String =
+                        self.pending.drain(..self.pending.len() - partial).collect();
+                    self.emit(&mut parts, ready);
+                    return parts;
+                }
+            }
+        }
+    }
+
+    /// Flush held-back text at the end of the stream
+    pub fn finish(&mut self) -> Vec<StreamPart> {
+        let rest = std::mem::take(&mut self.pending);
+        let mut parts = Vec::new();
+        self.emit(&mut parts, rest);
+        self.in_reasoning = false;
+        parts
+    }
+
+    fn emit(&self, parts: &mut Vec<StreamPart>, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        parts.push(if self.in_reasoning {
+            StreamPart::Reasoning(text)
+        } else {
+            StreamPart::Content(text)
+        });
+    }
+}
+
+/// Reasoning inside the `<think>` blocks of a complete response, joined by blank lines
+pub fn think_blocks(text: &str) -> String {
+    let mut splitter = ThinkTagSplitter::new();
+    let mut parts = splitter.push(text);
+    parts.extend(splitter.finish());
+
+    parts
+        .into_iter()
+        .filter_map(|part| match part {
+            StreamPart::Reasoning(reasoning) => Some(reasoning.trim().to_string()),
+            StreamPart::Content(_) => None,
+        })
+        .filter(|reasoning| !reasoning.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(chunks: &[&str]) -> Vec<StreamPart> {
+        let mut splitter = ThinkTagSplitter::new();
+        let mut parts: Vec<StreamPart> = chunks.iter().flat_map(|c| splitter.push(c)).collect();
+        parts.extend(splitter.finish());
+        parts
+    }
+
+    #[test]
+    fn test_tags_split_across_chunks() {
+        let parts = split(&["<th", "ink>Let me ", "see</thi", "nk>\n\nHello", " world"]);
+        assert_eq!(
+            parts,
+            vec![
+                StreamPart::Reasoning("Let me ".to_string()),
+                StreamPart::Reasoning("see".to_string()),
+                StreamPart::Content("\n\nHello".to_string()),
+                StreamPart::Content(" world".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_resembling_a_tag_is_content() {
+        let parts = split(&["a < b and <", "b>bold</b> <thin"]);
+        let content: String = parts
+            .iter()
+            .map(|part| match part {
+                StreamPart::Content(text) => text.as_str(),
+                StreamPart::Reasoning(_) => panic!("unexpected reasoning"),
+            })
+            .collect();
+        assert_eq!(content, "a < b and <b>bold</b> <thin");
+    }
+
+    #[test]
+    fn test_think_blocks_of_complete_response() {
+        assert_eq!(
+            think_blocks("<THINK> first </THINK>Answer<think>second</think>"),
+            "first\n\nsecond"
+        );
+        // A block left open by a cut-off response still counts
+        assert_eq!(think_blocks("<think>unfinished"), "unfinished");
+        assert_eq!(think_blocks("No reasoning"), "");
+    }
+}
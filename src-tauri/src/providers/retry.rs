@@ -0,0 +1,150 @@
+//! Retry policy for transient API failures
+//! 429 and 5xx responses are retried with exponential backoff and jitter, honoring `Retry-After`
+
+use rand::Rng;
+use std::time::Duration;
+
+use super::ProviderError;
+use crate::config::Configuration;
+
+/// Longest delay we are willing to wait before a retry. A `Retry-After` beyond this
+/// fails the request instead of leaving the user staring at a hung operation.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How often and how long to wait before retrying a failed request
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every further retry
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Configuration) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+        }
+    }
+
+    /// Delay before retry number `attempt + 1`, or `None` if the error is not
+    /// retryable, retries are exhausted or the server asks us to wait too long
+    pub fn next_delay(&self, error: &ProviderError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries || !error.is_retryable() {
+            return None;
+        }
+
+        match error.retry_after() {
+            Some(delay) if delay > MAX_RETRY_DELAY => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    /// Exponential backoff with "equal jitter": half the delay is fixed, half is random
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY);
+        let half_ms = (exponential.as_millis() / 2) as u64;
+        let jitter_ms = rand::thread_rng().gen_range(0..=half_ms);
+        Duration::from_millis(half_ms + jitter_ms)
+    }
+}
+
+/// Parse a `Retry-After` header value: either delay-seconds or an HTTP-date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    // A date in the past means "retry now"
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::check_status;
+    use crate::providers::test_support::mock_response;
+    use reqwest::StatusCode;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1000),
+        }
+    }
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> ProviderError {
+        ProviderError::Api {
+            status: StatusCode::from_u16(status).unwrap(),
+            body: String::new(),
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("4"), Some(Duration::from_secs(4)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        let policy = policy();
+        assert!(policy.next_delay(&api_error(429, None), 0).is_some());
+        assert!(policy.next_delay(&api_error(503, None), 0).is_some());
+        assert!(policy.next_delay(&api_error(400, None), 0).is_none());
+        assert!(policy.next_delay(&api_error(401, None), 0).is_none());
+        assert!(policy.next_delay(&ProviderError::Cancelled, 0).is_none());
+        assert!(policy.next_delay(&api_error(429, None), 3).is_none());
+    }
+
+    #[test]
+    fn test_retry_after_is_honored() {
+        let policy = policy();
+        let delay = policy.next_delay(&api_error(429, Some(Duration::from_secs(4))), 0);
+        assert_eq!(delay, Some(Duration::from_secs(4)));
+
+        // Waiting longer than the cap fails instead
+        let delay = policy.next_delay(&api_error(429, Some(Duration::from_secs(3600))), 0);
+        assert_eq!(delay, None);
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter() {
+        let policy = policy();
+        for attempt in 0..3 {
+            let full = 1000u64 << attempt;
+            let delay = policy.backoff(attempt).as_millis() as u64;
+            assert!(delay >= full / 2 && delay <= full, "{}ms", delay);
+        }
+        assert!(policy.backoff(30) <= MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_check_status_reads_retry_after() {
+        let (base_url, _server) = mock_response(
+            "429 Too Many Requests",
+            "retry-after: 7\r\n",
+            "{\"error\":\"rate limited\"}",
+            "application/json",
+        )
+        .await;
+
+        let response = reqwest::get(&base_url).await.unwrap();
+        let error = check_status(response, "Test", false).await.unwrap_err();
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(7)));
+        assert!(error.is_retryable());
+    }
+}
@@ -0,0 +1,271 @@
+//! Server-Sent Events decoder
+//! Works on raw bytes so multibyte characters split across network chunks are decoded intact
+
+use serde_json::Value;
+
+/// A dispatched SSE event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Value of the `event:` field (`None` for the default "message" event)
+    pub event: Option<String>,
+    /// `data:` lines joined with '\n'
+    pub data: String,
+}
+
+/// Incremental SSE decoder: feed it network chunks, get complete events back
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// Bytes of the current, not yet terminated line
+    buffer: Vec<u8>,
+    /// Whether the leading byte order mark has been checked
+    started: bool,
+    event: Option<String>,
+    data: String,
+    has_data: bool,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a chunk, returning every event completed by it
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        if !self.started {
+            if self.buffer.len() < 3 && b"\xEF\xBB\xBF".starts_with(&self.buffer) {
+                return Vec::new(); // Could still be a BOM, wait for more bytes
+            }
+            if self.buffer.starts_with(b"\xEF\xBB\xBF") {
+                self.buffer.drain(..3);
+            }
+            self.started = true;
+        }
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            let terminator_len = match self.buffer[i] {
+                b'\n' => 1,
+                b'\r' => match self.buffer.get(i + 1) {
+                    Some(b'\n') => 2,
+                    Some(_) => 1,
+                    // A CR at the end of the buffer may be the first half of a CRLF
+                    None => break,
+                },
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            // Line terminators are ASCII, so a complete line never ends mid code point
+            let line = String::from_utf8_lossy(&self.buffer[start..i]).into_owned();
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+            i += terminator_len;
+            start = i;
+        }
+        self.buffer.drain(..start);
+
+        events
+    }
+
+    /// Flush a final event when the stream ends without a trailing blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let bytes = std::mem::take(&mut self.buffer);
+            let line = String::from_utf8_lossy(&bytes);
+            let line = line.trim_end_matches('\r');
+            if let Some(event) = self.process_line(line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    /// Apply one line to the pending event; a blank line dispatches it
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        // Lines starting with a colon are comments (often used as keep-alives)
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "event" => self.event = Some(value.to_string()),
+            // `id` and `retry` only matter for reconnecting, which we never do
+            _ => {}
+        }
+        None
+    }
+
+    /// Emit the pending event (if it has data) and reset for the next one
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if !self.has_data {
+            return None;
+        }
+        self.has_data = false;
+        Some(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data),
+        })
+    }
+}
+
+/// Describe an `{"error": ...}` payload, e.g. "overloaded_error: Overloaded".
+/// Handles OpenAI/Anthropic (`type`), Gemini (`status`) and plain string errors.
+pub(crate) fn error_message(json: &Value) -> Option<String> {
+    match json.get("error")? {
+        Value::String(message) => Some(message.clone()),
+        error @ Value::Object(_) => {
+            let message = error["message"].as_str().unwrap_or("Unknown error");
+            let kind = error["type"]
+                .as_str()
+                .or_else(|| error["status"].as_str())
+                .or_else(|| error["code"].as_str());
+            Some(match kind {
+                Some(kind) => format!("{}: {}", kind, message),
+                None => message.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Error carried by an event: an `error` event or a data payload with an `error` field
+pub(crate) fn stream_error(event: &SseEvent) -> Option<String> {
+    let json = serde_json::from_str::<Value>(&event.data).ok();
+    if let Some(message) = json.as_ref().and_then(error_message) {
+        return Some(message);
+    }
+    if event.event.as_deref() == Some("error") {
+        return Some(event.data.clone());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| decoder.feed(c)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    fn data(text: &str) -> SseEvent {
+        SseEvent {
+            event: None,
+            data: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_code_point_split_across_chunks() {
+        // "ação 日本" with both multibyte characters cut between chunks
+        let bytes = "data: ação 日本\n\n".as_bytes();
+        let cut_a = "data: a".len() + 1; // inside 'ç'
+        let cut_b = "data: ação 日".len() + 1; // inside '本'
+        let events = decode_all(&[&bytes[..cut_a], &bytes[cut_a..cut_b], &bytes[cut_b..]]);
+        assert_eq!(events, vec![data("ação 日本")]);
+    }
+
+    #[test]
+    fn test_crlf_and_cr_line_endings() {
+        let events = decode_all(&[b"data: one\r", b"\n\r\ndata: two\r\r"]);
+        assert_eq!(events, vec![data("one"), data("two")]);
+    }
+
+    #[test]
+    fn test_comments_are_ignored() {
+        let events = decode_all(&[b": keep-alive\n\n: OPENROUTER PROCESSING\ndata: hi\n\n"]);
+        assert_eq!(events, vec![data("hi")]);
+    }
+
+    #[test]
+    fn test_event_name_and_multiline_data() {
+        let events = decode_all(&[b"event: error\ndata: {\"error\":\ndata: \"boom\"}\n\n"]);
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: Some("error".to_string()),
+                data: "{\"error\":\n\"boom\"}".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_field_parsing_details() {
+        // No space after the colon, a bare "data" line and a BOM at the start
+        let events = decode_all(&[b"\xEF\xBB", b"\xBFdata:x\ndata\nid: 7\n\n"]);
+        assert_eq!(events, vec![data("x\n")]);
+    }
+
+    #[test]
+    fn test_event_without_data_is_not_dispatched() {
+        let events = decode_all(&[b"event: ping\n\ndata: after\n\n"]);
+        assert_eq!(events, vec![data("after")]);
+    }
+
+    #[test]
+    fn test_unterminated_final_event_is_flushed() {
+        let events = decode_all(&[b"data: [DONE]"]);
+        assert_eq!(events, vec![data("[DONE]")]);
+    }
+
+    #[test]
+    fn test_error_payloads() {
+        let openai = SseEvent {
+            event: None,
+            data: r#"{"error":{"message":"Upstream timeout","type":"server_error","code":null}}"#
+                .to_string(),
+        };
+        assert_eq!(
+            stream_error(&openai).as_deref(),
+            Some("server_error: Upstream timeout")
+        );
+
+        let gemini: Value = serde_json::from_str(
+            r#"{"error":{"code":429,"message":"Quota","status":"RESOURCE_EXHAUSTED"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            error_message(&gemini).as_deref(),
+            Some("RESOURCE_EXHAUSTED: Quota")
+        );
+
+        let plain = SseEvent {
+            event: Some("error".to_string()),
+            data: "backend unavailable".to_string(),
+        };
+        assert_eq!(stream_error(&plain).as_deref(), Some("backend unavailable"));
+
+        // Regular chunks are not errors, even if their text mentions one
+        assert_eq!(
+            stream_error(&data(r#"{"choices":[{"delta":{"content":"error"}}]}"#)),
+            None
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Test helpers shared by the provider tests: a collecting stream sink and a one-shot mock HTTP server
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use super::StreamSink;
+
+/// Stream sink that records everything it receives
+#[derive(Default)]
+pub struct CollectSink {
+    pub chunks: Vec<String>,
+    pub reasoning: Vec<String>,
+    pub done: bool,
+}
+
+impl StreamSink for CollectSink {
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    fn on_content(&mut self, content: &str) {
+        self.chunks.push(content.to_string());
+    }
+
+    fn on_reasoning(&mut self, reasoning: &str) {
+        self.reasoning.push(reasoning.to_string());
+    }
+
+    fn on_done(&mut self) {
+        self.done = true;
+    }
+}
+
+/// Read one HTTP request (headers plus `content-length` body) from the socket
+async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request).to_string();
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|l| {
+                    l.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                })
+                .unwrap_or(0);
+            if request.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&request).to_string()
+}
+
+/// Serve a single canned `200 OK` response on a random local port.
+/// Returns the base URL and a handle resolving to the raw request that was received.
+pub async fn mock_server(
+    response_body: impl Into<String>,
+    content_type: &'static str,
+) -> (String, JoinHandle<String>) {
+    mock_response("200 OK", "", response_body, content_type).await
+}
+
+/// Serve a single canned response with the given status line (e.g. "429 Too Many Requests")
+/// and extra header lines (each terminated by `\r\n`).
+pub async fn mock_response(
+    status: &'static str,
+    extra_headers: &'static str,
+    response_body: impl Into<String>,
+    content_type: &'static str,
+) -> (String, JoinHandle<String>) {
+    let response_body = response_body.into();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let request = read_request(&mut socket).await;
+
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: {}\r\ncontent-length: {}\r\n{}connection: close\r\n\r\n{}",
+            status,
+            content_type,
+            response_body.len(),
+            extra_headers,
+            response_body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        socket.shutdown().await.ok();
+        request
+    });
+
+    (base_url, handle)
+}
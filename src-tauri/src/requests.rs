@@ -0,0 +1,113 @@
+//! In-flight request registry for AI Anywhere
+//! Every LLM request gets an id and its own cancellation token, so several requests
+//! can run at once and be cancelled independently
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Requests currently being processed, keyed by request id
+#[derive(Default)]
+pub struct RequestRegistry {
+    requests: Mutex<HashMap<String, CancellationToken>>,
+}
+
+/// A registered request; it leaves the registry when dropped
+pub struct InFlightRequest<'a> {
+    registry: &'a RequestRegistry,
+    id: String,
+    token: CancellationToken,
+}
+
+impl InFlightRequest<'_> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut requests) = self.registry.requests.lock() {
+            requests.remove(&self.id);
+        }
+    }
+}
+
+impl RequestRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a request under the caller's id, or a generated one when none is given
+    pub fn register(&self, request_id: Option<String>) -> Result<InFlightRequest<'_>, String> {
+        let id = request_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let mut requests = self.requests.lock().map_err(|e| e.to_string())?;
+        if requests.contains_key(&id) {
+            return Err(format!("Request {} is already running", id));
+        }
+        let token = CancellationToken::new();
+        requests.insert(id.clone(), token.clone());
+
+        Ok(InFlightRequest {
+            registry: self,
+            id,
+            token,
+        })
+    }
+
+    /// Cancel one request; returns false if it is not running
+    pub fn cancel(&self, request_id: &str) -> Result<bool, String> {
+        let requests = self.requests.lock().map_err(|e| e.to_string())?;
+        Ok(match requests.get(request_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Cancel every running request, returning how many were cancelled
+    pub fn cancel_all(&self) -> Result<usize, String> {
+        let requests = self.requests.lock().map_err(|e| e.to_string())?;
+        for token in requests.values() {
+            token.cancel();
+        }
+        Ok(requests.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_cancelled_independently() {
+        let registry = RequestRegistry::new();
+        let first = registry.register(Some("first".to_string())).unwrap();
+        let second = registry.register(None).unwrap();
+        assert!(!second.id().is_empty());
+        assert!(registry.register(Some("first".to_string())).is_err());
+
+        assert!(registry.cancel("first").unwrap());
+        assert!(first.token().is_cancelled());
+        assert!(!second.token().is_cancelled());
+
+        // Finished requests leave the registry and their id can be reused
+        drop(first);
+        assert!(!registry.cancel("first").unwrap());
+        assert!(registry.register(Some("first".to_string())).is_ok());
+
+        assert_eq!(registry.cancel_all().unwrap(), 1);
+        assert!(second.token().is_cancelled());
+    }
+}
@@ -0,0 +1,194 @@
+//! Structured output for AI Anywhere
+//! Checks JSON answers of custom tasks against their JSON Schema and renders them
+//! through paste templates
+
+use jsonschema::Validator;
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::providers::{StreamPart, ThinkTagSplitter};
+
+/// Compile an output schema. The root must be an object, as OpenAI requires.
+pub fn compile_schema(schema: &Value) -> Result<Validator, String> {
+    if schema.get("type").and_then(Value::as_str) != Some("object") {
+        return Err(
+            "The output schema must describe a JSON object (\"type\": \"object\")".to_string(),
+        );
+    }
+    jsonschema::validator_for(schema).map_err(|e| format!("Invalid output schema: {}", e))
+}
+
+/// Parse an answer as JSON and check it against the schema, listing every mismatch.
+/// Reasoning blocks and a Markdown code fence around the JSON are ignored.
+pub fn parse_answer(content: &str, validator: &Validator) -> Result<Value, String> {
+    let text = answer_text(content);
+    let value: Value = serde_json::from_str(strip_code_fence(&text))
+        .map_err(|e| format!("The answer is not valid JSON: {}", e))?;
+
+    let errors: Vec<String> = validator
+        .iter_errors(&value)
+        .map(|error| match error.instance_path.to_string() {
+            path if path.is_empty() => error.to_string(),
+            path => format!("{}: {}", path, error),
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(format!(
+            "The JSON does not match the schema:\n- {}",
+            errors.join("\n- ")
+        ));
+    }
+    Ok(value)
+}
+
+/// Fill the `{field}` placeholders of a paste template with values of the JSON.
+/// Nested fields and array items are reached with dots (`{sender.name}`, `{items.0}`);
+/// missing fields render as nothing.
+pub fn render_template(template: &str, value: &Value) -> String {
+    let placeholder = Regex::new(r"\{([\w.]+)\}").unwrap();
+    placeholder
+        .replace_all(template, |captures: &Captures| {
+            captures[1]
+                .split('.')
+                .try_fold(value, |value, key| match value {
+                    Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                    _ => value.get(key),
+                })
+                .map(display_value)
+                .unwrap_or_default()
+        })
+        .into_owned()
+}
+
+/// Top-level fields used by the placeholders of a template
+pub fn template_fields(template: &str) -> Vec<String> {
+    let placeholder = Regex::new(r"\{(\w+)[\w.]*\}").unwrap();
+    placeholder
+        .captures_iter(template)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
+/// Strings without quotes, lists of plain values comma-separated, anything else as JSON
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items)
+            if items
+                .iter()
+                .all(|item| !item.is_object() && !item.is_array()) =>
+        {
+            items
+                .iter()
+                .map(display_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        other => other.to_string(),
+    }
+}
+
+/// The answer without its `<think>` blocks
+fn answer_text(content: &str) -> String {
+    let mut splitter = ThinkTagSplitter::new();
+    let mut parts = splitter.push(content);
+    parts.extend(splitter.finish());
+
+    parts
+        .into_iter()
+        .filter_map(|part| match part {
+            StreamPart::Content(text) => Some(text),
+            StreamPart::Reasoning(_) => None,
+        })
+        .collect()
+}
+
+/// The inside of a ```json ... ``` fence, or the trimmed text if there is none
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    match text.strip_prefix("```") {
+        Some(fenced) => {
+            // Drop the language tag line
+            let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+            body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+        }
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn email_schema() -> Validator {
+        compile_schema(&json!({
+            "type": "object",
+            "properties": {
+                "subject": {"type": "string"},
+                "priority": {"enum": ["low", "high"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["subject", "priority"]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_answers_are_parsed_and_validated() {
+        let validator = email_schema();
+        let value = parse_answer(
+            "<think>The subject is obvious.</think>\n```json\n{\"subject\": \"Invoice\", \"priority\": \"high\"}\n```",
+            &validator,
+        )
+        .unwrap();
+        assert_eq!(value, json!({"subject": "Invoice", "priority": "high"}));
+
+        let error =
+            parse_answer(r#"{"subject": 42, "priority": "urgent"}"#, &validator).unwrap_err();
+        assert!(
+            error.contains("/subject: 42 is not of type \"string\""),
+            "{}",
+            error
+        );
+        assert!(error.contains("/priority:"), "{}", error);
+
+        let error = parse_answer("Sure! Here is the JSON.", &validator).unwrap_err();
+        assert!(
+            error.starts_with("The answer is not valid JSON"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_schemas_must_describe_objects() {
+        assert!(compile_schema(&json!({"type": "array"})).is_err());
+        assert!(
+            compile_schema(&json!({"type": "object", "properties": {"a": {"type": 5}}})).is_err()
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let value = json!({
+            "subject": "Invoice",
+            "sender": {"name": "Ana"},
+            "tags": ["billing", "q3"],
+            "items": [{"id": 1}],
+            "due": null
+        });
+        assert_eq!(
+            render_template(
+                "{subject} from {sender.name} [{tags}] {items.0} due:{due}{missing}",
+                &value
+            ),
+            "Invoice from Ana [billing, q3] {\"id\":1} due:"
+        );
+        assert_eq!(
+            template_fields("{subject} - {sender.name}"),
+            vec!["subject", "sender"]
+        );
+    }
+}
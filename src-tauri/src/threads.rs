@@ -0,0 +1,268 @@
+//! Conversation threads for AI Anywhere
+//! Stores the messages of text requests so a result can be followed up with
+//! further instructions, now or later from the history
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tokio::fs as async_fs;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::providers::ChatMessage;
+use crate::tokens::count_message_tokens;
+
+/// Longest message a thread keeps; longer prompts and answers are cut, so the threads
+/// file stays small enough to rewrite on every request
+const MAX_MESSAGE_CHARS: usize = 20_000;
+
+/// A message of a thread. Images are not kept, only the text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadMessage {
+    /// "system", "user" or "assistant"
+    pub role: String,
+    pub content: String,
+}
+
+/// The exchanges of a conversation, starting with the system prompt of its operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thread {
+    pub id: String,
+    pub operation_type: String,
+    pub messages: Vec<ThreadMessage>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Thread {
+    pub fn new(operation_type: &str, system_prompt: &str) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            operation_type: operation_type.to_string(),
+            messages: vec![ThreadMessage {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            }],
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    /// The system prompt the thread was started with
+    pub fn system_prompt(&self) -> &str {
+        self.messages
+            .first()
+            .filter(|m| m.role == "system")
+            .map_or("", |m| m.content.as_str())
+    }
+
+    /// The messages after the system prompt, as chat messages
+    pub fn history(&self) -> Vec<ChatMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.role != "system")
+            .map(|m| match m.role.as_str() {
+                "assistant" => ChatMessage::assistant(m.content.clone()),
+                _ => ChatMessage::user(m.content.clone()),
+            })
+            .collect()
+    }
+
+    /// Append a prompt and the answer it received, each cut to `MAX_MESSAGE_CHARS`
+    pub fn push_exchange(&mut self, prompt: &str, answer: &str) {
+        for (role, content) in [("user", prompt), ("assistant", answer)] {
+            let content = match content.char_indices().nth(MAX_MESSAGE_CHARS) {
+                Some((end, _)) => format!("{}\n[...]", &content[..end]),
+                None => content.to_string(),
+            };
+            self.messages.push(ThreadMessage {
+                role: role.to_string(),
+                content,
+            });
+        }
+        self.updated_at = chrono::Utc::now().to_rfc3339();
+    }
+
+    /// Number of prompt/answer exchanges
+    pub fn exchanges(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|m| m.role == "assistant")
+            .count()
+    }
+}
+
+/// Drop the oldest exchanges of a follow-up request (`system, user, assistant, ...,
+/// user`) until it fits `max_tokens`. The system prompt and the new prompt are always
+/// kept. Returns the number of exchanges dropped.
+pub fn trim_to_fit(messages: &mut Vec<ChatMessage>, model: &str, max_tokens: usize) -> usize {
+    let tokens = |messages: &[ChatMessage]| {
+        count_message_tokens(messages.iter().map(|m| m.content.as_str()), model)
+    };
+
+    let mut dropped = 0;
+    while messages.len() > 3 && tokens(messages) > max_tokens {
+        messages.drain(1..3);
+        dropped += 1;
+    }
+    dropped
+}
+
+/// Guards the threads file. Requests run concurrently and each one rewrites the whole
+/// file, so without it a write could drop the thread saved by another request.
+static THREADS_FILE: Mutex<()> = Mutex::const_new(());
+
+/// Thread manager for loading and saving conversation threads
+pub struct ThreadManager;
+
+impl ThreadManager {
+    /// Get the app data directory path (cross-platform)
+    /// - Windows: C:\Users\<user>\AppData\Roaming\ai-anywhere
+    /// - macOS: ~/Library/Application Support/ai-anywhere
+    /// - Linux: ~/.local/share/ai-anywhere
+    fn get_app_data_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ai-anywhere")
+    }
+
+    /// Get the threads file path
+    pub fn get_threads_path() -> PathBuf {
+        let data_dir = Self::get_app_data_dir();
+
+        if let Err(e) = fs::create_dir_all(&data_dir) {
+            eprintln!(
+                "[ThreadManager] Failed to create data directory {:?}: {}",
+                data_dir, e
+            );
+        }
+        data_dir.join("threads.json")
+    }
+
+    /// Load all threads (callers hold `THREADS_FILE`)
+    async fn load() -> Result<Vec<Thread>, String> {
+        let threads_path = Self::get_threads_path();
+
+        if !threads_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = async_fs::read_to_string(&threads_path)
+            .await
+            .map_err(|e| format!("Failed to read threads file: {}", e))?;
+
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse threads file: {}", e))
+    }
+
+    /// Save all threads (callers hold `THREADS_FILE`). The file is written next to the
+    /// old one and then renamed over it, so it is never read half-written.
+    async fn save(threads: &[Thread]) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(threads)
+            .map_err(|e| format!("Failed to serialize threads: {}", e))?;
+
+        let threads_path = Self::get_threads_path();
+        let temp_path = threads_path.with_extension("json.tmp");
+        async_fs::write(&temp_path, content)
+            .await
+            .map_err(|e| format!("Failed to write threads file: {}", e))?;
+        async_fs::rename(&temp_path, &threads_path)
+            .await
+            .map_err(|e| format!("Failed to write threads file: {}", e))
+    }
+
+    /// Get a thread by ID
+    pub async fn get(id: &str) -> Result<Option<Thread>, String> {
+        let _guard = THREADS_FILE.lock().await;
+        let threads = Self::load().await?;
+        Ok(threads.into_iter().find(|t| t.id == id))
+    }
+
+    /// Add a thread, or replace the stored one with the same ID
+    pub async fn put(thread: Thread) -> Result<(), String> {
+        let _guard = THREADS_FILE.lock().await;
+        let mut threads = Self::load().await?;
+        match threads.iter_mut().find(|t| t.id == thread.id) {
+            Some(existing) => *existing = thread,
+            None => threads.push(thread),
+        }
+        Self::save(&threads).await
+    }
+
+    /// Delete threads by ID
+    pub async fn delete(ids: &[String]) -> Result<(), String> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _guard = THREADS_FILE.lock().await;
+        let mut threads = Self::load().await?;
+        threads.retain(|t| !ids.contains(&t.id));
+        Self::save(&threads).await
+    }
+
+    /// Delete all threads
+    pub async fn clear() -> Result<(), String> {
+        let _guard = THREADS_FILE.lock().await;
+        Self::save(&[]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_history() {
+        let mut thread = Thread::new("textRewrite", "Rewrite the text.");
+        thread.push_exchange("Text: hello there", "Greetings.");
+        thread.push_exchange("More formal", "Good day.");
+
+        assert_eq!(thread.system_prompt(), "Rewrite the text.");
+        assert_eq!(thread.exchanges(), 2);
+        let history = thread.history();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[1].role, "assistant");
+        assert_eq!(history[3].content, "Good day.");
+    }
+
+    #[test]
+    fn test_long_messages_are_cut() {
+        let mut thread = Thread::new("textRewrite", "Rewrite the text.");
+        thread.push_exchange(&"é".repeat(MAX_MESSAGE_CHARS + 10), "Done.");
+
+        let prompt = &thread.messages[1].content;
+        assert!(prompt.ends_with("\n[...]"));
+        assert_eq!(prompt.chars().count(), MAX_MESSAGE_CHARS + "\n[...]".len());
+        assert_eq!(thread.messages[2].content, "Done.");
+    }
+
+    #[test]
+    fn test_oldest_exchanges_are_trimmed_first() {
+        let long = "word ".repeat(500);
+        let mut messages = vec![
+            ChatMessage::system("Rewrite the text."),
+            ChatMessage::user(long.clone()),
+            ChatMessage::assistant(long.clone()),
+            ChatMessage::user("Shorter"),
+            ChatMessage::assistant("A short text."),
+            ChatMessage::user("Even shorter"),
+        ];
+
+        let dropped = trim_to_fit(&mut messages, "gpt-4o", 100);
+        assert_eq!(dropped, 1);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1].content, "Shorter");
+        assert_eq!(messages[3].content, "Even shorter");
+
+        // The system prompt and the new prompt stay even if they do not fit
+        let dropped = trim_to_fit(&mut messages, "gpt-4o", 1);
+        assert_eq!(dropped, 1);
+        assert_eq!(messages.len(), 2);
+    }
+}
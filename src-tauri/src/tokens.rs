@@ -0,0 +1,429 @@
+//! Token counting module for AI Anywhere
+//! Counts tokens with a real BPE tokenizer and knows the context window of common models
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::CoreBPE;
+
+/// Context window assumed for models missing from the table (and without a configured override)
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 16_384;
+
+/// Tokens added per chat message for role and separators (OpenAI chat format)
+const TOKENS_PER_MESSAGE: usize = 3;
+
+/// Tokens that prime every assistant reply
+const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Known context windows, matched as prefixes of the normalized model name.
+/// More specific prefixes must come first.
+const CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    // OpenAI
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("chatgpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-1106", 128_000),
+    ("gpt-4-0125", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-3.5-turbo", 16_385),
+    ("gpt-oss", 131_072),
+    ("o1-mini", 128_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    // Anthropic
+    ("claude", 200_000),
+    // Google
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-1.5", 1_048_576),
+    ("gemini-2", 1_048_576),
+    ("gemini", 32_768),
+    ("gemma", 8_192),
+    // Open models (Ollama tags, OpenRouter ids, ...)
+    ("llama3.1", 131_072),
+    ("llama3.2", 131_072),
+    ("llama3.3", 131_072),
+    ("llama-3.1", 131_072),
+    ("llama-3.2", 131_072),
+    ("llama-3.3", 131_072),
+    ("llama", 8_192),
+    ("mistral-large", 131_072),
+    ("mistral-small", 32_768),
+    ("mistral-nemo", 131_072),
+    ("mistral", 32_768),
+    ("mixtral", 32_768),
+    ("qwen", 32_768),
+    ("deepseek", 65_536),
+    ("grok", 131_072),
+    ("command-r", 128_000),
+    ("phi", 16_384),
+];
+
+/// BPE vocabulary used to count tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// GPT-4 / GPT-3.5 vocabulary, also a reasonable approximation for non-OpenAI models
+    Cl100kBase,
+    /// GPT-4o and later OpenAI models
+    O200kBase,
+}
+
+impl Encoding {
+    /// Pick the vocabulary for a model. Models from other vendors use their own
+    /// tokenizers, which are closer to cl100k than to o200k for most text.
+    pub fn for_model(model: &str) -> Self {
+        let name = normalize_model(model);
+        let o200k = [
+            "gpt-5",
+            "gpt-4.1",
+            "gpt-4o",
+            "chatgpt-4o",
+            "gpt-oss",
+            "o1",
+            "o3",
+            "o4",
+        ];
+        if o200k.iter().any(|prefix| name.starts_with(prefix)) {
+            Encoding::O200kBase
+        } else {
+            Encoding::Cl100kBase
+        }
+    }
+
+    /// Count the tokens of a text with this vocabulary
+    pub fn count(self, text: &str) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+        self.bpe().encode_ordinary(text).len()
+    }
+
+    /// The shared tokenizer (vocabularies are bundled in the binary and loaded once)
+    fn bpe(self) -> &'static CoreBPE {
+        match self {
+            Encoding::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+            Encoding::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        }
+    }
+}
+
+/// Token count of a prompt, with the limit of the model it will be sent to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCount {
+    pub tokens: usize,
+    pub model: String,
+    pub encoding: Encoding,
+    pub context_window: u32,
+    /// Tokens available for the prompt once the response budget is reserved
+    pub max_input_tokens: u32,
+    /// Whether the operation splits prompts over the limit into chunks instead of failing
+    pub supports_chunking: bool,
+}
+
+/// Lowercase the model id and drop vendor/path prefixes
+/// ("openai/gpt-4o" → "gpt-4o", "models/gemini-2.0-flash" → "gemini-2.0-flash")
+pub(crate) fn normalize_model(model: &str) -> String {
+    let model = model.trim().to_lowercase();
+    match model.rsplit_once('/') {
+        Some((_, name)) => name.to_string(),
+        None => model,
+    }
+}
+
+/// Count the tokens of a text as the model's tokenizer would
+pub fn count_tokens(text: &str, model: &str) -> usize {
+    Encoding::for_model(model).count(text)
+}
+
+/// Count the tokens of a chat conversation, including per-message overhead
+pub fn count_message_tokens<'a>(contents: impl IntoIterator<Item = &'a str>, model: &str) -> usize {
+    let bpe = Encoding::for_model(model).bpe();
+    contents
+        .into_iter()
+        .map(|content| bpe.encode_ordinary(content).len() + TOKENS_PER_MESSAGE)
+        .sum::<usize>()
+        + REPLY_PRIMING_TOKENS
+}
+
+/// Context window of a model from the built-in table, if known
+pub fn known_context_window(model: &str) -> Option<u32> {
+    let name = normalize_model(model);
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, window)| *window)
+}
+
+/// Tokens left for the prompt after reserving `max_tokens` for the response.
+/// At most half of the window is reserved so small models still accept a prompt.
+pub fn max_input_tokens(context_window: u32, max_tokens: u32) -> u32 {
+    context_window.saturating_sub(max_tokens.min(context_window / 2))
+}
+
+/// Split a text into chunks of at most `max_tokens` tokens. Chunks break between
+/// paragraphs where possible, then between sentences, and only then mid-sentence.
+pub fn split_into_chunks(text: &str, model: &str, max_tokens: usize) -> Vec<String> {
+    let mut builder = ChunkBuilder::new(Encoding::for_model(model), max_tokens.max(1));
+    let paragraph_break = Regex::new(r"\n[ \t]*\n\s*").unwrap();
+
+    for paragraph in paragraph_break.split(text.trim()) {
+        if builder.fits(paragraph) {
+            builder.add(paragraph, "\n\n");
+            continue;
+        }
+
+        let mut separator = "\n\n";
+        for sentence in split_sentences(paragraph) {
+            if builder.fits(sentence) {
+                builder.add(sentence, separator);
+            } else {
+                for piece in builder.hard_split(sentence) {
+                    builder.add(&piece, separator);
+                    separator = "";
+                }
+            }
+            separator = "";
+        }
+    }
+
+    builder.finish()
+}
+
+/// Split a paragraph after sentence terminators, keeping the original whitespace
+fn split_sentences(paragraph: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = paragraph.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let ends_sentence = match c {
+            // Full-width terminators are not followed by a space
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' | '…' => chars.peek().is_some_and(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if !ends_sentence {
+            continue;
+        }
+
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, next)) = chars.peek() {
+            if !next.is_whitespace() {
+                break;
+            }
+            end = j + next.len_utf8();
+            chars.next();
+        }
+        sentences.push(&paragraph[start..end]);
+        start = end;
+    }
+
+    if start < paragraph.len() {
+        sentences.push(&paragraph[start..]);
+    }
+    sentences
+}
+
+/// Packs pieces of text into chunks that stay under a token budget
+struct ChunkBuilder {
+    encoding: Encoding,
+    max_tokens: usize,
+    chunks: Vec<String>,
+    current: String,
+    current_tokens: usize,
+}
+
+impl ChunkBuilder {
+    fn new(encoding: Encoding, max_tokens: usize) -> Self {
+        Self {
+            encoding,
+            max_tokens,
+            chunks: Vec::new(),
+            current: String::new(),
+            current_tokens: 0,
+        }
+    }
+
+    /// Whether a piece fits in a chunk on its own
+    fn fits(&self, piece: &str) -> bool {
+        self.encoding.count(piece) <= self.max_tokens
+    }
+
+    /// Append a piece, starting a new chunk when the current one would overflow.
+    /// `separator` joins the piece to the previous one within a chunk.
+    fn add(&mut self, piece: &str, separator: &str) {
+        let tokens = self.encoding.count(piece);
+        let separator_tokens = self.encoding.count(separator);
+
+        if !self.current.is_empty()
+            && self.current_tokens + separator_tokens + tokens > self.max_tokens
+        {
+            self.flush();
+        }
+        if !self.current.is_empty() {
+            self.current.push_str(separator);
+            self.current_tokens += separator_tokens;
+        }
+        self.current.push_str(piece);
+        self.current_tokens += tokens;
+    }
+
+    /// Cut a piece that is too long for one chunk at character boundaries,
+    /// preferring to cut at whitespace
+    fn hard_split(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut pieces = Vec::new();
+        let mut start = 0;
+
+        while start < chars.len() {
+            let mut end = chars.len();
+            loop {
+                let tokens = self
+                    .encoding
+                    .count(&chars[start..end].iter().collect::<String>());
+                if tokens <= self.max_tokens {
+                    break;
+                }
+                // Shrink proportionally; always make progress
+                let shrunk = start + ((end - start) * self.max_tokens / tokens).max(1);
+                end = shrunk.min(end - 1).max(start + 1);
+            }
+
+            if end < chars.len() {
+                if let Some(space) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+                    if space > 0 {
+                        end = start + space + 1;
+                    }
+                }
+            }
+
+            pieces.push(chars[start..end].iter().collect());
+            start = end;
+        }
+        pieces
+    }
+
+    fn flush(&mut self) {
+        let chunk = std::mem::take(&mut self.current);
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            self.chunks.push(chunk.to_string());
+        }
+        self.current_tokens = 0;
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.flush();
+        self.chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_for_model() {
+        assert_eq!(Encoding::for_model("gpt-4o-mini"), Encoding::O200kBase);
+        assert_eq!(Encoding::for_model("openai/o3-mini"), Encoding::O200kBase);
+        assert_eq!(Encoding::for_model("gpt-4-turbo"), Encoding::Cl100kBase);
+        assert_eq!(Encoding::for_model("llama3.1:8b"), Encoding::Cl100kBase);
+    }
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(count_tokens("", "gpt-4o"), 0);
+        assert_eq!(count_tokens("hello world", "gpt-4"), 2);
+        assert_eq!(count_tokens("hello world", "gpt-4o"), 2);
+
+        // Whitespace-free text is where word-based estimates fall apart
+        let url = "https://example.com/a/very/long/path?with=query&and=more#fragment";
+        assert!(count_tokens(url, "gpt-4o") > 10);
+        assert!(count_tokens("日本語のテキストを翻訳してください", "gpt-4o") > 5);
+    }
+
+    #[test]
+    fn test_count_message_tokens_adds_overhead() {
+        let text = count_tokens("Be brief.", "gpt-4o") + count_tokens("Hello", "gpt-4o");
+        assert_eq!(
+            count_message_tokens(["Be brief.", "Hello"], "gpt-4o"),
+            text + 2 * TOKENS_PER_MESSAGE + REPLY_PRIMING_TOKENS
+        );
+    }
+
+    #[test]
+    fn test_known_context_window() {
+        assert_eq!(known_context_window("gpt-4o-2024-08-06"), Some(128_000));
+        assert_eq!(known_context_window("gpt-4-0613"), Some(8_192));
+        assert_eq!(
+            known_context_window("anthropic/claude-3.5-sonnet"),
+            Some(200_000)
+        );
+        assert_eq!(
+            known_context_window("models/gemini-2.0-flash"),
+            Some(1_048_576)
+        );
+        assert_eq!(known_context_window("llama3.1:8b"), Some(131_072));
+        assert_eq!(known_context_window("my-finetune"), None);
+    }
+
+    #[test]
+    fn test_max_input_tokens() {
+        assert_eq!(max_input_tokens(128_000, 4096), 123_904);
+        assert_eq!(max_input_tokens(4096, 4096), 2048);
+    }
+
+    #[test]
+    fn test_split_keeps_short_text_whole() {
+        let text = "First paragraph.\n\nSecond paragraph.";
+        assert_eq!(split_into_chunks(text, "gpt-4o", 100), vec![text]);
+    }
+
+    #[test]
+    fn test_split_on_paragraphs() {
+        let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(8);
+        let paragraph = paragraph.trim();
+        let text = [paragraph; 4].join("\n\n");
+        let limit = count_tokens(paragraph, "gpt-4o") * 2 + 5;
+
+        let chunks = split_into_chunks(&text, "gpt-4o", limit);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], [paragraph; 2].join("\n\n"));
+        assert!(chunks.iter().all(|c| count_tokens(c, "gpt-4o") <= limit));
+    }
+
+    #[test]
+    fn test_split_long_paragraph_on_sentences() {
+        let text = "One sentence here. Another one follows! Is this the third? 第四句。第五句。";
+        let sentences = split_sentences(text);
+        assert_eq!(
+            sentences,
+            vec![
+                "One sentence here. ",
+                "Another one follows! ",
+                "Is this the third? ",
+                "第四句。",
+                "第五句。"
+            ]
+        );
+
+        let chunks = split_into_chunks(text, "gpt-4o", 10);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| count_tokens(c, "gpt-4o") <= 10));
+        assert_eq!(chunks[0], "One sentence here. Another one follows!");
+    }
+
+    #[test]
+    fn test_split_text_without_boundaries() {
+        let text = "token".repeat(500);
+        let chunks = split_into_chunks(&text, "gpt-4", 50);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| count_tokens(c, "gpt-4") <= 50));
+        assert_eq!(chunks.concat(), text);
+    }
+}
@@ -0,0 +1,602 @@
+//! Local tools for AI Anywhere
+//! Functions a model may call while answering: date and time, a calculator, unit
+//! conversion, reading files from allow-listed folders and searching the history
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use crate::attachments::read_attachment;
+use crate::history::HistoryManager;
+use crate::providers::{ToolCall, ToolDefinition};
+
+/// Characters of a file or history entry returned to the model
+const MAX_TOOL_OUTPUT_CHARS: usize = 20_000;
+
+/// History entries returned by a search when the model does not ask for a number
+const DEFAULT_SEARCH_RESULTS: usize = 5;
+
+/// Most history entries a search may return
+const MAX_SEARCH_RESULTS: usize = 20;
+
+/// Characters kept of each prompt and response in history search results
+const HISTORY_EXCERPT_CHARS: usize = 500;
+
+/// A built-in tool custom tasks can opt into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tool {
+    DateTime,
+    Calculator,
+    ConvertUnits,
+    ReadFile,
+    SearchHistory,
+}
+
+impl Tool {
+    /// Name the model calls the tool by
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::DateTime => "date_time",
+            Tool::Calculator => "calculator",
+            Tool::ConvertUnits => "convert_units",
+            Tool::ReadFile => "read_file",
+            Tool::SearchHistory => "search_history",
+        }
+    }
+
+    /// Look up a tool by the name the model called
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_json::from_value(json!(name)).ok()
+    }
+
+    /// Description and JSON Schema of the arguments, as sent to the provider
+    pub fn definition(self) -> ToolDefinition {
+        let (description, parameters) = match self {
+            Tool::DateTime => (
+                "Get the current local date, time, weekday and UTC offset.",
+                json!({"type": "object", "properties": {}}),
+            ),
+            Tool::Calculator => (
+                "Evaluate an arithmetic expression. Supports + - * / % ^, parentheses, \
+                 pi, e and the functions sqrt, abs, round, floor, ceil, ln, log, sin, cos and tan \
+                 (radians).",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "expression": {"type": "string", "description": "For example (2 + 3) * sqrt(16)"}
+                    },
+                    "required": ["expression"]
+                }),
+            ),
+            Tool::ConvertUnits => (
+                "Convert a value between units of length, area, mass, volume, time, speed, \
+                 data size or temperature (e.g. km, mi, lb, kg, gal, l, mph, GB, GiB, C, F, K).",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "value": {"type": "number"},
+                        "from": {"type": "string", "description": "Unit of the value"},
+                        "to": {"type": "string", "description": "Unit to convert to"}
+                    },
+                    "required": ["value", "from", "to"]
+                }),
+            ),
+            Tool::ReadFile => (
+                "Read the text of a file (text, source code, PDF or DOCX) from one of the \
+                 folders the user allowed.",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {"type": "string", "description": "Absolute path of the file"}
+                    },
+                    "required": ["path"]
+                }),
+            ),
+            Tool::SearchHistory => (
+                "Search the user's AI Anywhere history (past prompts and responses) for a word \
+                 or phrase. Returns the most recent matches.",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {"type": "string"},
+                        "limit": {"type": "integer", "minimum": 1, "maximum": MAX_SEARCH_RESULTS}
+                    },
+                    "required": ["query"]
+                }),
+            ),
+        };
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: description.to_string(),
+            parameters,
+        }
+    }
+
+    /// Run the tool. Failures are returned as text for the model to read.
+    pub async fn run(self, arguments: &Value, folders: &[String]) -> String {
+        let result = match self {
+            Tool::DateTime => Ok(date_time()),
+            Tool::Calculator => string_arg(arguments, "expression")
+                .and_then(evaluate)
+                .map(format_number),
+            Tool::ConvertUnits => convert_units(arguments),
+            Tool::ReadFile => match string_arg(arguments, "path") {
+                Ok(path) => read_file(path, folders).await,
+                Err(e) => Err(e),
+            },
+            Tool::SearchHistory => search_history(arguments).await,
+        };
+        result.unwrap_or_else(|e| format!("Error: {}", e))
+    }
+}
+
+/// Run a tool call if it names one of the tools offered to the model
+pub async fn run_call(call: &ToolCall, offered: &[ToolDefinition], folders: &[String]) -> String {
+    match Tool::from_name(&call.name) {
+        Some(tool) if offered.iter().any(|d| d.name == call.name) => {
+            tool.run(&call.arguments, folders).await
+        }
+        _ => format!("Error: unknown tool '{}'", call.name),
+    }
+}
+
+fn string_arg<'a>(arguments: &'a Value, key: &str) -> Result<&'a str, String> {
+    arguments[key]
+        .as_str()
+        .ok_or_else(|| format!("missing string argument '{}'", key))
+}
+
+fn date_time() -> String {
+    let now = chrono::Local::now();
+    format!(
+        "{} ({}, UTC{})",
+        now.format("%Y-%m-%d %H:%M:%S"),
+        now.format("%A"),
+        now.format("%:z")
+    )
+}
+
+/// Numbers without float noise: 0.1 + 0.2 reads as 0.3
+fn format_number(value: f64) -> String {
+    let rounded = (value * 1e10).round() / 1e10;
+    if rounded == 0.0 {
+        "0".to_string()
+    } else {
+        rounded.to_string()
+    }
+}
+
+/// Evaluate an arithmetic expression
+fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+    };
+    let value = parser.expression()?;
+    if let Some(c) = parser.peek() {
+        return Err(format!("unexpected '{}' in expression", c));
+    }
+    if !value.is_finite() {
+        return Err("the result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// Recursive-descent parser over `expression := term (('+' | '-') term)*`,
+/// `term := unary (('*' | '/' | '%') unary)*`, `unary := ('-' | '+') unary | power`
+/// and `power := atom ('^' unary)?`
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                value %= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            Ok(-self.unary()?)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            // Right-associative and tighter than minus: 2^3^2 = 2^9, -2^2 = -4
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        if self.eat('(') {
+            let value = self.expression()?;
+            if !self.eat(')') {
+                return Err("missing ')'".to_string());
+            }
+            return Ok(value);
+        }
+
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.pos += 1;
+                }
+                let number: String = self.chars[start..self.pos].iter().collect();
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}'", number))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                match name.to_lowercase().as_str() {
+                    "pi" => Ok(std::f64::consts::PI),
+                    "e" => Ok(std::f64::consts::E),
+                    function => {
+                        if self.peek() != Some('(') {
+                            return Err(format!("unknown name '{}'", name));
+                        }
+                        let argument = self.atom()?;
+                        apply_function(function, argument)
+                    }
+                }
+            }
+            Some(c) => Err(format!("unexpected '{}' in expression", c)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn apply_function(name: &str, x: f64) -> Result<f64, String> {
+    Ok(match name {
+        "sqrt" => x.sqrt(),
+        "abs" => x.abs(),
+        "round" => x.round(),
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        "ln" => x.ln(),
+        "log" => x.log10(),
+        "sin" => x.sin(),
+        "cos" => x.cos(),
+        "tan" => x.tan(),
+        _ => return Err(format!("unknown function '{}'", name)),
+    })
+}
+
+/// Linear units: quantity, names (lowercase) and size in the quantity's base unit
+const UNITS: &[(&str, &[&str], f64)] = &[
+    ("length", &["m", "meter", "metre"], 1.0),
+    ("length", &["km", "kilometer", "kilometre"], 1000.0),
+    ("length", &["cm", "centimeter", "centimetre"], 0.01),
+    ("length", &["mm", "millimeter", "millimetre"], 0.001),
+    ("length", &["mi", "mile"], 1609.344),
+    ("length", &["yd", "yard"], 0.9144),
+    ("length", &["ft", "foot", "feet"], 0.3048),
+    ("length", &["in", "inch", "inches"], 0.0254),
+    ("length", &["nmi", "nautical mile"], 1852.0),
+    ("area", &["m2", "m²", "square meter", "square metre"], 1.0),
+    (
+        "area",
+        &["km2", "km²", "square kilometer", "square kilometre"],
+        1e6,
+    ),
+    (
+        "area",
+        &["cm2", "cm²", "square centimeter", "square centimetre"],
+        1e-4,
+    ),
+    ("area", &["ha", "hectare"], 1e4),
+    ("area", &["acre"], 4_046.856_422_4),
+    (
+        "area",
+        &["ft2", "ft²", "square foot", "square feet"],
+        0.092_903_04,
+    ),
+    ("area", &["mi2", "mi²", "square mile"], 2_589_988.110_336),
+    ("mass", &["kg", "kilogram"], 1.0),
+    ("mass", &["g", "gram"], 0.001),
+    ("mass", &["mg", "milligram"], 1e-6),
+    ("mass", &["t", "tonne", "metric ton"], 1000.0),
+    ("mass", &["lb", "lbs", "pound"], 0.453_592_37),
+    ("mass", &["oz", "ounce"], 0.028_349_523_125),
+    ("mass", &["st", "stone"], 6.350_293_18),
+    ("volume", &["l", "liter", "litre"], 1.0),
+    ("volume", &["ml", "milliliter", "millilitre"], 0.001),
+    (
+        "volume",
+        &["m3", "m³", "cubic meter", "cubic metre"],
+        1000.0,
+    ),
+    ("volume", &["gal", "gallon"], 3.785_411_784),
+    ("volume", &["qt", "quart"], 0.946_352_946),
+    ("volume", &["pt", "pint"], 0.473_176_473),
+    ("volume", &["cup"], 0.236_588_236_5),
+    (
+        "volume",
+        &["fl oz", "floz", "fluid ounce"],
+        0.029_573_529_562_5,
+    ),
+    ("volume", &["tbsp", "tablespoon"], 0.014_786_764_781_25),
+    ("volume", &["tsp", "teaspoon"], 0.004_928_921_593_75),
+    ("time", &["s", "sec", "second"], 1.0),
+    ("time", &["ms", "millisecond"], 0.001),
+    ("time", &["min", "minute"], 60.0),
+    ("time", &["h", "hr", "hour"], 3600.0),
+    ("time", &["d", "day"], 86_400.0),
+    ("time", &["wk", "week"], 604_800.0),
+    ("time", &["yr", "year"], 31_557_600.0),
+    ("speed", &["m/s"], 1.0),
+    ("speed", &["km/h", "kph"], 1.0 / 3.6),
+    ("speed", &["mph"], 0.447_04),
+    ("speed", &["kn", "knot"], 1852.0 / 3600.0),
+    ("speed", &["ft/s"], 0.3048),
+    ("data", &["b", "byte"], 1.0),
+    ("data", &["bit"], 0.125),
+    ("data", &["kb", "kilobyte"], 1e3),
+    ("data", &["mb", "megabyte"], 1e6),
+    ("data", &["gb", "gigabyte"], 1e9),
+    ("data", &["tb", "terabyte"], 1e12),
+    ("data", &["kib", "kibibyte"], 1024.0),
+    ("data", &["mib", "mebibyte"], 1_048_576.0),
+    ("data", &["gib", "gibibyte"], 1_073_741_824.0),
+    ("data", &["tib", "tebibyte"], 1_099_511_627_776.0),
+];
+
+/// Quantity and size in the base unit of a linear unit, accepting plural names
+fn linear_unit(unit: &str) -> Option<(&'static str, f64)> {
+    let find = |name: &str| {
+        UNITS
+            .iter()
+            .find(|(_, names, _)| names.contains(&name))
+            .map(|(quantity, _, factor)| (*quantity, *factor))
+    };
+    find(unit).or_else(|| unit.strip_suffix('s').and_then(find))
+}
+
+/// Temperature scale of a unit name
+fn temperature_scale(unit: &str) -> Option<char> {
+    match unit.trim_start_matches('°') {
+        "c" | "celsius" => Some('c'),
+        "f" | "fahrenheit" => Some('f'),
+        "k" | "kelvin" => Some('k'),
+        _ => None,
+    }
+}
+
+fn convert_units(arguments: &Value) -> Result<String, String> {
+    let value = arguments["value"]
+        .as_f64()
+        .ok_or("missing number argument 'value'")?;
+    let from = string_arg(arguments, "from")?;
+    let to = string_arg(arguments, "to")?;
+    let converted = convert(
+        value,
+        &from.trim().to_lowercase(),
+        &to.trim().to_lowercase(),
+    )?;
+    Ok(format!(
+        "{} {} = {} {}",
+        format_number(value),
+        from,
+        format_number(converted),
+        to
+    ))
+}
+
+fn convert(value: f64, from: &str, to: &str) -> Result<f64, String> {
+    if let (Some(from), Some(to)) = (temperature_scale(from), temperature_scale(to)) {
+        let kelvin = match from {
+            'c' => value + 273.15,
+            'f' => (value - 32.0) * 5.0 / 9.0 + 273.15,
+            _ => value,
+        };
+        return Ok(match to {
+            'c' => kelvin - 273.15,
+            'f' => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+            _ => kelvin,
+        });
+    }
+
+    let unknown = |unit: &str| format!("unknown unit '{}'", unit);
+    let (from_quantity, from_factor) = linear_unit(from).ok_or_else(|| unknown(from))?;
+    let (to_quantity, to_factor) = linear_unit(to).ok_or_else(|| unknown(to))?;
+    if from_quantity != to_quantity {
+        return Err(format!(
+            "cannot convert {} ({}) to {} ({})",
+            from, from_quantity, to, to_quantity
+        ));
+    }
+    Ok(value * from_factor / to_factor)
+}
+
+/// Read a file's text if it is inside one of the allowed folders
+async fn read_file(path: &str, folders: &[String]) -> Result<String, String> {
+    if folders.is_empty() {
+        return Err("no folders are allowed for reading files".to_string());
+    }
+    // Resolve `..` and links before checking, so paths cannot escape the folders
+    let path = Path::new(path)
+        .canonicalize()
+        .map_err(|e| format!("cannot open {}: {}", path, e))?;
+    let allowed = folders
+        .iter()
+        .filter_map(|folder| PathBuf::from(folder).canonicalize().ok())
+        .any(|folder| path.starts_with(folder));
+    if !allowed {
+        return Err(format!(
+            "{} is outside the allowed folders ({})",
+            path.display(),
+            folders.join(", ")
+        ));
+    }
+
+    let attachment = read_attachment(path).await?;
+    Ok(truncate(&attachment.text, MAX_TOOL_OUTPUT_CHARS))
+}
+
+async fn search_history(arguments: &Value) -> Result<String, String> {
+    let query = string_arg(arguments, "query")?;
+    let limit = arguments["limit"]
+        .as_u64()
+        .map_or(DEFAULT_SEARCH_RESULTS, |limit| limit as usize)
+        .clamp(1, MAX_SEARCH_RESULTS);
+
+    let entries = HistoryManager::search(query).await?;
+    if entries.is_empty() {
+        return Ok(format!("No history entries match '{}'", query));
+    }
+    let results: Vec<Value> = entries
+        .iter()
+        .take(limit)
+        .map(|entry| {
+            json!({
+                "date": entry.created_at,
+                "operation": entry.operation_type,
+                "prompt": truncate(&entry.prompt_text, HISTORY_EXCERPT_CHARS),
+                "response": entry
+                    .response_text
+                    .as_deref()
+                    .map(|text| truncate(text, HISTORY_EXCERPT_CHARS)),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&results).map_err(|e| e.to_string())
+}
+
+/// The first `max_chars` characters, marked when something was cut
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}\n[... truncated]", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculator() {
+        assert_eq!(evaluate("(2 + 3) * sqrt(16)").unwrap(), 20.0);
+        assert_eq!(evaluate("2^3^2").unwrap(), 512.0);
+        assert_eq!(evaluate("-2^2 + 10 % 4").unwrap(), -2.0);
+        assert_eq!(format_number(evaluate("0.1 + 0.2").unwrap()), "0.3");
+        assert_eq!(
+            format_number(evaluate("round(pi * 100) / 100").unwrap()),
+            "3.14"
+        );
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("2 +").is_err());
+        assert!(evaluate("foo(2)").is_err());
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        assert_eq!(format_number(convert(5.0, "km", "m").unwrap()), "5000");
+        assert_eq!(
+            format_number(convert(1.0, "miles", "km").unwrap()),
+            "1.609344"
+        );
+        assert_eq!(format_number(convert(100.0, "c", "°f").unwrap()), "212");
+        assert_eq!(
+            format_number(convert(1.0, "gib", "mb").unwrap()),
+            "1073.741824"
+        );
+        assert!(convert(1.0, "kg", "m")
+            .unwrap_err()
+            .contains("cannot convert"));
+        assert!(convert(1.0, "parsec", "m").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_files_outside_allowed_folders_are_refused() {
+        let dir = std::env::temp_dir().join("ai-anywhere-tool-test");
+        let allowed = dir.join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::write(allowed.join("notes.txt"), "meeting at 10").unwrap();
+        std::fs::write(dir.join("secret.txt"), "password").unwrap();
+        let folders = vec![allowed.to_string_lossy().to_string()];
+
+        let notes = allowed.join("notes.txt");
+        assert_eq!(
+            read_file(notes.to_str().unwrap(), &folders).await.unwrap(),
+            "meeting at 10"
+        );
+        let escape = allowed.join("..").join("secret.txt");
+        assert!(read_file(escape.to_str().unwrap(), &folders)
+            .await
+            .unwrap_err()
+            .contains("outside the allowed folders"));
+        assert!(read_file(notes.to_str().unwrap(), &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_offered_tools_run() {
+        let call = |name: &str| ToolCall {
+            id: "call_0".to_string(),
+            name: name.to_string(),
+            arguments: json!({"expression": "6 * 7"}),
+            signature: None,
+        };
+        let offered = vec![Tool::Calculator.definition()];
+
+        assert_eq!(run_call(&call("calculator"), &offered, &[]).await, "42");
+        assert_eq!(
+            run_call(&call("read_file"), &offered, &[]).await,
+            "Error: unknown tool 'read_file'"
+        );
+    }
+}
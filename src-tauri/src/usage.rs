@@ -0,0 +1,510 @@
+//! Usage ledger for AI Anywhere
+//! Records the token usage and cost of every completion, totals it by day and month
+//! and measures it against the budgets of endpoint profiles
+
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use tokio::fs as async_fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{Budget, BudgetUnit, Configuration, EndpointProfile};
+use crate::providers::Usage;
+
+/// One completion in the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRecord {
+    pub timestamp: String,
+    pub profile_id: String,
+    pub model: String,
+    /// Operation type of a built-in operation, or the name of a custom task
+    pub operation: String,
+    pub usage: Usage,
+    /// Cost in the configured currency; absent when the model has no price
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+impl UsageRecord {
+    /// Record a completion made now, priced with the configured price table
+    pub fn new(
+        config: &Configuration,
+        profile_id: String,
+        model: String,
+        operation: String,
+        usage: Usage,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            cost: config.model_price(&model).map(|price| price.cost(&usage)),
+            profile_id,
+            model,
+            operation,
+            usage,
+        }
+    }
+
+    /// Calendar day of the record in local time
+    pub fn local_date(&self) -> Option<NaiveDate> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Local).date_naive())
+    }
+}
+
+/// Aggregated usage of a set of records
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Cost of the priced requests
+    pub cost: f64,
+    /// Requests whose model had no price when they were made
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.prompt_tokens += record.usage.prompt_tokens as u64;
+        self.completion_tokens += record.usage.completion_tokens as u64;
+        self.total_tokens += record.usage.total_tokens as u64;
+        match record.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+/// Totals of one model or operation within a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageGroup {
+    pub name: String,
+    pub totals: UsageTotals,
+}
+
+/// Totals of one day ("2026-10-17") or month ("2026-10")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsagePeriod {
+    pub period: String,
+    pub totals: UsageTotals,
+    /// Most expensive first
+    pub by_model: Vec<UsageGroup>,
+    /// Most expensive first
+    pub by_operation: Vec<UsageGroup>,
+}
+
+/// Daily and monthly usage, newest period first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    pub currency: String,
+    pub daily: Vec<UsagePeriod>,
+    pub monthly: Vec<UsagePeriod>,
+}
+
+/// Accumulates the records of one period
+#[derive(Default)]
+struct PeriodBuilder {
+    totals: UsageTotals,
+    by_model: HashMap<String, UsageTotals>,
+    by_operation: HashMap<String, UsageTotals>,
+}
+
+impl PeriodBuilder {
+    fn add(&mut self, record: &UsageRecord) {
+        self.totals.add(record);
+        self.by_model
+            .entry(record.model.clone())
+            .or_default()
+            .add(record);
+        self.by_operation
+            .entry(record.operation.clone())
+            .or_default()
+            .add(record);
+    }
+
+    fn build(self, period: String) -> UsagePeriod {
+        fn groups(map: HashMap<String, UsageTotals>) -> Vec<UsageGroup> {
+            let mut groups: Vec<UsageGroup> = map
+                .into_iter()
+                .map(|(name, totals)| UsageGroup { name, totals })
+                .collect();
+            groups.sort_by(|a, b| {
+                b.totals
+                    .cost
+                    .total_cmp(&a.totals.cost)
+                    .then(b.totals.total_tokens.cmp(&a.totals.total_tokens))
+                    .then(a.name.cmp(&b.name))
+            });
+            groups
+        }
+
+        UsagePeriod {
+            period,
+            totals: self.totals,
+            by_model: groups(self.by_model),
+            by_operation: groups(self.by_operation),
+        }
+    }
+}
+
+/// Total the records of the last `days` days and `months` calendar months up to `today`.
+/// Periods without records are left out.
+pub fn summarize(
+    records: &[UsageRecord],
+    today: NaiveDate,
+    days: u32,
+    months: u32,
+) -> (Vec<UsagePeriod>, Vec<UsagePeriod>) {
+    let first_day = today - chrono::Duration::days(days.saturating_sub(1) as i64);
+    let month_index = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+    let first_month = month_index(today) - months.saturating_sub(1) as i32;
+
+    let mut daily: BTreeMap<NaiveDate, PeriodBuilder> = BTreeMap::new();
+    let mut monthly: BTreeMap<i32, PeriodBuilder> = BTreeMap::new();
+    for record in records {
+        let Some(date) = record.local_date() else {
+            continue;
+        };
+        if date > today {
+            continue;
+        }
+        if days > 0 && date >= first_day {
+            daily.entry(date).or_default().add(record);
+        }
+        if months > 0 && month_index(date) >= first_month {
+            monthly.entry(month_index(date)).or_default().add(record);
+        }
+    }
+
+    let daily = daily
+        .into_iter()
+        .rev()
+        .map(|(date, builder)| builder.build(date.format("%Y-%m-%d").to_string()))
+        .collect();
+    let monthly = monthly
+        .into_iter()
+        .rev()
+        .map(|(index, builder)| {
+            builder.build(format!(
+                "{:04}-{:02}",
+                index.div_euclid(12),
+                index.rem_euclid(12) + 1
+            ))
+        })
+        .collect();
+    (daily, monthly)
+}
+
+/// Period a budget limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetPeriod {
+    Daily,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// When the limit starts over
+    pub fn reset(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "tomorrow",
+            BudgetPeriod::Monthly => "next month",
+        }
+    }
+}
+
+/// How much of one budget limit has been spent in the current period
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetUse {
+    pub period: BudgetPeriod,
+    /// Day ("2026-10-17") or month ("2026-10") being measured
+    pub period_key: String,
+    pub unit: BudgetUnit,
+    pub spent: f64,
+    pub limit: f64,
+}
+
+impl BudgetUse {
+    pub fn percent(&self) -> f64 {
+        self.spent / self.limit * 100.0
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.spent >= self.limit
+    }
+
+    /// Spent and limit amounts, e.g. "104211 of 100000 tokens" or "4.20 of 5.00 USD"
+    pub fn amounts(&self, currency: &str) -> String {
+        match self.unit {
+            BudgetUnit::Tokens => format!("{:.0} of {:.0} tokens", self.spent, self.limit),
+            BudgetUnit::Currency => {
+                format!("{:.2} of {:.2} {}", self.spent, self.limit, currency)
+            }
+        }
+    }
+}
+
+/// Spending of a profile against each limit of its budget on `today` and in its month
+pub fn budget_use(
+    records: &[UsageRecord],
+    profile_id: &str,
+    budget: &Budget,
+    today: NaiveDate,
+) -> Vec<BudgetUse> {
+    let amount = |record: &UsageRecord| match budget.unit {
+        BudgetUnit::Tokens => record.usage.total_tokens as f64,
+        BudgetUnit::Currency => record.cost.unwrap_or(0.0),
+    };
+    let same_month = |date: NaiveDate| date.year() == today.year() && date.month() == today.month();
+
+    let mut daily = 0.0;
+    let mut monthly = 0.0;
+    for record in records.iter().filter(|r| r.profile_id == profile_id) {
+        let Some(date) = record.local_date() else {
+            continue;
+        };
+        if date == today {
+            daily += amount(record);
+        }
+        if same_month(date) && date <= today {
+            monthly += amount(record);
+        }
+    }
+
+    let mut uses = Vec::new();
+    if let Some(limit) = budget.daily_limit {
+        uses.push(BudgetUse {
+            period: BudgetPeriod::Daily,
+            period_key: today.format("%Y-%m-%d").to_string(),
+            unit: budget.unit,
+            spent: daily,
+            limit,
+        });
+    }
+    if let Some(limit) = budget.monthly_limit {
+        uses.push(BudgetUse {
+            period: BudgetPeriod::Monthly,
+            period_key: today.format("%Y-%m").to_string(),
+            unit: budget.unit,
+            spent: monthly,
+            limit,
+        });
+    }
+    uses
+}
+
+/// Usage manager for the ledger file (one JSON record per line)
+pub struct UsageManager;
+
+impl UsageManager {
+    /// Get the app data directory path (cross-platform)
+    /// - Windows: C:\Users\<user>\AppData\Roaming\ai-anywhere
+    /// - macOS: ~/Library/Application Support/ai-anywhere
+    /// - Linux: ~/.local/share/ai-anywhere
+    fn get_app_data_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ai-anywhere")
+    }
+
+    /// Get the ledger file path
+    pub fn get_ledger_path() -> PathBuf {
+        let data_dir = Self::get_app_data_dir();
+
+        if let Err(e) = fs::create_dir_all(&data_dir) {
+            eprintln!(
+                "[UsageManager] Failed to create data directory {:?}: {}",
+                data_dir, e
+            );
+        }
+        data_dir.join("usage.jsonl")
+    }
+
+    /// Append a record to the ledger
+    pub async fn record(record: &UsageRecord) -> Result<(), String> {
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize usage record: {}", e))?;
+        line.push('\n');
+
+        let mut file = async_fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::get_ledger_path())
+            .await
+            .map_err(|e| format!("Failed to open usage ledger: {}", e))?;
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write usage ledger: {}", e))
+    }
+
+    /// Load every record; unreadable lines (e.g. from an interrupted write) are skipped
+    pub async fn load() -> Result<Vec<UsageRecord>, String> {
+        let ledger_path = Self::get_ledger_path();
+
+        if !ledger_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = async_fs::read_to_string(&ledger_path)
+            .await
+            .map_err(|e| format!("Failed to read usage ledger: {}", e))?;
+
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Daily totals for the last `days` days and monthly totals for the last `months` months
+    pub async fn summary(
+        config: &Configuration,
+        days: u32,
+        months: u32,
+    ) -> Result<UsageSummary, String> {
+        let records = Self::load().await?;
+        let (daily, monthly) = summarize(&records, Local::now().date_naive(), days, months);
+        Ok(UsageSummary {
+            currency: config.currency.clone(),
+            daily,
+            monthly,
+        })
+    }
+
+    /// Current spending against the budget limits of a profile (empty when it has none)
+    pub async fn budget_use(profile: &EndpointProfile) -> Result<Vec<BudgetUse>, String> {
+        if profile.budget.daily_limit.is_none() && profile.budget.monthly_limit.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let records = Self::load().await?;
+        Ok(budget_use(
+            &records,
+            &profile.id,
+            &profile.budget,
+            Local::now().date_naive(),
+        ))
+    }
+
+    /// Whether a profile has used up one of its budget limits
+    pub async fn is_exhausted(profile: &EndpointProfile) -> Result<bool, String> {
+        let uses = Self::budget_use(profile).await?;
+        Ok(uses.iter().any(|u| u.is_exceeded()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(
+        date: (i32, u32, u32),
+        model: &str,
+        operation: &str,
+        cost: Option<f64>,
+    ) -> UsageRecord {
+        UsageRecord {
+            timestamp: Local
+                .with_ymd_and_hms(date.0, date.1, date.2, 12, 0, 0)
+                .unwrap()
+                .to_rfc3339(),
+            profile_id: "default".to_string(),
+            model: model.to_string(),
+            operation: operation.to_string(),
+            usage: Usage::new(100, 50),
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_summarize_by_day_and_month() {
+        let records = vec![
+            record((2026, 9, 30), "gpt-4o", "TextTranslation", Some(0.5)),
+            record((2026, 10, 16), "gpt-4o", "TextTranslation", Some(0.5)),
+            record((2026, 10, 17), "gpt-4o", "TextRewrite", Some(0.5)),
+            record((2026, 10, 17), "llama3", "TextRewrite", None),
+            record((2026, 10, 17), "gpt-4o-mini", "Meeting Notes", Some(0.25)),
+        ];
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+
+        let (daily, monthly) = summarize(&records, today, 2, 12);
+        assert_eq!(
+            daily.iter().map(|p| p.period.as_str()).collect::<Vec<_>>(),
+            vec!["2026-10-17", "2026-10-16"]
+        );
+        let today_totals = &daily[0].totals;
+        assert_eq!(today_totals.requests, 3);
+        assert_eq!(today_totals.total_tokens, 450);
+        assert_eq!(today_totals.unpriced_requests, 1);
+        assert!((today_totals.cost - 0.75).abs() < 1e-9);
+        assert_eq!(daily[0].by_model[0].name, "gpt-4o");
+        assert_eq!(daily[0].by_operation[0].name, "TextRewrite");
+        assert_eq!(daily[0].by_operation[0].totals.requests, 2);
+
+        assert_eq!(
+            monthly
+                .iter()
+                .map(|p| p.period.as_str())
+                .collect::<Vec<_>>(),
+            vec!["2026-10", "2026-09"]
+        );
+        assert_eq!(monthly[0].totals.requests, 4);
+
+        let (_, monthly) = summarize(&records, today, 0, 1);
+        assert_eq!(monthly.len(), 1);
+    }
+
+    #[test]
+    fn test_budget_use_per_profile() {
+        let mut other_profile = record((2026, 10, 17), "gpt-4o", "TextRewrite", Some(9.0));
+        other_profile.profile_id = "local".to_string();
+        let records = vec![
+            record((2026, 9, 30), "gpt-4o", "TextRewrite", Some(0.5)),
+            record((2026, 10, 1), "gpt-4o", "TextRewrite", Some(0.5)),
+            record((2026, 10, 17), "gpt-4o", "TextRewrite", Some(0.5)),
+            record((2026, 10, 17), "llama3", "TextRewrite", None),
+            other_profile,
+        ];
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+
+        let tokens = Budget {
+            daily_limit: Some(300.0),
+            ..Default::default()
+        };
+        let uses = budget_use(&records, "default", &tokens, today);
+        assert_eq!(uses.len(), 1);
+        assert_eq!(uses[0].spent, 300.0);
+        assert!(uses[0].is_exceeded());
+        assert_eq!(uses[0].amounts("USD"), "300 of 300 tokens");
+
+        let currency = Budget {
+            unit: BudgetUnit::Currency,
+            daily_limit: Some(2.0),
+            monthly_limit: Some(1.25),
+            ..Default::default()
+        };
+        let uses = budget_use(&records, "default", &currency, today);
+        assert_eq!(uses[0].period, BudgetPeriod::Daily);
+        assert!((uses[0].percent() - 25.0).abs() < 1e-9);
+        assert_eq!(uses[1].period_key, "2026-10");
+        assert!((uses[1].spent - 1.0).abs() < 1e-9);
+        assert!(!uses[1].is_exceeded());
+        assert_eq!(uses[1].amounts("EUR"), "1.00 of 1.25 EUR");
+    }
+}
@@ -0,0 +1,361 @@
+//! Image input for multimodal models
+//! Loads the images attached to a request from the clipboard, a file or the history media
+//! folder, scaling down large ones so they fit the providers' limits
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::history::HistoryManager;
+use crate::providers::ImageAttachment;
+
+/// Longest side sent to the model; providers scale larger images down anyway
+const MAX_IMAGE_DIMENSION: u32 = 2048;
+
+/// Largest encoded image sent as is (Anthropic's per-image limit)
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Most images accepted in one request
+const MAX_IMAGES: usize = 10;
+
+/// Longest side of an image sent for editing or variations: the largest size DALL-E 2
+/// works with, which also keeps the PNG under its 4 MB upload limit
+const MAX_EDIT_DIMENSION: u32 = 1024;
+
+/// Where an image attached to a request comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum ImageInput {
+    /// The image currently on the clipboard
+    Clipboard,
+    /// An image file on disk
+    File { path: String },
+    /// A media file of a history entry (e.g. a generated image)
+    History { path: String },
+}
+
+/// Load every image of a request. The clipboard is only readable with an app handle.
+pub async fn load_images(
+    inputs: &[ImageInput],
+    app: Option<&AppHandle>,
+) -> Result<Vec<ImageAttachment>, String> {
+    if inputs.len() > MAX_IMAGES {
+        return Err(format!(
+            "Too many images ({}); at most {} can be attached",
+            inputs.len(),
+            MAX_IMAGES
+        ));
+    }
+    let sources = inputs
+        .iter()
+        .map(|input| Source::read(input, app))
+        .collect::<Result<Vec<_>, _>>()?;
+    blocking(move || sources.into_iter().map(Source::load).collect()).await
+}
+
+/// Load the input image of an image edit or variation, and the mask of an edit, as PNG
+/// (the format those endpoints take). The mask is scaled to the size the image is sent at.
+pub async fn load_edit_images(
+    image: &ImageInput,
+    mask: Option<&ImageInput>,
+    app: Option<&AppHandle>,
+) -> Result<(Vec<u8>, Option<Vec<u8>>), String> {
+    let image = Source::read(image, app)?;
+    let mask = mask.map(|mask| Source::read(mask, app)).transpose()?;
+    blocking(move || {
+        let image = fit_image(image.decode()?, MAX_EDIT_DIMENSION);
+        let mask = mask
+            .map(|mask| {
+                let mask = mask.decode()?;
+                encode_png(&mask.resize_exact(image.width(), image.height(), FilterType::Lanczos3))
+            })
+            .transpose()?;
+        Ok((encode_png(&image)?, mask))
+    })
+    .await
+}
+
+/// Run image reading, decoding and encoding on the blocking thread pool, as up to
+/// `MAX_IMAGES` large images would otherwise stall the async runtime
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| format!("Failed to load the images: {}", e))?
+}
+
+/// An image input with the clipboard already read, as the clipboard is read through
+/// the app handle before the rest of the work moves to the blocking thread pool
+enum Source {
+    Clipboard(DynamicImage),
+    File(PathBuf),
+    History(String),
+}
+
+impl Source {
+    fn read(input: &ImageInput, app: Option<&AppHandle>) -> Result<Self, String> {
+        Ok(match input {
+            ImageInput::Clipboard => Source::Clipboard(read_clipboard_image(app)?),
+            ImageInput::File { path } => Source::File(PathBuf::from(path)),
+            ImageInput::History { path } => Source::History(path.clone()),
+        })
+    }
+
+    /// The image as sent to a multimodal model
+    fn load(self) -> Result<ImageAttachment, String> {
+        match self {
+            Source::Clipboard(image) => {
+                encode_image(fit_image(image, MAX_IMAGE_DIMENSION), MAX_IMAGE_BYTES)
+            }
+            Source::File(path) => read_image_file(&path),
+            Source::History(path) => read_image_file(&history_media_path(&path)?),
+        }
+    }
+
+    /// Decode the image, whatever its source
+    fn decode(self) -> Result<DynamicImage, String> {
+        let path = match self {
+            Source::Clipboard(image) => return Ok(image),
+            Source::File(path) => path,
+            Source::History(path) => history_media_path(&path)?,
+        };
+        let bytes = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+        image::load_from_memory(&bytes).map_err(|e| format!("Failed to decode image: {}", e))
+    }
+}
+
+fn read_clipboard_image(app: Option<&AppHandle>) -> Result<DynamicImage, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let app = app.ok_or_else(|| "The clipboard is not available".to_string())?;
+    let image = app
+        .clipboard()
+        .read_image()
+        .map_err(|_| "The clipboard does not contain an image".to_string())?;
+    let rgba = RgbaImage::from_raw(image.width(), image.height(), image.rgba().to_vec())
+        .ok_or_else(|| "Invalid clipboard image".to_string())?;
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+/// Only files inside the media folder, so a request cannot name arbitrary paths
+fn history_media_path(path: &str) -> Result<PathBuf, String> {
+    let resolved = Path::new(path)
+        .canonicalize()
+        .map_err(|_| format!("History media file not found: {}", path))?;
+    let media_dir = HistoryManager::get_media_path()
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve the media folder: {}", e))?;
+    if !resolved.starts_with(&media_dir) {
+        return Err("Not a history media file".to_string());
+    }
+    Ok(resolved)
+}
+
+fn read_image_file(path: &Path) -> Result<ImageAttachment, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+    prepare_image(bytes)
+}
+
+/// Send PNG, JPEG, GIF and WebP images within the limits as they are;
+/// decode anything else (or anything larger) and re-encode it scaled down
+pub fn prepare_image(bytes: Vec<u8>) -> Result<ImageAttachment, String> {
+    let reader = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let format = reader
+        .format()
+        .ok_or_else(|| "Unsupported image format".to_string())?;
+
+    let media_type = match format {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    };
+    if let Some(media_type) = media_type {
+        let (width, height) = reader
+            .into_dimensions()
+            .map_err(|e| format!("Failed to decode image: {}", e))?;
+        if width.max(height) <= MAX_IMAGE_DIMENSION && bytes.len() <= MAX_IMAGE_BYTES {
+            return Ok(ImageAttachment {
+                media_type: media_type.to_string(),
+                data: STANDARD.encode(&bytes),
+            });
+        }
+    }
+
+    let image = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    encode_image(fit_image(image, MAX_IMAGE_DIMENSION), MAX_IMAGE_BYTES)
+}
+
+/// Scale an image down so its longest side is at most `max_dimension`
+fn fit_image(image: DynamicImage, max_dimension: u32) -> DynamicImage {
+    if image.width().max(image.height()) <= max_dimension {
+        return image;
+    }
+    image.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+}
+
+/// Encode as an RGBA PNG
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(image.to_rgba8())
+        .write_to(&mut bytes, ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(bytes.into_inner())
+}
+
+/// Encode as PNG when the image has transparency, as JPEG otherwise (much smaller for
+/// photos). An image still over `max_bytes` is scaled down until it fits.
+fn encode_image(mut image: DynamicImage, max_bytes: usize) -> Result<ImageAttachment, String> {
+    // Clipboard images always have an alpha channel, usually fully opaque
+    let transparent = has_transparency(&image);
+    loop {
+        let mut bytes = Cursor::new(Vec::new());
+        let media_type = if transparent {
+            image
+                .write_to(&mut bytes, ImageFormat::Png)
+                .map(|_| "image/png")
+        } else {
+            DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_to(&mut bytes, ImageFormat::Jpeg)
+                .map(|_| "image/jpeg")
+        }
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+        let bytes = bytes.into_inner();
+        let longest = image.width().max(image.height());
+        if bytes.len() <= max_bytes || longest <= 1 {
+            return Ok(ImageAttachment {
+                media_type: media_type.to_string(),
+                data: STANDARD.encode(bytes),
+            });
+        }
+        let target = longest * 3 / 4;
+        image = image.resize(target, target, FilterType::Triangle);
+    }
+}
+
+/// Whether any pixel is not fully opaque
+fn has_transparency(image: &DynamicImage) -> bool {
+    match image {
+        DynamicImage::ImageRgba8(rgba) => rgba.pixels().any(|p| p[3] < u8::MAX),
+        image if image.color().has_alpha() => image.to_rgba8().pixels().any(|p| p[3] < u8::MAX),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::new_rgba8(width, height)
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_small_images_are_sent_unchanged() {
+        let bytes = png(64, 32);
+        let attachment = prepare_image(bytes.clone()).unwrap();
+        assert_eq!(attachment.media_type, "image/png");
+        assert_eq!(STANDARD.decode(attachment.data).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_large_images_are_scaled_down() {
+        let attachment = prepare_image(png(4096, 1024)).unwrap();
+        let bytes = STANDARD.decode(attachment.data).unwrap();
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (2048, 512));
+        // Transparency is kept
+        assert_eq!(attachment.media_type, "image/png");
+
+        let mut bmp = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(16, 16)
+            .write_to(&mut bmp, ImageFormat::Bmp)
+            .unwrap();
+        let attachment = prepare_image(bmp.into_inner()).unwrap();
+        assert_eq!(attachment.media_type, "image/jpeg");
+
+        assert!(prepare_image(b"not an image".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_opaque_images_are_sent_as_jpeg_within_the_limit() {
+        // Noise compresses badly, so the image must shrink to fit
+        let noise = |alpha: u8| {
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 256, |x, y| {
+                let v = (x.wrapping_mul(7919) ^ y.wrapping_mul(104729)).wrapping_mul(2654435761);
+                image::Rgba([v as u8, (v >> 8) as u8, (v >> 16) as u8, alpha])
+            }))
+        };
+
+        let attachment = encode_image(noise(255), 40_000).unwrap();
+        assert_eq!(attachment.media_type, "image/jpeg");
+        assert!(STANDARD.decode(attachment.data).unwrap().len() <= 40_000);
+
+        let attachment = encode_image(noise(128), 40_000).unwrap();
+        assert_eq!(attachment.media_type, "image/png");
+        let bytes = STANDARD.decode(attachment.data).unwrap();
+        assert!(bytes.len() <= 40_000);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert!(image.width() < 256);
+    }
+
+    #[tokio::test]
+    async fn test_edit_images_are_sent_as_png() {
+        let dir = std::env::temp_dir().join(format!("ai-anywhere-edit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image_path = dir.join("photo.jpg");
+        DynamicImage::new_rgb8(2048, 1024)
+            .save_with_format(&image_path, ImageFormat::Jpeg)
+            .unwrap();
+        let mask_path = dir.join("mask.png");
+        std::fs::write(&mask_path, png(64, 32)).unwrap();
+
+        let file = |path: &Path| ImageInput::File {
+            path: path.to_string_lossy().to_string(),
+        };
+        let (image, mask) = load_edit_images(&file(&image_path), Some(&file(&mask_path)), None)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let image = image::load_from_memory_with_format(&image, ImageFormat::Png).unwrap();
+        assert_eq!((image.width(), image.height()), (1024, 512));
+        assert!(image.color().has_alpha());
+        let mask = image::load_from_memory_with_format(&mask.unwrap(), ImageFormat::Png).unwrap();
+        assert_eq!((mask.width(), mask.height()), (1024, 512));
+    }
+
+    #[tokio::test]
+    async fn test_image_input_tags() {
+        let inputs: Vec<ImageInput> = serde_json::from_str(
+            r#"[{"source": "clipboard"}, {"source": "file", "path": "/tmp/shot.png"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            inputs,
+            vec![
+                ImageInput::Clipboard,
+                ImageInput::File {
+                    path: "/tmp/shot.png".to_string()
+                }
+            ]
+        );
+        assert!(load_images(&inputs[..1], None).await.is_err());
+    }
+}
@@ -118,7 +118,7 @@ export function AudioUpload({ onError }: AudioUploadProps) {
               </button>
             )}
             <p className="text-xs text-slate-500 dark:text-slate-500 mt-2">
-              Supported: {SUPPORTED_AUDIO_FORMATS.slice(0, 5).join(', ')}... (Max {formatFileSize(MAX_AUDIO_FILE_SIZE)})
+              Supported: {SUPPORTED_AUDIO_FORMATS.slice(0, 5).join(', ')}... (files over {formatFileSize(MAX_AUDIO_FILE_SIZE)} are split into parts)
             </p>
           </div>
         )}
@@ -0,0 +1,86 @@
+import { open } from '@tauri-apps/plugin-dialog';
+import { useApp } from '../context/AppContext';
+import { useI18n } from '../i18n/index';
+import { SUPPORTED_ATTACHMENT_FORMATS } from '../types';
+
+interface FileAttachmentsProps {
+  onError: (message: string) => void;
+}
+
+export function FileAttachments({ onError }: FileAttachmentsProps) {
+  const { attachments, addAttachments, removeAttachment } = useApp();
+  const { t, language } = useI18n();
+
+  const handleBrowse = async () => {
+    let paths: string[];
+    try {
+      const selected = await open({
+        multiple: true,
+        filters: [
+          {
+            name: 'Documents',
+            extensions: SUPPORTED_ATTACHMENT_FORMATS,
+          },
+          {
+            name: 'All files',
+            extensions: ['*'],
+          },
+        ],
+      });
+      paths = Array.isArray(selected) ? selected : selected ? [selected] : [];
+    } catch (error) {
+      console.error('Failed to open file dialog:', error);
+      onError('Failed to open file dialog');
+      return;
+    }
+
+    if (paths.length === 0) return;
+    try {
+      await addAttachments(paths);
+    } catch (error) {
+      // Unsupported, binary or unreadable file
+      onError(String(error));
+    }
+  };
+
+  return (
+    <div className="space-y-2">
+      <div className="flex items-center justify-between">
+        <label className="form-label">
+          {t.attachments.title}
+        </label>
+        <button
+          onClick={handleBrowse}
+          className="btn-text text-xs"
+        >
+          📎 {t.attachments.browse}
+        </button>
+      </div>
+
+      {attachments.length > 0 && (
+        <div className="flex flex-wrap gap-2">
+          {attachments.map(info => (
+            <span
+              key={info.path}
+              className="inline-flex items-center gap-1 px-2 py-1 text-xs rounded-lg
+                         bg-slate-100 dark:bg-slate-800 text-slate-700 dark:text-slate-300"
+              title={info.path}
+            >
+              <span className="truncate max-w-[12rem]">{info.name}</span>
+              <span className="text-slate-400 dark:text-slate-500">
+                ~{info.tokens.toLocaleString(language)} {t.attachments.tokens}
+              </span>
+              <button
+                onClick={() => removeAttachment(info.path)}
+                className="text-slate-400 hover:text-red-500 transition-colors"
+                title={t.attachments.remove}
+              >
+                ✕
+              </button>
+            </span>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
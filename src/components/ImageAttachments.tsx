@@ -0,0 +1,133 @@
+import { open } from '@tauri-apps/plugin-dialog';
+import { useApp } from '../context/AppContext';
+import { useI18n } from '../i18n/index';
+import { ImageInput, SUPPORTED_IMAGE_FORMATS } from '../types';
+
+interface ImageAttachmentsProps {
+  onError: (message: string) => void;
+  single?: boolean; // one input image (image edits and variations)
+  withMask?: boolean; // also offer a mask (image edits)
+}
+
+export function ImageAttachments({ onError, single = false, withMask = false }: ImageAttachmentsProps) {
+  const { imageInputs, addImageInput, removeImageInput, imageMask, setImageMask } = useApp();
+  const { t } = useI18n();
+
+  const hasClipboardImage = imageInputs.some(input => input.source === 'clipboard');
+  const isFull = single && imageInputs.length > 0;
+
+  const handleBrowse = async () => {
+    try {
+      const selected = await open({
+        multiple: !single,
+        filters: [{
+          name: 'Images',
+          extensions: SUPPORTED_IMAGE_FORMATS,
+        }],
+      });
+
+      const paths = Array.isArray(selected) ? selected : selected ? [selected] : [];
+      paths.forEach(path => addImageInput({ source: 'file', path }));
+    } catch (error) {
+      console.error('Failed to open file dialog:', error);
+      onError('Failed to open file dialog');
+    }
+  };
+
+  const handleBrowseMask = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        filters: [{
+          name: 'Images',
+          extensions: ['png'],
+        }],
+      });
+      if (typeof selected === 'string') {
+        setImageMask({ source: 'file', path: selected });
+      }
+    } catch (error) {
+      console.error('Failed to open file dialog:', error);
+      onError('Failed to open file dialog');
+    }
+  };
+
+  const label = (input: ImageInput) =>
+    input.source === 'clipboard'
+      ? t.images.clipboardImage
+      : input.path.split(/[/\\]/).pop();
+
+  return (
+    <div className="space-y-2">
+      <div className="flex items-center justify-between">
+        <label className="form-label">
+          {single ? t.images.inputImage : t.images.title}
+        </label>
+        <div className="flex items-center gap-2">
+          <button
+            onClick={() => addImageInput({ source: 'clipboard' })}
+            disabled={hasClipboardImage || isFull}
+            className="btn-text text-xs"
+          >
+            📋 {t.images.paste}
+          </button>
+          <button
+            onClick={handleBrowse}
+            disabled={isFull}
+            className="btn-text text-xs"
+          >
+            🖼️ {t.images.browse}
+          </button>
+        </div>
+      </div>
+
+      {imageInputs.length > 0 && (
+        <div className="flex flex-wrap gap-2">
+          {imageInputs.map((input, index) => (
+            <span
+              key={`${input.source}-${index}`}
+              className="inline-flex items-center gap-1 px-2 py-1 text-xs rounded-lg
+                         bg-slate-100 dark:bg-slate-800 text-slate-700 dark:text-slate-300"
+            >
+              <span className="truncate max-w-[12rem]">{label(input)}</span>
+              <button
+                onClick={() => removeImageInput(index)}
+                className="text-slate-400 hover:text-red-500 transition-colors"
+                title={t.images.remove}
+              >
+                ✕
+              </button>
+            </span>
+          ))}
+        </div>
+      )}
+
+      {withMask && (
+        <div className="flex items-center justify-between gap-2">
+          <span className="help-text" title={t.images.maskHelp}>
+            {t.images.mask}
+            {imageMask && (
+              <span className="inline-flex items-center gap-1 ml-2 px-2 py-0.5 text-xs rounded-lg
+                               bg-slate-100 dark:bg-slate-800 text-slate-700 dark:text-slate-300">
+                <span className="truncate max-w-[12rem]">{label(imageMask)}</span>
+                <button
+                  onClick={() => setImageMask(null)}
+                  className="text-slate-400 hover:text-red-500 transition-colors"
+                  title={t.images.removeMask}
+                >
+                  ✕
+                </button>
+              </span>
+            )}
+          </span>
+          <button
+            onClick={handleBrowseMask}
+            className="btn-text text-xs"
+          >
+            🎭 {t.images.browseMask}
+          </button>
+        </div>
+      )}
+    </div>
+  );
+}
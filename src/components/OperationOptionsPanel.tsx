@@ -8,6 +8,8 @@ const optionKeyToTranslationKey: Record<string, string> = {
   'length': 'length',
   'language': 'language',
   'size': 'imageSize',
+  'mode': 'imageMode',
+  'n': 'imageCount',
   'quality': 'quality',
   'style': 'style',
   'format': 'format',
@@ -63,6 +65,10 @@ const formatValueToKey: Record<string, keyof typeof import('../i18n/translations
 const qualityValueToKey: Record<string, keyof typeof import('../i18n/translations').translations['en']['operationOptions']['qualities']> = {
   'standard': 'standard',
   'hd': 'hd',
+  'auto': 'auto',
+  'high': 'high',
+  'medium': 'medium',
+  'low': 'low',
 };
 
 // Mapping for style values
@@ -71,6 +77,13 @@ const styleValueToKey: Record<string, keyof typeof import('../i18n/translations'
   'natural': 'natural',
 };
 
+// Mapping for image mode values
+const imageModeValueToKey: Record<string, keyof typeof import('../i18n/translations').translations['en']['operationOptions']['imageModes']> = {
+  'generate': 'generate',
+  'edit': 'edit',
+  'variation': 'variation',
+};
+
 // Mapping for language values (for STT and TTS language options)
 const languageCodeToKey: Record<string, keyof typeof import('../i18n/translations').translations['en']['operationOptions']['languages']> = {
   'auto': 'auto',
@@ -211,6 +224,14 @@ export function OperationOptionsPanel() {
       }
     }
     
+    // Image mode values
+    if (option.key === 'mode') {
+      const modeKey = imageModeValueToKey[value];
+      if (modeKey && t.operationOptions.imageModes[modeKey]) {
+        return t.operationOptions.imageModes[modeKey];
+      }
+    }
+
     // Language values (for STT/TTS language options and translation target)
     if (option.key === 'language') {
       const langKey = languageCodeToKey[value];
@@ -30,12 +30,14 @@ interface ReviewModalProps {
 }
 
 export function ReviewModal({ onShowToast }: ReviewModalProps) {
-  const { result, closeModal, clearResult, selectedOperation, config } = useApp();
+  const { result, closeModal, clearResult, selectedOperation, config, followUp, isProcessing } = useApp();
   const { t } = useI18n();
   const [isEditing, setIsEditing] = useState(false);
   const [editedContent, setEditedContent] = useState('');
   const [imageLoading, setImageLoading] = useState(true);
   const [imageError, setImageError] = useState(false);
+  const [followUpText, setFollowUpText] = useState('');
+  const [selectedImage, setSelectedImage] = useState(0);
   const textareaRef = useRef<HTMLTextAreaElement>(null);
 
   useEffect(() => {
@@ -44,8 +46,17 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
     }
   }, [result?.content]);
 
+  // Pick the first of a new set of generated images
+  useEffect(() => {
+    setSelectedImage(0);
+    setImageLoading(true);
+    setImageError(false);
+  }, [result?.images]);
+
   if (!result) return null;
 
+  const imagePath = result.images?.[selectedImage];
+
   const handleBack = () => {
     closeModal();
   };
@@ -93,91 +104,32 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
   };
 
   const handleSaveImage = async () => {
-    if (!result.imageUrl) {
-      console.error('[SaveImage] No image URL available in result');
-      onShowToast('error', 'Error', 'No image URL available');
-      return;
-    }
-
-    console.log('[SaveImage] Starting save process...');
-    console.log('[SaveImage] Image URL:', result.imageUrl);
+    if (!imagePath) return;
 
     try {
-      // Show save dialog
-      console.log('[SaveImage] Opening save dialog...');
+      const extension = imagePath.split('.').pop() || 'png';
       const savePath = await save({
         filters: [{
           name: 'Images',
-          extensions: ['png', 'jpg', 'jpeg', 'webp'],
+          extensions: [extension],
         }],
-        defaultPath: 'generated-image.png',
+        defaultPath: `generated-image.${extension}`,
       });
+      if (!savePath) return;
 
-      console.log('[SaveImage] Save path selected:', savePath);
-
-      if (!savePath) {
-        console.log('[SaveImage] User cancelled save dialog');
-        return;
-      }
-
-      // Try to download and save via backend command (bypasses CORS)
-      console.log('[SaveImage] Attempting to download image via backend...');
-      try {
-        await invoke('download_and_save_image', {
-          imageUrl: result.imageUrl,
-          savePath: savePath
-        });
-        console.log('[SaveImage] Backend download successful!');
-        onShowToast('success', 'Saved', 'Image saved successfully');
-        return;
-      } catch (backendError) {
-        console.warn('[SaveImage] Backend download failed, trying frontend fetch...', backendError);
-      }
-
-      // Fallback: Try frontend fetch (may fail due to CORS)
-      console.log('[SaveImage] Attempting frontend fetch...');
-      console.log('[SaveImage] Fetching from URL:', result.imageUrl);
-
-      const response = await fetch(result.imageUrl);
-      console.log('[SaveImage] Fetch response status:', response.status);
-      console.log('[SaveImage] Fetch response ok:', response.ok);
-      console.log('[SaveImage] Fetch response headers:', Object.fromEntries(response.headers.entries()));
-
-      if (!response.ok) {
-        throw new Error(`Fetch failed with status ${response.status}: ${response.statusText} `);
-      }
-
-      const arrayBuffer = await response.arrayBuffer();
-      console.log('[SaveImage] ArrayBuffer size:', arrayBuffer.byteLength);
-
-      if (arrayBuffer.byteLength === 0) {
-        throw new Error('Downloaded image is empty (0 bytes)');
-      }
-
-      const uint8Array = new Uint8Array(arrayBuffer);
-      console.log('[SaveImage] Uint8Array length:', uint8Array.length);
-
-      console.log('[SaveImage] Writing file to:', savePath);
-      await writeFile(savePath, uint8Array);
-      console.log('[SaveImage] File written successfully!');
-
+      await invoke('save_image_as', { imagePath, savePath });
       onShowToast('success', 'Saved', 'Image saved successfully');
     } catch (error) {
-      console.error('[SaveImage] Error details:', {
-        name: error instanceof Error ? error.name : 'Unknown',
-        message: error instanceof Error ? error.message : String(error),
-        stack: error instanceof Error ? error.stack : undefined,
-        error: error
-      });
+      console.error('[SaveImage] Error:', error);
       onShowToast('error', 'Error', `Failed to save image: ${error instanceof Error ? error.message : String(error)} `);
     }
   };
 
   const handleCopyImage = async () => {
-    if (!result.imageUrl) return;
+    if (!imagePath) return;
 
     try {
-      await invoke('copy_image_to_clipboard_command', { imageUrl: result.imageUrl });
+      await invoke('copy_image_to_clipboard_command', { imagePath });
       onShowToast('success', t.review.copied, t.review.copied);
     } catch (error) {
       onShowToast('error', t.toast.error, `Failed to copy image: ${error instanceof Error ? error.message : String(error)} `);
@@ -185,11 +137,11 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
   };
 
   const handlePasteImage = async () => {
-    if (!result.imageUrl) return;
+    if (!imagePath) return;
 
     try {
       // First copy the image to clipboard
-      await invoke('copy_image_to_clipboard_command', { imageUrl: result.imageUrl });
+      await invoke('copy_image_to_clipboard_command', { imagePath });
 
       // Close the modal first
       closeModal();
@@ -274,6 +226,20 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
     window.print();
   };
 
+  // Ask for changes to the result; the answer replaces it
+  const handleFollowUp = async () => {
+    const prompt = followUpText.trim();
+    if (!prompt || isProcessing) return;
+
+    const response = await followUp(prompt);
+    if (response?.success) {
+      setFollowUpText('');
+      setIsEditing(false);
+    } else if (response) {
+      onShowToast('error', t.toast.error, response.error);
+    }
+  };
+
   const characterCount = (isEditing ? editedContent : (result.content || '')).length;
 
   return (
@@ -291,6 +257,7 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
               </h2>
               <p className="text-sm text-slate-500 dark:text-slate-400">
                 {selectedOperation?.name}
+                {result.model && ` · ${result.model}`}
               </p>
             </div>
           </div>
@@ -304,8 +271,31 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
 
         {/* Content */}
         <div className="flex-1 overflow-y-auto p-4 print-modal-content">
-          {result.isImage && result.imageUrl ? (
+          {result.isImage && imagePath ? (
             <div className="space-y-4">
+              {/* Several images: pick the one to copy, paste or save */}
+              {result.images && result.images.length > 1 && (
+                <div className="flex gap-2 overflow-x-auto no-print">
+                  {result.images.map((path, index) => (
+                    <button
+                      key={path}
+                      onClick={() => {
+                        setSelectedImage(index);
+                        setImageLoading(true);
+                        setImageError(false);
+                      }}
+                      className={`flex-shrink-0 rounded-lg overflow-hidden border-2 transition-colors ${
+                        index === selectedImage
+                          ? 'border-blue-500'
+                          : 'border-transparent hover:border-slate-300 dark:hover:border-slate-600'
+                      }`}
+                      title={t.review.pickImage}
+                    >
+                      <img src={convertFileSrc(path)} alt="" className="h-20 w-20 object-cover" />
+                    </button>
+                  ))}
+                </div>
+              )}
               {imageLoading && (
                 <div className="flex items-center justify-center h-64">
                   <div className="animate-spin text-4xl">⏳</div>
@@ -317,7 +307,7 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
                 </div>
               ) : (
                 <img
-                  src={result.imageUrl}
+                  src={convertFileSrc(imagePath)}
                   alt="Generated image"
                   className={`w-full rounded-lg ${imageLoading ? 'hidden' : 'block'}`}
                   onLoad={() => setImageLoading(false)}
@@ -386,6 +376,36 @@ export function ReviewModal({ onShowToast }: ReviewModalProps) {
           )}
         </div>
 
+        {/* Follow-up */}
+        {result.threadId && !result.isImage && !result.isAudio && (
+          <div className="flex items-end gap-2 px-4 pb-4 no-print">
+            <textarea
+              value={followUpText}
+              onChange={(e) => setFollowUpText(e.target.value)}
+              onKeyDown={(e) => {
+                if (e.key === 'Enter' && (e.ctrlKey || e.metaKey)) {
+                  e.preventDefault();
+                  handleFollowUp();
+                }
+              }}
+              placeholder={t.review.followUpPlaceholder}
+              rows={2}
+              disabled={isProcessing}
+              className="flex-1 px-3 py-2 text-sm rounded-lg border border-slate-300 dark:border-slate-600 
+                         bg-white dark:bg-slate-800 text-slate-900 dark:text-white
+                         focus:ring-2 focus:ring-blue-500 focus:border-transparent
+                         resize-none transition-colors duration-200"
+            />
+            <button
+              onClick={handleFollowUp}
+              disabled={isProcessing || !followUpText.trim()}
+              className="btn-primary"
+            >
+              {isProcessing ? t.review.followingUp : `💬 ${t.review.followUp}`}
+            </button>
+          </div>
+        )}
+
         {/* Footer */}
         <div className="flex items-center justify-between p-4 border-t border-slate-200 dark:border-slate-700 no-print">
           <button
@@ -1,29 +1,27 @@
-import { useState, useEffect, useCallback, useMemo } from 'react';
+import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { confirm } from '@tauri-apps/plugin-dialog';
 import { save, open } from '@tauri-apps/plugin-dialog';
 import { readTextFile, writeTextFile } from '@tauri-apps/plugin-fs';
 import { useI18n } from '../../i18n/index';
 import { useApp } from '../../context/AppContext';
-import { CustomTask, CustomTaskOption } from '../../types';
+import {
+  AVAILABLE_TOOLS, CustomTask, CustomTaskInput, CustomTaskOption, GenerationParams, JsonOutput,
+  ReasoningEffort, ToolName,
+} from '../../types';
+import { useTokenCount } from '../../hooks/useTokenCount';
 import {
   FormField, FormInput, Card, PageLayout, EmptyState, Badge
 } from '../ui';
 
-// Token estimation constants (same as HomePage)
-const MAX_ESTIMATED_TOKENS = 16000;
+// System prompt limit (matches the backend validation)
+const MAX_SYSTEM_PROMPT_TOKENS = 16000;
 const WARNING_THRESHOLD = 12000;
-const TOKEN_CORRECTION_FACTOR = 1.20;
-
-/**
- * Estimates the number of tokens in a text string.
- * Uses word count * 1.33 * 1.20 as approximation.
- */
-const estimateTokens = (text: string): number => {
-  if (!text.trim()) return 0;
-  const words = text.trim().split(/\s+/).filter(w => w.length > 0).length;
-  return Math.ceil(words * 1.33 * TOKEN_CORRECTION_FACTOR);
-};
+
+const REASONING_EFFORTS: ReasoningEffort[] = ['minimal', 'low', 'medium', 'high'];
+
+// Number input value to an optional parameter (empty means unset)
+const optionalNumber = (value: string) => (value.trim() === '' ? undefined : Number(value));
 
 interface CustomTasksPageProps {
   showToast: (type: 'success' | 'error' | 'warning' | 'info', title: string, message?: string) => void;
@@ -43,6 +41,12 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
   const [formDescription, setFormDescription] = useState('');
   const [formSystemPrompt, setFormSystemPrompt] = useState('');
   const [formOptions, setFormOptions] = useState<CustomTaskOption[]>([]);
+  const [formParams, setFormParams] = useState<GenerationParams>({});
+  const [formStop, setFormStop] = useState('');
+  const [formExtraBody, setFormExtraBody] = useState('');
+  const [formOutputSchema, setFormOutputSchema] = useState('');
+  const [formOutputTemplate, setFormOutputTemplate] = useState('');
+  const [formTools, setFormTools] = useState<ToolName[]>([]);
 
   // Load tasks on mount
   useEffect(() => {
@@ -56,17 +60,29 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
       setFormDescription(editingTask.description);
       setFormSystemPrompt(editingTask.systemPrompt);
       setFormOptions(editingTask.options || []);
+      setFormParams(editingTask.params || {});
+      setFormStop((editingTask.params?.stop || []).join('\n'));
+      setFormExtraBody(editingTask.params?.extraBody ? JSON.stringify(editingTask.params.extraBody, null, 2) : '');
+      setFormOutputSchema(editingTask.jsonOutput ? JSON.stringify(editingTask.jsonOutput.schema, null, 2) : '');
+      setFormOutputTemplate(editingTask.jsonOutput?.template || '');
+      setFormTools(editingTask.tools || []);
     } else if (isCreating) {
       setFormName('');
       setFormDescription('');
       setFormSystemPrompt('');
       setFormOptions([]);
+      setFormParams({});
+      setFormStop('');
+      setFormExtraBody('');
+      setFormOutputSchema('');
+      setFormOutputTemplate('');
+      setFormTools([]);
     }
   }, [editingTask, isCreating]);
 
-  // Token count estimation for the systemPrompt
-  const tokenCount = useMemo(() => estimateTokens(formSystemPrompt), [formSystemPrompt]);
-  const isOverTokenLimit = tokenCount > MAX_ESTIMATED_TOKENS;
+  // Token count of the systemPrompt
+  const tokenCount = useTokenCount(formSystemPrompt)?.tokens ?? 0;
+  const isOverTokenLimit = tokenCount > MAX_SYSTEM_PROMPT_TOKENS;
   const isNearTokenLimit = tokenCount > WARNING_THRESHOLD;
 
   // Create new task
@@ -136,7 +152,7 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
     if (isOverTokenLimit) {
       const errorMsg = (t.customTasks.tokenError || 'System prompt too long (~{count} tokens). Maximum: {max}')
         .replace('{count}', formatNumber(tokenCount))
-        .replace('{max}', formatNumber(MAX_ESTIMATED_TOKENS));
+        .replace('{max}', formatNumber(MAX_SYSTEM_PROMPT_TOKENS));
       showToast('error', 'Validation Error', errorMsg);
       return;
     }
@@ -153,22 +169,64 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
       }
     }
 
+    // Extra body must be a JSON object
+    let extraBody: Record<string, unknown> | undefined;
+    if (formExtraBody.trim()) {
+      try {
+        extraBody = JSON.parse(formExtraBody);
+      } catch {
+        extraBody = undefined;
+      }
+      if (!extraBody || typeof extraBody !== 'object' || Array.isArray(extraBody)) {
+        showToast('error', 'Validation Error', t.customTasks.extraBodyInvalid);
+        return;
+      }
+    }
+
+    // Output schema must be a JSON object (the backend checks it is a valid schema)
+    let jsonOutput: JsonOutput | undefined;
+    if (formOutputSchema.trim()) {
+      let schema: unknown;
+      try {
+        schema = JSON.parse(formOutputSchema);
+      } catch {
+        schema = undefined;
+      }
+      if (!schema || typeof schema !== 'object' || Array.isArray(schema)) {
+        showToast('error', 'Validation Error', t.customTasks.outputSchemaInvalid);
+        return;
+      }
+      jsonOutput = {
+        schema: schema as Record<string, unknown>,
+        template: formOutputTemplate.trim() || undefined,
+      };
+    }
+
     try {
-      const taskData = {
+      const params: GenerationParams = {
+        ...formParams,
+        stop: formStop.split('\n').filter(s => s.trim()),
+        extraBody,
+      };
+
+      const taskData: CustomTaskInput = {
         name: formName.trim(),
         description: formDescription.trim(),
         systemPrompt: formSystemPrompt,
         options: formOptions.filter(opt => opt.key.trim() && opt.name.trim()),
+        params,
+        jsonOutput,
+        tools: formTools,
       };
 
       if (editingTask) {
         await invoke('update_custom_task', {
           id: editingTask.id,
-          ...taskData
+          task: taskData
         });
       } else {
         await invoke('create_custom_task', {
-          ...taskData
+          task: taskData
         });
       }
 
@@ -179,7 +237,7 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
       console.error('Failed to save task:', error);
       showToast('error', 'Error', String(error));
     }
-  }, [formName, formDescription, formSystemPrompt, formOptions, editingTask, loadCustomTasks, handleCancel, showToast]);
+  }, [formName, formDescription, formSystemPrompt, formOptions, formParams, formStop, formExtraBody, formOutputSchema, formOutputTemplate, formTools, editingTask, loadCustomTasks, handleCancel, showToast, t]);
 
   // Delete task
   const handleDelete = useCallback(async (id: string) => {
@@ -435,7 +493,7 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
                 }`}>
                 {(t.customTasks.tokenCount || '~{count} of {max} max. tokens')
                   .replace('{count}', formatNumber(tokenCount))
-                  .replace('{max}', formatNumber(MAX_ESTIMATED_TOKENS))}
+                  .replace('{max}', formatNumber(MAX_SYSTEM_PROMPT_TOKENS))}
                 {isNearTokenLimit && !isOverTokenLimit && (
                   <span className="ml-1">⚠️</span>
                 )}
@@ -469,6 +527,139 @@ export function CustomTasksPage({ showToast }: CustomTasksPageProps) {
               )}
             </div>
           </div>
+
+          {/* Generation Parameters */}
+          <FormField label={t.customTasks.generationParams} helpText={t.customTasks.generationParamsHelp}>
+            <div className="grid grid-cols-2 gap-3">
+              <div>
+                <label className="form-label-xs mb-1">{t.customTasks.temperature}</label>
+                <input
+                  type="number"
+                  min={0}
+                  max={2}
+                  step={0.1}
+                  value={formParams.temperature ?? ''}
+                  onChange={(e) => setFormParams(prev => ({ ...prev, temperature: optionalNumber(e.target.value) }))}
+                  className="form-input-sm"
+                />
+              </div>
+              <div>
+                <label className="form-label-xs mb-1">{t.customTasks.topP}</label>
+                <input
+                  type="number"
+                  min={0}
+                  max={1}
+                  step={0.05}
+                  value={formParams.topP ?? ''}
+                  onChange={(e) => setFormParams(prev => ({ ...prev, topP: optionalNumber(e.target.value) }))}
+                  className="form-input-sm"
+                />
+              </div>
+              <div>
+                <label className="form-label-xs mb-1">{t.customTasks.maxTokens}</label>
+                <input
+                  type="number"
+                  min={1}
+                  step={1}
+                  value={formParams.maxTokens ?? ''}
+                  onChange={(e) => setFormParams(prev => ({ ...prev, maxTokens: optionalNumber(e.target.value) }))}
+                  className="form-input-sm"
+                />
+              </div>
+              <div>
+                <label className="form-label-xs mb-1">{t.customTasks.seed}</label>
+                <input
+                  type="number"
+                  step={1}
+                  value={formParams.seed ?? ''}
+                  onChange={(e) => setFormParams(prev => ({ ...prev, seed: optionalNumber(e.target.value) }))}
+                  className="form-input-sm"
+                />
+              </div>
+              <div className="col-span-2">
+                <label className="form-label-xs mb-1">{t.customTasks.reasoningEffort}</label>
+                <select
+                  value={formParams.reasoningEffort ?? ''}
+                  onChange={(e) => setFormParams(prev => ({
+                    ...prev,
+                    reasoningEffort: (e.target.value || undefined) as ReasoningEffort | undefined,
+                  }))}
+                  className="form-input-sm"
+                >
+                  <option value="">{t.customTasks.providerDefault}</option>
+                  {REASONING_EFFORTS.map(effort => (
+                    <option key={effort} value={effort}>{effort}</option>
+                  ))}
+                </select>
+              </div>
+              <div className="col-span-2">
+                <label className="form-label-xs mb-1">{t.customTasks.stopSequences}</label>
+                <textarea
+                  value={formStop}
+                  onChange={(e) => setFormStop(e.target.value)}
+                  placeholder={t.customTasks.stopSequencesPlaceholder}
+                  rows={2}
+                  className="form-input-sm font-mono text-xs"
+                />
+              </div>
+              <div className="col-span-2">
+                <label className="form-label-xs mb-1">{t.customTasks.extraBody}</label>
+                <textarea
+                  value={formExtraBody}
+                  onChange={(e) => setFormExtraBody(e.target.value)}
+                  placeholder='{"top_k": 40}'
+                  rows={3}
+                  className="form-input-sm font-mono text-xs"
+                />
+              </div>
+            </div>
+          </FormField>
+
+          {/* Structured Output */}
+          <FormField label={t.customTasks.jsonOutput} helpText={t.customTasks.jsonOutputHelp}>
+            <div className="space-y-3">
+              <div>
+                <label className="form-label-xs mb-1">{t.customTasks.outputSchema}</label>
+                <textarea
+                  value={formOutputSchema}
+                  onChange={(e) => setFormOutputSchema(e.target.value)}
+                  placeholder='{"type": "object", "properties": {"subject": {"type": "string"}}, "required": ["subject"]}'
+                  rows={6}
+                  className="form-input-sm font-mono text-xs"
+                />
+              </div>
+              <div>
+                <label className="form-label-xs mb-1">{t.customTasks.outputTemplate}</label>
+                <textarea
+                  value={formOutputTemplate}
+                  onChange={(e) => setFormOutputTemplate(e.target.value)}
+                  placeholder={t.customTasks.outputTemplatePlaceholder}
+                  rows={2}
+                  disabled={!formOutputSchema.trim()}
+                  className="form-input-sm font-mono text-xs"
+                />
+              </div>
+            </div>
+          </FormField>
+
+          {/* Tools */}
+          <FormField label={t.customTasks.tools} helpText={t.customTasks.toolsHelp}>
+            <div className="flex flex-wrap gap-x-6 gap-y-2">
+              {AVAILABLE_TOOLS.map(tool => (
+                <label key={tool} className="flex items-center gap-2 text-sm text-slate-700 dark:text-slate-300 cursor-pointer">
+                  <input
+                    type="checkbox"
+                    checked={formTools.includes(tool)}
+                    onChange={(e) => setFormTools(prev => e.target.checked
+                      ? [...prev, tool]
+                      : prev.filter(name => name !== tool))}
+                    className="w-4 h-4 rounded border-slate-300 dark:border-slate-600 text-blue-600 focus:ring-blue-500"
+                  />
+                  {t.tools[tool]}
+                </label>
+              ))}
+            </div>
+          </FormField>
         </div>
       </PageLayout>
     );
@@ -1,7 +1,7 @@
 import React, { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { convertFileSrc } from '@tauri-apps/api/core';
-import { confirm, save } from '@tauri-apps/plugin-dialog';
+import { confirm, message, save } from '@tauri-apps/plugin-dialog';
 import { copyFile } from '@tauri-apps/plugin-fs';
 import { open } from '@tauri-apps/plugin-shell';
 import { downloadDir } from '@tauri-apps/api/path';
@@ -14,9 +14,25 @@ interface HistoryPageProps {
   onNavigateToHome: () => void;
 }
 
+// History entry as loaded back into the home page
+function toHistoryEntry(entry: HistoryEntryResponse): HistoryEntry {
+  return {
+    id: entry.id,
+    operationType: entry.operationType,
+    promptText: entry.promptText,
+    responseText: entry.responseText,
+    operationOptions: entry.operationOptions,
+    mediaPath: entry.mediaPath,
+    model: entry.model,
+    reasoning: entry.reasoning,
+    threadId: entry.threadId,
+    createdAt: entry.createdAt,
+  };
+}
+
 export function HistoryPage({ onNavigateToHome }: HistoryPageProps) {
   const { t } = useI18n();
-  const { loadHistoryEntry, customTasks, operations } = useApp();
+  const { loadHistoryEntry, continueThread, addImageInput, customTasks, operations } = useApp();
   const [history, setHistory] = useState<HistoryEntryResponse[]>([]);
   const [searchQuery, setSearchQuery] = useState('');
   const [loading, setLoading] = useState(true);
@@ -82,19 +98,29 @@ export function HistoryPage({ onNavigateToHome }: HistoryPageProps) {
 
   // Re-run a history entry
   const handleRerun = useCallback((entry: HistoryEntryResponse) => {
-    const historyEntry: HistoryEntry = {
-      id: entry.id,
-      operationType: entry.operationType,
-      promptText: entry.promptText,
-      responseText: entry.responseText,
-      operationOptions: entry.operationOptions,
-      mediaPath: entry.mediaPath,
-      createdAt: entry.createdAt,
-    };
-    loadHistoryEntry(historyEntry);
+    loadHistoryEntry(toHistoryEntry(entry));
     onNavigateToHome();
   }, [loadHistoryEntry, onNavigateToHome]);
 
+  // Continue the conversation of a history entry with a follow-up
+  const handleContinue = useCallback(async (entry: HistoryEntryResponse) => {
+    if (!entry.threadId) return;
+    loadHistoryEntry(toHistoryEntry(entry));
+    try {
+      await continueThread(entry.threadId);
+      onNavigateToHome();
+    } catch (error) {
+      console.error('Failed to continue conversation:', error);
+      await message(String(error), { title: t.history.continue, kind: 'error' });
+    }
+  }, [loadHistoryEntry, continueThread, onNavigateToHome, t]);
+
+  // Attach an entry's image to the next request
+  const handleUseAsInput = useCallback((mediaPath: string) => {
+    addImageInput({ source: 'history', path: mediaPath });
+    onNavigateToHome();
+  }, [addImageInput, onNavigateToHome]);
+
   // Delete a single entry
   const handleDelete = useCallback(async (id: string) => {
     const confirmed = await confirm(t.history.confirmDelete, {
@@ -265,6 +291,11 @@ export function HistoryPage({ onNavigateToHome }: HistoryPageProps) {
                         <span className="text-xs text-slate-500 dark:text-slate-400">
                           {formatDate(entry.createdAt)}
                         </span>
+                        {entry.model && (
+                          <span className="text-xs text-slate-400 dark:text-slate-500 truncate">
+                            {entry.model}
+                          </span>
+                        )}
                       </div>
 
                       {/* Prompt Preview */}
@@ -308,6 +339,18 @@ export function HistoryPage({ onNavigateToHome }: HistoryPageProps) {
                       </p>
                     </div>
 
+                    {/* Reasoning of thinking models (saved only when enabled) */}
+                    {entry.reasoning && (
+                      <div className="p-4 border-b border-slate-200 dark:border-slate-700">
+                        <h4 className="text-xs font-semibold text-slate-500 dark:text-slate-400 uppercase mb-2">
+                          {t.history.reasoning}
+                        </h4>
+                        <p className="text-slate-500 dark:text-slate-400 whitespace-pre-wrap text-sm italic">
+                          {entry.reasoning}
+                        </p>
+                      </div>
+                    )}
+
                     {/* Full Response with Markdown */}
                     <div className="p-4 border-b border-slate-200 dark:border-slate-700">
                       <h4 className="text-xs font-semibold text-slate-500 dark:text-slate-400 uppercase mb-2">
@@ -381,6 +424,28 @@ export function HistoryPage({ onNavigateToHome }: HistoryPageProps) {
                       >
                         {t.history.delete}
                       </button>
+                      {entry.mediaPath && entry.mediaPath.match(/\.(png|jpg|jpeg|webp|gif)$/i) && (
+                        <button
+                          onClick={(e) => {
+                            e.stopPropagation();
+                            handleUseAsInput(entry.mediaPath!);
+                          }}
+                          className="btn-outline"
+                        >
+                          {t.history.useAsInput}
+                        </button>
+                      )}
+                      {entry.threadId && (
+                        <button
+                          onClick={(e) => {
+                            e.stopPropagation();
+                            handleContinue(entry);
+                          }}
+                          className="btn-outline"
+                        >
+                          {t.history.continue}
+                        </button>
+                      )}
                       <button
                         onClick={(e) => {
                           e.stopPropagation();
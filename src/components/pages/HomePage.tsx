@@ -4,30 +4,15 @@ import { useI18n } from '../../i18n/index';
 import { useClipboardSync } from '../../hooks/useClipboardSync';
 import { OperationOptionsPanel } from '../OperationOptionsPanel';
 import { AudioUpload } from '../AudioUpload';
+import { ImageAttachments } from '../ImageAttachments';
+import { FileAttachments } from '../FileAttachments';
 import { ToastType } from '../../types';
 import { SearchableSelect, Option } from '../ui/SearchableSelect';
 import { parseApiError } from '../../utils/apiErrors';
+import { useTokenCount } from '../../hooks/useTokenCount';
 
-// Token estimation constants
-// Most LLMs use ~1.3 tokens per word for English (inverse of ~0.75 words per token)
-// We apply a 20% correction factor (1.20) to avoid underestimating
-// Conservative limit that works with most models (GPT-4, Claude, etc.)
-const MAX_ESTIMATED_TOKENS = 24000;
-const WARNING_THRESHOLD = 16000;
-const TOKEN_CORRECTION_FACTOR = 1.20; // 20% safety margin for underestimation
-
-/**
- * Estimates the number of tokens in a text string.
- * Uses word count * 1.33 * 1.20 as approximation:
- * - 1.33: based on ~0.75 words per token for English
- * - 1.20: correction factor to avoid underestimation
- * This is a rough estimate - actual tokenization varies by model.
- */
-const estimateTokens = (text: string): number => {
-  if (!text.trim()) return 0;
-  const words = text.trim().split(/\s+/).filter(w => w.length > 0).length;
-  return Math.ceil(words * 1.33 * TOKEN_CORRECTION_FACTOR);
-};
+// Warn once the prompt uses this share of the model's prompt budget
+const TOKEN_WARNING_RATIO = 0.8;
 
 interface HomePageProps {
   onShowToast: (type: ToastType, title: string, message?: string) => void;
@@ -40,13 +25,22 @@ export function HomePage({ onShowToast }: HomePageProps) {
     customTasks,
     selectedOperation,
     setSelectedOperation,
+    operationOptions,
     promptText,
     setPromptText,
     promptLoadedFromHistory,
     clearPromptLoadedFromHistory,
+    imageInputs,
+    attachments,
     isProcessing,
     streamingContent,
+    streamingReasoning,
     isStreaming,
+    retryNotice,
+    chunkProgress,
+    toolCall,
+    thread,
+    endThread,
     clearStreamingContent,
     processRequestStreaming,
     cancelRequest,
@@ -83,10 +77,18 @@ export function HomePage({ onShowToast }: HomePageProps) {
     currentText: promptText,
   });
 
-  // Token count estimation for the prompt text
-  const tokenCount = useMemo(() => estimateTokens(promptText), [promptText]);
-  const isOverTokenLimit = tokenCount > MAX_ESTIMATED_TOKENS;
-  const isNearTokenLimit = tokenCount > WARNING_THRESHOLD;
+  // Token count of the prompt text and attached files, checked against the model's prompt budget
+  const tokenInfo = useTokenCount(promptText, selectedOperation?.type, config?.llmModel);
+  const attachmentTokens = attachments.reduce((sum, info) => sum + info.tokens, 0);
+  const tokenCount = (tokenInfo?.tokens ?? 0) + attachmentTokens;
+  const maxTokens = tokenInfo?.maxInputTokens ?? 0;
+  const exceedsTokenLimit = maxTokens > 0 && tokenCount > maxTokens;
+  // Translation, rewrite and summarization split long texts into chunks instead,
+  // unless images or files are attached (they must stay with the whole text)
+  const hasAttachments = imageInputs.length > 0 || attachments.length > 0;
+  const willChunk = exceedsTokenLimit && !!tokenInfo?.supportsChunking && !hasAttachments;
+  const isOverTokenLimit = exceedsTokenLimit && !willChunk;
+  const isNearTokenLimit = maxTokens > 0 && tokenCount > maxTokens * TOKEN_WARNING_RATIO;
 
   // Focus textarea on mount
   useEffect(() => {
@@ -197,7 +199,7 @@ export function HomePage({ onShowToast }: HomePageProps) {
       return;
     }
 
-    if (!promptText.trim() && selectedOperation.type !== 'speechToText') {
+    if (!promptText.trim() && selectedOperation.type !== 'speechToText' && imageMode !== 'variation') {
       onShowToast('error', t.toast.error, t.home.pleaseEnterPrompt);
       return;
     }
@@ -206,7 +208,7 @@ export function HomePage({ onShowToast }: HomePageProps) {
     if (isOverTokenLimit && selectedOperation.type !== 'speechToText') {
       const errorMsg = t.home.tokenError
         .replace('{count}', formatNumber(tokenCount))
-        .replace('{max}', formatNumber(MAX_ESTIMATED_TOKENS));
+        .replace('{max}', formatNumber(maxTokens));
       onShowToast('error', t.toast.error, errorMsg);
       return;
     }
@@ -216,6 +218,9 @@ export function HomePage({ onShowToast }: HomePageProps) {
       if (!response.success) {
         const { title, message } = parseApiError(response.error, t);
         onShowToast('error', title, message);
+      } else if (response.incomplete) {
+        // Still cut off at the token limit after the continuation rounds
+        onShowToast('warning', t.toast.warning, t.home.incompleteResponse);
       } else if (config?.pasteBehavior === 'clipboardMode') {
         // Notify user that content was copied to clipboard
         onShowToast('success', t.toast.success, t.review.copied);
@@ -234,6 +239,14 @@ export function HomePage({ onShowToast }: HomePageProps) {
   };
 
   const isSpeechToText = selectedOperation?.type === 'speechToText';
+  // Text operations and custom tasks can send images to multimodal models and files as context
+  const acceptsImages = !!selectedOperation
+    && !['speechToText', 'textToSpeech', 'imageGeneration'].includes(selectedOperation.type);
+  // Image edits and variations start from an input image
+  const imageMode = selectedOperation?.type === 'imageGeneration'
+    ? (operationOptions.mode || 'generate')
+    : null;
+  const needsInputImage = imageMode === 'edit' || imageMode === 'variation';
 
   return (
     <div className="flex flex-col h-full">
@@ -270,9 +283,48 @@ export function HomePage({ onShowToast }: HomePageProps) {
             )}
           </div>
 
+          {/* Conversation being followed up */}
+          {thread && (
+            <div className="flex items-center justify-between gap-2 px-3 py-2 rounded-lg text-sm flex-shrink-0
+                            bg-blue-50 dark:bg-blue-900/20 text-blue-700 dark:text-blue-300">
+              <span>
+                💬 {t.home.continuingThread.replace('{count}', String(thread.messages.filter(m => m.role === 'assistant').length))}
+              </span>
+              <button
+                onClick={endThread}
+                className="btn-text"
+                title={t.home.newThreadTooltip}
+              >
+                {t.home.newThread}
+              </button>
+            </div>
+          )}
+
           {/* Operation Options */}
           <OperationOptionsPanel />
 
+          {/* Image Attachments (vision input) */}
+          {acceptsImages && (
+            <ImageAttachments onError={(msg) => onShowToast('error', t.images.title, msg)} />
+          )}
+
+          {/* Input image of an image edit or variation */}
+          {needsInputImage && (
+            <ImageAttachments
+              single
+              withMask={imageMode === 'edit'}
+              onError={(msg) => onShowToast('error', t.images.title, msg)}
+            />
+          )}
+          {imageMode === 'variation' && (
+            <p className="help-text italic">{t.images.variationNoPrompt}</p>
+          )}
+
+          {/* File Attachments (text context) */}
+          {acceptsImages && (
+            <FileAttachments onError={(msg) => onShowToast('error', t.attachments.title, msg)} />
+          )}
+
           {/* Audio Upload (for STT) */}
           {isSpeechToText && (
             <AudioUpload onError={(msg) => onShowToast('error', t.audio.title, msg)} />
@@ -358,10 +410,13 @@ export function HomePage({ onShowToast }: HomePageProps) {
                     }`}>
                     {t.home.tokenCount
                       .replace('{count}', formatNumber(tokenCount))
-                      .replace('{max}', formatNumber(MAX_ESTIMATED_TOKENS))}
+                      .replace('{max}', formatNumber(maxTokens))}
                     {isNearTokenLimit && !isOverTokenLimit && (
                       <span className="ml-1">⚠️</span>
                     )}
+                    {willChunk && (
+                      <span className="ml-1">{t.home.tokenChunked}</span>
+                    )}
                     {isOverTokenLimit && (
                       <span className="ml-1">❌</span>
                     )}
@@ -380,6 +435,15 @@ export function HomePage({ onShowToast }: HomePageProps) {
                       <span className="inline-block w-2 h-2 bg-green-500 rounded-full animate-pulse" />
                     )}
                   </div>
+                  {/* Reasoning of thinking models, shown apart from the answer */}
+                  {streamingReasoning && (
+                    <details open={!streamingContent} className="text-xs text-slate-400 dark:text-slate-500">
+                      <summary className="cursor-pointer select-none">{t.home.reasoning}</summary>
+                      <div className="mt-1 max-h-24 overflow-y-auto whitespace-pre-wrap italic">
+                        {streamingReasoning}
+                      </div>
+                    </details>
+                  )}
                   <div
                     ref={streamingRef}
                     className="h-16 w-full px-4 py-2 text-sm rounded-lg border border-slate-200 dark:border-slate-700 
@@ -407,6 +471,30 @@ export function HomePage({ onShowToast }: HomePageProps) {
       {/* Footer */}
       <div className="px-6 py-4 border-t border-slate-200 dark:border-slate-800 bg-white dark:bg-slate-900 flex-shrink-0">
         <div className="max-w-3xl flex items-center justify-end gap-3">
+          {isProcessing && chunkProgress && !retryNotice && (
+            <span className="mr-auto text-sm text-slate-500 dark:text-slate-400">
+              {(chunkProgress.stage === 'reduce'
+                ? t.home.reduceProgress
+                : chunkProgress.stage === 'transcribe'
+                  ? t.home.transcribeProgress
+                  : t.home.chunkProgress)
+                .replace('{current}', String(chunkProgress.current))
+                .replace('{total}', String(chunkProgress.total))}
+            </span>
+          )}
+          {isProcessing && toolCall && !chunkProgress && !retryNotice && (
+            <span className="mr-auto text-sm text-slate-500 dark:text-slate-400">
+              {t.home.usingTool.replace('{tool}', t.tools[toolCall.name] ?? toolCall.name)}
+            </span>
+          )}
+          {isProcessing && retryNotice && (
+            <span className="mr-auto text-sm text-amber-600 dark:text-amber-400">
+              {t.home.retrying
+                .replace('{seconds}', String(Math.ceil(retryNotice.delayMs / 1000)))
+                .replace('{attempt}', String(retryNotice.attempt))
+                .replace('{max}', String(retryNotice.maxRetries))}
+            </span>
+          )}
           {isProcessing && (
             <button
               onClick={handleCancel}
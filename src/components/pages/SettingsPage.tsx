@@ -1,4 +1,5 @@
 import { useState, useEffect } from 'react';
+import { open } from '@tauri-apps/plugin-dialog';
 import { useApp } from '../../context/AppContext';
 import { useI18n } from '../../i18n/index';
 import { useHotkeyCapture } from '../../hooks/useHotkeyCapture';
@@ -34,6 +35,9 @@ export function SettingsPage({ onShowToast }: SettingsPageProps) {
   const [copyDelayMs, setCopyDelayMs] = useState(200);
   const [historyLimit, setHistoryLimit] = useState(100);
   const [mediaRetentionDays, setMediaRetentionDays] = useState(30);
+  const [saveReasoningToHistory, setSaveReasoningToHistory] = useState(false);
+  const [maxToolRounds, setMaxToolRounds] = useState(5);
+  const [toolFolders, setToolFolders] = useState<string[]>([]);
 
   // Model lists
   const [models, setModels] = useState<string[]>([]);
@@ -95,6 +99,9 @@ export function SettingsPage({ onShowToast }: SettingsPageProps) {
       setTtsModels(config.ttsModels || []);
       setHistoryLimit(config.historyLimit ?? 100);
       setMediaRetentionDays(config.mediaRetentionDays ?? 30);
+      setSaveReasoningToHistory(config.saveReasoningToHistory ?? false);
+      setMaxToolRounds(config.maxToolRounds ?? 5);
+      setToolFolders(config.toolFolders || []);
     }
   }, [config]);
 
@@ -106,6 +113,13 @@ export function SettingsPage({ onShowToast }: SettingsPageProps) {
 
   const handleHotkeyKeyDown = hotkeyKeyDownHandler;
 
+  const handleAddToolFolder = async () => {
+    const folder = await open({ directory: true });
+    if (typeof folder === 'string' && !toolFolders.includes(folder)) {
+      setToolFolders([...toolFolders, folder]);
+    }
+  };
+
   const handleFetchModels = async (showToast = true): Promise<{
     allModels: string[];
     selectedLlm: string;
@@ -284,6 +298,9 @@ export function SettingsPage({ onShowToast }: SettingsPageProps) {
         ttsModels: currentModels,
         historyLimit,
         mediaRetentionDays,
+        saveReasoningToHistory,
+        maxToolRounds,
+        toolFolders,
       };
       await saveConfig(request);
       await loadConfig();
@@ -553,6 +570,61 @@ export function SettingsPage({ onShowToast }: SettingsPageProps) {
                   onChange={(e) => setMediaRetentionDays(Math.max(0, Math.min(365, parseInt(e.target.value) || 30)))}
                 />
               </FormField>
+
+              <SettingsToggle
+                title={t.settings.history.saveReasoning}
+                description={t.settings.history.saveReasoningDesc}
+                checked={saveReasoningToHistory}
+                onChange={setSaveReasoningToHistory}
+              />
+            </div>
+
+            {/* Tools Section */}
+            <div className="pt-6 border-t border-slate-200 dark:border-slate-700 mt-6">
+              <h3 className="section-title mb-4">
+                {t.settings.tools.title}
+              </h3>
+
+              <FormField
+                label={t.settings.tools.maxToolRounds}
+                helpText={t.settings.tools.maxToolRoundsDesc}
+              >
+                <FormInput
+                  type="number"
+                  min="1"
+                  max="20"
+                  step="1"
+                  value={maxToolRounds}
+                  onChange={(e) => setMaxToolRounds(Math.max(1, Math.min(20, parseInt(e.target.value) || 5)))}
+                />
+              </FormField>
+
+              <FormField
+                label={t.settings.tools.folders}
+                helpText={t.settings.tools.foldersDesc}
+              >
+                <div className="space-y-2">
+                  {toolFolders.length === 0 && (
+                    <p className="text-sm text-slate-500 dark:text-slate-400">{t.settings.tools.noFolders}</p>
+                  )}
+                  {toolFolders.map(folder => (
+                    <div key={folder} className="flex items-center gap-2">
+                      <span className="flex-1 truncate font-mono text-xs text-slate-700 dark:text-slate-300" title={folder}>
+                        {folder}
+                      </span>
+                      <button
+                        onClick={() => setToolFolders(toolFolders.filter(f => f !== folder))}
+                        className="btn-text-neutral text-xs"
+                      >
+                        {t.settings.tools.removeFolder}
+                      </button>
+                    </div>
+                  ))}
+                  <button onClick={handleAddToolFolder} className="btn-outline text-sm">
+                    + {t.settings.tools.addFolder}
+                  </button>
+                </div>
+              </FormField>
             </div>
           </div>
         )}
@@ -1,4 +1,4 @@
-import { createContext, useContext, useState, useCallback, useEffect, ReactNode } from 'react';
+import { createContext, useContext, useState, useCallback, useEffect, useRef, ReactNode } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import {
@@ -9,8 +9,17 @@ import {
   ModalType,
   SaveConfigRequest,
   StreamingChunk,
+  ReasoningChunk,
+  StreamCancelled,
+  RetryEvent,
+  ToolCallEvent,
+  ChunkProgress,
   CustomTask,
   HistoryEntry,
+  ResponseDetails,
+  ImageInput,
+  AttachmentInfo,
+  Thread,
 } from '../types';
 
 interface AppContextType {
@@ -41,11 +50,23 @@ interface AppContextType {
   setSelectedText: (text: string) => void;
   audioFilePath: string;
   setAudioFilePath: (path: string) => void;
+  imageInputs: ImageInput[];
+  addImageInput: (input: ImageInput) => void;
+  removeImageInput: (index: number) => void;
+  imageMask: ImageInput | null;
+  setImageMask: (mask: ImageInput | null) => void;
+  attachments: AttachmentInfo[];
+  addAttachments: (paths: string[]) => Promise<void>;
+  removeAttachment: (path: string) => void;
 
   // Processing & Streaming
   isProcessing: boolean;
   streamingContent: string;
+  streamingReasoning: string;
   isStreaming: boolean;
+  retryNotice: RetryEvent | null;
+  chunkProgress: ChunkProgress | null;
+  toolCall: ToolCallEvent | null;
   clearStreamingContent: () => void;
   processRequest: () => Promise<LlmResponse | null>;
   processRequestStreaming: () => Promise<LlmResponse | null>;
@@ -56,6 +77,12 @@ interface AppContextType {
   setResult: (result: LlmResponse | null) => void;
   clearResult: () => void;
 
+  // Conversation
+  thread: Thread | null;
+  continueThread: (threadId: string) => Promise<void>;
+  endThread: () => void;
+  followUp: (prompt: string) => Promise<LlmResponse | null>;
+
   // History
   loadHistoryEntry: (entry: HistoryEntry) => void;
 
@@ -91,27 +118,45 @@ export function AppProvider({ children }: { children: ReactNode }) {
   const [promptLoadedFromHistory, setPromptLoadedFromHistory] = useState(false);
   const [selectedText, setSelectedText] = useState('');
   const [audioFilePath, setAudioFilePath] = useState('');
+  const [imageInputs, setImageInputs] = useState<ImageInput[]>([]);
+  const [imageMask, setImageMask] = useState<ImageInput | null>(null);
+  const [attachments, setAttachments] = useState<AttachmentInfo[]>([]);
 
   // Processing & streaming state
   const [isProcessing, setIsProcessing] = useState(false);
   const [streamingContent, setStreamingContent] = useState('');
+  const [streamingReasoning, setStreamingReasoning] = useState('');
   const [isStreaming, setIsStreaming] = useState(false);
+  const [retryNotice, setRetryNotice] = useState<RetryEvent | null>(null);
+  const [chunkProgress, setChunkProgress] = useState<ChunkProgress | null>(null);
+  const [toolCall, setToolCall] = useState<ToolCallEvent | null>(null);
+  // Id of the request this window is waiting for; events of other requests are ignored
+  const activeRequestId = useRef<string | null>(null);
 
   // Result state
   const [result, setResult] = useState<LlmResponse | null>(null);
 
+  // Conversation the next request follows up, if any
+  const [thread, setThread] = useState<Thread | null>(null);
+
   // UI state
   const [activeModal, setActiveModal] = useState<ModalType>('none');
 
   // Set up streaming event listeners
   useEffect(() => {
     let unlistenChunk: UnlistenFn | null = null;
+    let unlistenReasoning: UnlistenFn | null = null;
     let unlistenCancelled: UnlistenFn | null = null;
+    let unlistenRetry: UnlistenFn | null = null;
+    let unlistenChunkProgress: UnlistenFn | null = null;
+    let unlistenToolCall: UnlistenFn | null = null;
     let isMounted = true; // Track if effect is still active
 
     const setupListeners = async () => {
       const chunkListener = await listen<StreamingChunk>('llm-stream-chunk', (event) => {
         if (!isMounted) return; // Don't update state if unmounted
+        if (event.payload.requestId !== activeRequestId.current) return;
+        setRetryNotice(null);
         if (event.payload.done) {
           setIsStreaming(false);
         } else {
@@ -119,20 +164,58 @@ export function AppProvider({ children }: { children: ReactNode }) {
         }
       });
 
-      const cancelledListener = await listen('llm-stream-cancelled', () => {
+      const reasoningListener = await listen<ReasoningChunk>('llm-stream-reasoning', (event) => {
+        if (!isMounted) return; // Don't update state if unmounted
+        if (event.payload.requestId !== activeRequestId.current) return;
+        setRetryNotice(null);
+        setStreamingReasoning(prev => prev + event.payload.content);
+      });
+
+      const cancelledListener = await listen<StreamCancelled>('llm-stream-cancelled', (event) => {
         if (!isMounted) return; // Don't update state if unmounted
+        if (event.payload.requestId !== activeRequestId.current) return;
         setIsStreaming(false);
         setStreamingContent('');
+        setStreamingReasoning('');
+        setRetryNotice(null);
+        setChunkProgress(null);
+        setToolCall(null);
+      });
+
+      const retryListener = await listen<RetryEvent>('llm-retry', (event) => {
+        if (!isMounted) return; // Don't update state if unmounted
+        if (event.payload.requestId !== activeRequestId.current) return;
+        setRetryNotice(event.payload);
+      });
+
+      const chunkProgressListener = await listen<ChunkProgress>('llm-chunk-progress', (event) => {
+        if (!isMounted) return; // Don't update state if unmounted
+        if (event.payload.requestId !== activeRequestId.current) return;
+        setChunkProgress(event.payload);
+      });
+
+      const toolCallListener = await listen<ToolCallEvent>('llm-tool-call', (event) => {
+        if (!isMounted) return; // Don't update state if unmounted
+        if (event.payload.requestId !== activeRequestId.current) return;
+        setToolCall(event.payload);
       });
 
       // Only assign if still mounted
       if (isMounted) {
         unlistenChunk = chunkListener;
+        unlistenReasoning = reasoningListener;
         unlistenCancelled = cancelledListener;
+        unlistenRetry = retryListener;
+        unlistenChunkProgress = chunkProgressListener;
+        unlistenToolCall = toolCallListener;
       } else {
         // Component unmounted before listeners were set up, clean them up immediately
         chunkListener();
+        reasoningListener();
         cancelledListener();
+        retryListener();
+        chunkProgressListener();
+        toolCallListener();
       }
     };
 
@@ -141,7 +224,11 @@ export function AppProvider({ children }: { children: ReactNode }) {
     return () => {
       isMounted = false;
       unlistenChunk?.();
+      unlistenReasoning?.();
       unlistenCancelled?.();
+      unlistenRetry?.();
+      unlistenChunkProgress?.();
+      unlistenToolCall?.();
     };
   }, []);
 
@@ -229,6 +316,8 @@ export function AppProvider({ children }: { children: ReactNode }) {
   // Set selected operation
   const setSelectedOperation = useCallback((op: Operation | null) => {
     setSelectedOperationState(op);
+    // A conversation belongs to its operation
+    setThread(prev => (prev && prev.operationType !== op?.type ? null : prev));
     if (op) {
       initializeOptions(op);
       // Persist selection
@@ -256,7 +345,8 @@ export function AppProvider({ children }: { children: ReactNode }) {
     promptText: string,
     responseText: string | undefined,
     options: Record<string, string>,
-    mediaPath?: string
+    mediaPath?: string,
+    details?: ResponseDetails
   ) => {
     try {
       await invoke('save_history_entry', {
@@ -265,16 +355,28 @@ export function AppProvider({ children }: { children: ReactNode }) {
         responseText: responseText || null,
         operationOptions: options,
         mediaPath: mediaPath || null,
+        details: details || null,
       });
     } catch (error) {
       console.error('Failed to save to history:', error);
     }
   }, []);
 
+  // Load a stored conversation so the next requests follow it up
+  const loadThread = useCallback(async (threadId: string) => {
+    const stored = await invoke<Thread | null>('get_thread', { id: threadId });
+    if (!stored) {
+      throw new Error('This conversation is no longer available');
+    }
+    setThread(stored);
+  }, []);
+
   // Process LLM request
   const processRequest = useCallback(async (): Promise<LlmResponse | null> => {
     if (!selectedOperation) return null;
 
+    const requestId = crypto.randomUUID();
+    activeRequestId.current = requestId;
     setIsProcessing(true);
     try {
       const request: LlmRequest = {
@@ -283,27 +385,22 @@ export function AppProvider({ children }: { children: ReactNode }) {
         selectedText: selectedText || undefined,
         options: operationOptions,
         audioFilePath: audioFilePath || undefined,
+        images: imageInputs.length > 0 ? imageInputs : undefined,
+        mask: imageMask ?? undefined,
+        attachments: attachments.length > 0 ? attachments.map(info => info.path) : undefined,
+        threadId: thread?.id,
+        requestId,
       };
 
       const response = await invoke<LlmResponse>('process_llm_request', { request });
       setResult(response);
 
       if (response.success) {
-        let mediaPath: string | undefined;
-
-        // For image responses, save to media folder
-        if (response.isImage && response.imageUrl) {
-          try {
-            mediaPath = await invoke<string>('save_generated_image', { imageUrl: response.imageUrl });
-          } catch (e) {
-            console.error('Failed to save generated image:', e);
-          }
-        }
-
+        // Generated images are already in the media folder; each one gets a history entry.
         // For audio responses (TTS), the audio file path IS the media path
-        if (response.isAudio && response.audioFilePath) {
-          mediaPath = response.audioFilePath;
-        }
+        const mediaPaths: (string | undefined)[] = response.isImage
+          ? response.images ?? []
+          : [response.isAudio ? response.audioFilePath : undefined];
 
         // For STT, use the audio file path as the prompt (since it's the input)
         const historyPrompt = selectedOperation.type === 'speechToText' && audioFilePath
@@ -311,13 +408,26 @@ export function AppProvider({ children }: { children: ReactNode }) {
           : promptText;
 
         // Save to history with media path if available
-        await saveToHistory(
-          selectedOperation.type,
-          historyPrompt,
-          response.content,
-          operationOptions,
-          mediaPath
-        );
+        for (const mediaPath of mediaPaths) {
+          await saveToHistory(
+            selectedOperation.type,
+            historyPrompt,
+            response.content,
+            operationOptions,
+            mediaPath,
+            {
+              model: response.model,
+              usage: response.usage,
+              reasoning: response.reasoning,
+              threadId: response.threadId,
+            }
+          );
+        }
+
+        // Keep the exchange count of a continued conversation current
+        if (thread && response.threadId) {
+          await loadThread(response.threadId).catch(e => console.error('Failed to reload the conversation:', e));
+        }
 
         // For TTS responses, always show review modal for Save As dialog
         // regardless of paste behavior setting
@@ -340,16 +450,25 @@ export function AppProvider({ children }: { children: ReactNode }) {
       setResult(errorResponse);
       return errorResponse;
     } finally {
+      if (activeRequestId.current === requestId) {
+        activeRequestId.current = null;
+      }
       setIsProcessing(false);
+      setRetryNotice(null);
+      setChunkProgress(null);
+      setToolCall(null);
     }
-  }, [selectedOperation, promptText, selectedText, operationOptions, audioFilePath, saveToHistory, config?.pasteBehavior]);
+  }, [selectedOperation, promptText, selectedText, operationOptions, audioFilePath, imageInputs, imageMask, attachments, thread, loadThread, saveToHistory, config?.pasteBehavior]);
 
   // Process LLM request with streaming
   const processRequestStreaming = useCallback(async (): Promise<LlmResponse | null> => {
     if (!selectedOperation) return null;
 
+    const requestId = crypto.randomUUID();
+    activeRequestId.current = requestId;
     setIsProcessing(true);
     setStreamingContent('');
+    setStreamingReasoning('');
     setIsStreaming(true);
 
     try {
@@ -359,6 +478,11 @@ export function AppProvider({ children }: { children: ReactNode }) {
         selectedText: selectedText || undefined,
         options: operationOptions,
         audioFilePath: audioFilePath || undefined,
+        images: imageInputs.length > 0 ? imageInputs : undefined,
+        mask: imageMask ?? undefined,
+        attachments: attachments.length > 0 ? attachments.map(info => info.path) : undefined,
+        threadId: thread?.id,
+        requestId,
       };
 
       const response = await invoke<LlmResponse>('process_llm_request_streaming', { request });
@@ -366,21 +490,11 @@ export function AppProvider({ children }: { children: ReactNode }) {
       setIsStreaming(false);
 
       if (response.success) {
-        let mediaPath: string | undefined;
-
-        // For image responses, save to media folder
-        if (response.isImage && response.imageUrl) {
-          try {
-            mediaPath = await invoke<string>('save_generated_image', { imageUrl: response.imageUrl });
-          } catch (e) {
-            console.error('Failed to save generated image:', e);
-          }
-        }
-
+        // Generated images are already in the media folder; each one gets a history entry.
         // For audio responses (TTS), the audio file path IS the media path
-        if (response.isAudio && response.audioFilePath) {
-          mediaPath = response.audioFilePath;
-        }
+        const mediaPaths: (string | undefined)[] = response.isImage
+          ? response.images ?? []
+          : [response.isAudio ? response.audioFilePath : undefined];
 
         // For STT, use the audio file path as the prompt (since it's the input)
         const historyPrompt = selectedOperation.type === 'speechToText' && audioFilePath
@@ -388,13 +502,26 @@ export function AppProvider({ children }: { children: ReactNode }) {
           : promptText;
 
         // Save to history with media path if available
-        await saveToHistory(
-          selectedOperation.type,
-          historyPrompt,
-          response.content,
-          operationOptions,
-          mediaPath
-        );
+        for (const mediaPath of mediaPaths) {
+          await saveToHistory(
+            selectedOperation.type,
+            historyPrompt,
+            response.content,
+            operationOptions,
+            mediaPath,
+            {
+              model: response.model,
+              usage: response.usage,
+              reasoning: response.reasoning,
+              threadId: response.threadId,
+            }
+          );
+        }
+
+        // Keep the exchange count of a continued conversation current
+        if (thread && response.threadId) {
+          await loadThread(response.threadId).catch(e => console.error('Failed to reload the conversation:', e));
+        }
 
         // For TTS responses, always show review modal for Save As dialog
         // regardless of paste behavior setting
@@ -418,17 +545,95 @@ export function AppProvider({ children }: { children: ReactNode }) {
       setIsStreaming(false);
       return errorResponse;
     } finally {
+      if (activeRequestId.current === requestId) {
+        activeRequestId.current = null;
+      }
+      setIsProcessing(false);
+      setRetryNotice(null);
+      setChunkProgress(null);
+      setToolCall(null);
+    }
+  }, [selectedOperation, promptText, selectedText, operationOptions, audioFilePath, imageInputs, imageMask, attachments, thread, loadThread, config?.pasteBehavior, saveToHistory]);
+
+  // Follow up the current result with a further instruction, in the same conversation.
+  // On failure the current result is kept.
+  const followUp = useCallback(async (prompt: string): Promise<LlmResponse | null> => {
+    if (!selectedOperation || !result?.threadId) return null;
+
+    const requestId = crypto.randomUUID();
+    activeRequestId.current = requestId;
+    setIsProcessing(true);
+    try {
+      const request: LlmRequest = {
+        operationType: selectedOperation.type,
+        prompt,
+        options: operationOptions,
+        threadId: result.threadId,
+        requestId,
+      };
+
+      const response = await invoke<LlmResponse>('process_llm_request', { request });
+      if (response.success) {
+        setResult(response);
+        await saveToHistory(
+          selectedOperation.type,
+          prompt,
+          response.content,
+          operationOptions,
+          undefined,
+          {
+            model: response.model,
+            usage: response.usage,
+            reasoning: response.reasoning,
+            threadId: response.threadId,
+          }
+        );
+        if (response.threadId) {
+          await loadThread(response.threadId).catch(e => console.error('Failed to reload the conversation:', e));
+        }
+      }
+      return response;
+    } catch (error) {
+      return {
+        success: false,
+        error: String(error),
+        isImage: false,
+        isAudio: false,
+      };
+    } finally {
+      if (activeRequestId.current === requestId) {
+        activeRequestId.current = null;
+      }
       setIsProcessing(false);
+      setRetryNotice(null);
+      setChunkProgress(null);
+      setToolCall(null);
     }
-  }, [selectedOperation, promptText, selectedText, operationOptions, audioFilePath, config?.pasteBehavior, saveToHistory]);
+  }, [selectedOperation, result?.threadId, operationOptions, saveToHistory, loadThread]);
+
+  // Continue a conversation from the history: the prompt becomes its next instruction
+  const continueThread = useCallback(async (threadId: string) => {
+    await loadThread(threadId);
+    setPromptText('');
+    setSelectedText('');
+  }, [loadThread]);
+
+  // Start a new conversation with the next request
+  const endThread = useCallback(() => {
+    setThread(null);
+  }, []);
 
   // Cancel the current request
   const cancelRequest = useCallback(async () => {
     try {
-      await invoke('cancel_llm_request');
+      await invoke('cancel_llm_request', { requestId: activeRequestId.current });
       setIsStreaming(false);
       setIsProcessing(false);
       setStreamingContent('');
+      setStreamingReasoning('');
+      setRetryNotice(null);
+      setChunkProgress(null);
+      setToolCall(null);
     } catch (error) {
       console.error('Failed to cancel request:', error);
     }
@@ -476,6 +681,28 @@ export function AppProvider({ children }: { children: ReactNode }) {
     setPromptLoadedFromHistory(true); // Flag to prevent clipboard sync from overwriting
   }, [operations, customTasks]);
 
+  // Image inputs for multimodal models
+  const addImageInput = useCallback((input: ImageInput) => {
+    setImageInputs(prev => [...prev, input]);
+  }, []);
+
+  const removeImageInput = useCallback((index: number) => {
+    setImageInputs(prev => prev.filter((_, i) => i !== index));
+  }, []);
+
+  // File attachments, checked by the backend before they are added (throws on unreadable files)
+  const addAttachments = useCallback(async (paths: string[]) => {
+    const added = await invoke<AttachmentInfo[]>('inspect_attachments', { paths });
+    setAttachments(prev => [
+      ...prev,
+      ...added.filter(info => !prev.some(existing => existing.path === info.path)),
+    ]);
+  }, []);
+
+  const removeAttachment = useCallback((path: string) => {
+    setAttachments(prev => prev.filter(info => info.path !== path));
+  }, []);
+
   // Clear promptLoadedFromHistory flag (call after clipboard sync is skipped)
   const clearPromptLoadedFromHistory = useCallback(() => {
     setPromptLoadedFromHistory(false);
@@ -489,6 +716,7 @@ export function AppProvider({ children }: { children: ReactNode }) {
   // Clear streaming content
   const clearStreamingContent = useCallback(() => {
     setStreamingContent('');
+    setStreamingReasoning('');
   }, []);
 
   // Modal controls
@@ -541,9 +769,21 @@ export function AppProvider({ children }: { children: ReactNode }) {
     setSelectedText,
     audioFilePath,
     setAudioFilePath,
+    imageInputs,
+    addImageInput,
+    removeImageInput,
+    imageMask,
+    setImageMask,
+    attachments,
+    addAttachments,
+    removeAttachment,
     isProcessing,
     streamingContent,
+    streamingReasoning,
     isStreaming,
+    retryNotice,
+    chunkProgress,
+    toolCall,
     clearStreamingContent,
     processRequest,
     processRequestStreaming,
@@ -551,6 +791,10 @@ export function AppProvider({ children }: { children: ReactNode }) {
     result,
     setResult,
     clearResult,
+    thread,
+    continueThread,
+    endThread,
+    followUp,
     loadHistoryEntry,
     activeModal,
     openModal,
@@ -0,0 +1,36 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { TokenCount } from '../types';
+
+/** Delay before re-counting after the text changes */
+const DEBOUNCE_MS = 250;
+
+/**
+ * Hook that counts the tokens of a text with the backend tokenizer.
+ *
+ * The count uses the vocabulary of the model that would process the text and
+ * comes with that model's prompt budget. It is refreshed (debounced) whenever
+ * the text, the operation or the model changes.
+ */
+export function useTokenCount(text: string, operationType?: string, model?: string) {
+  const [tokenCount, setTokenCount] = useState<TokenCount | null>(null);
+
+  useEffect(() => {
+    let cancelled = false;
+    const timer = setTimeout(async () => {
+      try {
+        const count = await invoke<TokenCount>('count_tokens', { text, operationType });
+        if (!cancelled) setTokenCount(count);
+      } catch (error) {
+        console.error('Failed to count tokens:', error);
+      }
+    }, DEBOUNCE_MS);
+
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+    };
+  }, [text, operationType, model]);
+
+  return tokenCount;
+}
@@ -34,6 +34,17 @@ export interface Translations {
     defaultTasks: string;
     generating: string;
     waitingForResponse: string;
+    retrying: string;
+    chunkProgress: string;
+    reduceProgress: string;
+    transcribeProgress: string;
+    usingTool: string;
+    continuingThread: string;
+    newThread: string;
+    newThreadTooltip: string;
+    incompleteResponse: string;
+    reasoning: string;
+    tokenChunked: string;
     promptHint: string;
     tokenCount: string;
     tokenWarning: string;
@@ -78,6 +89,8 @@ export interface Translations {
     language: string;
     languageOptional: string;
     imageSize: string;
+    imageMode: string;
+    imageCount: string;
     quality: string;
     style: string;
     writingTone: string;
@@ -125,12 +138,22 @@ export interface Translations {
     qualities: {
       standard: string;
       hd: string;
+      auto: string;
+      high: string;
+      medium: string;
+      low: string;
     };
     // Style values
     styles: {
       vivid: string;
       natural: string;
     };
+    // Image mode values
+    imageModes: {
+      generate: string;
+      edit: string;
+      variation: string;
+    };
     // Language names
     languages: {
       auto: string;
@@ -239,9 +262,30 @@ export interface Translations {
       historyLimitDesc: string;
       mediaRetention: string;
       mediaRetentionDesc: string;
+      saveReasoning: string;
+      saveReasoningDesc: string;
+    };
+    tools: {
+      title: string;
+      maxToolRounds: string;
+      maxToolRoundsDesc: string;
+      folders: string;
+      foldersDesc: string;
+      addFolder: string;
+      removeFolder: string;
+      noFolders: string;
     };
   };
 
+  // Local tools, keyed by the name the model calls them by
+  tools: {
+    date_time: string;
+    calculator: string;
+    convert_units: string;
+    read_file: string;
+    search_history: string;
+  };
+
   // About
   about: {
     title: string;
@@ -270,6 +314,10 @@ export interface Translations {
     saved: string;
     imageGenerated: string;
     saveImage: string;
+    pickImage: string;
+    followUp: string;
+    followingUp: string;
+    followUpPlaceholder: string;
   };
 
   // Audio Upload
@@ -281,6 +329,29 @@ export interface Translations {
     maxSize: string;
   };
 
+  // Vision input
+  images: {
+    title: string;
+    paste: string;
+    browse: string;
+    clipboardImage: string;
+    remove: string;
+    inputImage: string;
+    mask: string;
+    maskHelp: string;
+    browseMask: string;
+    removeMask: string;
+    variationNoPrompt: string;
+  };
+
+  // File attachments
+  attachments: {
+    title: string;
+    browse: string;
+    remove: string;
+    tokens: string;
+  };
+
   // Toasts
   toast: {
     success: string;
@@ -340,6 +411,8 @@ export interface Translations {
     noHistory: string;
     noResults: string;
     rerun: string;
+    continue: string;
+    useAsInput: string;
     delete: string;
     clearAll: string;
     confirmDelete: string;
@@ -350,6 +423,7 @@ export interface Translations {
     date: string;
     prompt: string;
     response: string;
+    reasoning: string;
   };
 
   // Custom Tasks
@@ -383,6 +457,26 @@ export interface Translations {
     optionMin: string;
     optionMax: string;
     optionStep: string;
+    generationParams: string;
+    generationParamsHelp: string;
+    temperature: string;
+    topP: string;
+    maxTokens: string;
+    seed: string;
+    reasoningEffort: string;
+    providerDefault: string;
+    stopSequences: string;
+    stopSequencesPlaceholder: string;
+    extraBody: string;
+    extraBodyInvalid: string;
+    jsonOutput: string;
+    jsonOutputHelp: string;
+    outputSchema: string;
+    outputSchemaInvalid: string;
+    outputTemplate: string;
+    outputTemplatePlaceholder: string;
+    tools: string;
+    toolsHelp: string;
     save: string;
     cancel: string;
     validationError: string;
@@ -426,6 +520,17 @@ export const translations: Record<Language, Translations> = {
       defaultTasks: 'Default Tasks',
       generating: 'Generating...',
       waitingForResponse: 'Waiting for response...',
+      retrying: 'Retrying in {seconds}s (attempt {attempt}/{max})...',
+      chunkProgress: 'Processing chunk {current}/{total}...',
+      reduceProgress: 'Merging summaries ({current}/{total})...',
+      transcribeProgress: 'Transcribing long audio ({current}/{total} parts done)...',
+      usingTool: 'Using {tool}...',
+      continuingThread: 'Continuing a conversation ({count} exchanges). Your prompt follows it up.',
+      newThread: 'New conversation',
+      newThreadTooltip: 'Start over without the previous exchanges',
+      incompleteResponse: 'The response reached the length limit and may be incomplete',
+      reasoning: 'Reasoning',
+      tokenChunked: 'Long text: it will be processed in chunks',
       promptHint: '💡 Tip: Use Ctrl+Enter to send the prompt.',
       tokenCount: '~{count} of {max} max. tokens',
       tokenWarning: 'Approaching token limit',
@@ -468,6 +573,8 @@ export const translations: Record<Language, Translations> = {
       language: 'Language',
       languageOptional: 'Language (optional)',
       imageSize: 'Image Size',
+      imageMode: 'Mode',
+      imageCount: 'Number of Images',
       quality: 'Quality',
       style: 'Style',
       writingTone: 'Writing Tone',
@@ -515,12 +622,22 @@ export const translations: Record<Language, Translations> = {
       qualities: {
         standard: 'Standard',
         hd: 'HD',
+        auto: 'Auto',
+        high: 'High',
+        medium: 'Medium',
+        low: 'Low',
       },
       // Style values
       styles: {
         vivid: 'Vivid',
         natural: 'Natural',
       },
+      // Image mode values
+      imageModes: {
+        generate: 'Generate',
+        edit: 'Edit image',
+        variation: 'Variation of image',
+      },
       // Language names
       languages: {
         auto: 'Auto-detect',
@@ -622,9 +739,29 @@ export const translations: Record<Language, Translations> = {
         historyLimitDesc: 'Maximum number of history entries to keep. Older entries will be automatically deleted.',
         mediaRetention: 'Media Retention (Days)',
         mediaRetentionDesc: 'Number of days to keep generated images and audio files. Set to 0 to keep forever.',
+        saveReasoning: 'Save reasoning to history',
+        saveReasoningDesc: 'Keep the reasoning of thinking models with each entry. It is never pasted.',
+      },
+      tools: {
+        title: 'Tools',
+        maxToolRounds: 'Max Tool Rounds',
+        maxToolRoundsDesc: 'How many rounds of tool calls a model may make before it must answer.',
+        folders: 'Readable Folders',
+        foldersDesc: 'The file reading tool can only open files inside these folders.',
+        addFolder: 'Add Folder',
+        removeFolder: 'Remove',
+        noFolders: 'No folders allowed',
       },
     },
 
+    tools: {
+      date_time: 'Date & time',
+      calculator: 'Calculator',
+      convert_units: 'Unit conversion',
+      read_file: 'Read file',
+      search_history: 'Search history',
+    },
+
     about: {
       title: 'About',
       version: 'Version',
@@ -659,6 +796,10 @@ export const translations: Record<Language, Translations> = {
       saved: 'Saved successfully',
       imageGenerated: 'Image Generated',
       saveImage: 'Save',
+      pickImage: 'Use this image',
+      followUp: 'Follow up',
+      followingUp: 'Sending...',
+      followUpPlaceholder: 'Ask for changes, e.g. "make it shorter" (Ctrl+Enter to send)',
     },
 
     audio: {
@@ -669,6 +810,27 @@ export const translations: Record<Language, Translations> = {
       maxSize: 'Max size',
     },
 
+    images: {
+      title: 'Images',
+      paste: 'Paste from clipboard',
+      browse: 'Attach image',
+      clipboardImage: 'Clipboard image',
+      remove: 'Remove image',
+      inputImage: 'Input image',
+      mask: 'Mask (optional)',
+      maskHelp: 'Transparent areas of the mask are the ones changed; without a mask, those of the image',
+      browseMask: 'Choose mask',
+      removeMask: 'Remove mask',
+      variationNoPrompt: 'Variations are made from the image alone; the prompt is not used',
+    },
+
+    attachments: {
+      title: 'Files',
+      browse: 'Attach file',
+      remove: 'Remove file',
+      tokens: 'tokens',
+    },
+
     toast: {
       success: 'Success',
       error: 'Error',
@@ -725,6 +887,8 @@ export const translations: Record<Language, Translations> = {
       noHistory: 'No history entries yet',
       noResults: 'No matching entries found',
       rerun: 'Re-run',
+      continue: 'Continue',
+      useAsInput: 'Use as image input',
       delete: 'Delete',
       clearAll: 'Clear All',
       confirmDelete: 'Are you sure you want to delete this entry?',
@@ -735,6 +899,7 @@ export const translations: Record<Language, Translations> = {
       date: 'Date',
       prompt: 'Prompt',
       response: 'Response',
+      reasoning: 'Reasoning',
     },
 
     customTasks: {
@@ -767,6 +932,26 @@ export const translations: Record<Language, Translations> = {
       optionMin: 'Min',
       optionMax: 'Max',
       optionStep: 'Step',
+      generationParams: 'Generation Parameters',
+      generationParamsHelp: 'Leave a field empty to use the provider default',
+      temperature: 'Temperature',
+      topP: 'Top P',
+      maxTokens: 'Max Tokens',
+      seed: 'Seed',
+      reasoningEffort: 'Reasoning Effort',
+      providerDefault: 'Provider default',
+      stopSequences: 'Stop Sequences',
+      stopSequencesPlaceholder: 'One sequence per line',
+      extraBody: 'Extra Request Body (JSON)',
+      extraBodyInvalid: 'Extra request body must be a JSON object',
+      jsonOutput: 'Structured Output (JSON)',
+      jsonOutputHelp: 'The answer must match this JSON Schema; invalid answers are sent back for correction. Leave empty for plain text.',
+      outputSchema: 'JSON Schema',
+      outputSchemaInvalid: 'The output schema must be a JSON object',
+      outputTemplate: 'Paste Template (optional)',
+      outputTemplatePlaceholder: '{subject} - due {dueDate}. Without a template the JSON is pasted.',
+      tools: 'Tools',
+      toolsHelp: 'Local functions the model may call while answering. The answer is shown once complete instead of streamed.',
       save: 'Save Task',
       cancel: 'Cancel',
       validationError: 'Please fix the validation errors',
@@ -809,6 +994,17 @@ export const translations: Record<Language, Translations> = {
       defaultTasks: 'Tarefas Padrão',
       generating: 'Gerando...',
       waitingForResponse: 'Aguardando resposta...',
+      retrying: 'Tentando novamente em {seconds}s (tentativa {attempt}/{max})...',
+      chunkProgress: 'Processando parte {current}/{total}...',
+      reduceProgress: 'Combinando resumos ({current}/{total})...',
+      transcribeProgress: 'Transcrevendo áudio longo ({current}/{total} partes concluídas)...',
+      usingTool: 'Usando {tool}...',
+      continuingThread: 'Continuando uma conversa ({count} trocas). Seu prompt dá sequência a ela.',
+      newThread: 'Nova conversa',
+      newThreadTooltip: 'Recomeçar sem as trocas anteriores',
+      incompleteResponse: 'A resposta atingiu o limite de tamanho e pode estar incompleta',
+      reasoning: 'Raciocínio',
+      tokenChunked: 'Texto longo: será processado em partes',
       promptHint: '💡 Dica: Use Ctrl+Enter para enviar o prompt.',
       tokenCount: '~{count} de {max} max. tokens',
       tokenWarning: 'Aproximando-se do limite de tokens',
@@ -851,6 +1047,8 @@ export const translations: Record<Language, Translations> = {
       language: 'Idioma',
       languageOptional: 'Idioma (opcional)',
       imageSize: 'Tamanho da Imagem',
+      imageMode: 'Modo',
+      imageCount: 'Número de Imagens',
       quality: 'Qualidade',
       style: 'Estilo',
       writingTone: 'Tom da Escrita',
@@ -898,12 +1096,22 @@ export const translations: Record<Language, Translations> = {
       qualities: {
         standard: 'Padrão',
         hd: 'HD',
+        auto: 'Automática',
+        high: 'Alta',
+        medium: 'Média',
+        low: 'Baixa',
       },
       // Style values
       styles: {
         vivid: 'Vívido',
         natural: 'Natural',
       },
+      // Image mode values
+      imageModes: {
+        generate: 'Gerar',
+        edit: 'Editar imagem',
+        variation: 'Variação da imagem',
+      },
       // Language names
       languages: {
         auto: 'Detectar automaticamente',
@@ -1005,9 +1213,29 @@ export const translations: Record<Language, Translations> = {
         historyLimitDesc: 'Número máximo de entradas no histórico. Entradas antigas serão excluídas automaticamente.',
         mediaRetention: 'Retenção de Mídia (Dias)',
         mediaRetentionDesc: 'Número de dias para manter imagens e arquivos de áudio gerados. Defina como 0 para manter para sempre.',
+        saveReasoning: 'Salvar raciocínio no histórico',
+        saveReasoningDesc: 'Mantém o raciocínio de modelos que pensam antes de responder em cada entrada. Ele nunca é colado.',
+      },
+      tools: {
+        title: 'Ferramentas',
+        maxToolRounds: 'Máximo de Rodadas de Ferramentas',
+        maxToolRoundsDesc: 'Quantas rodadas de chamadas de ferramentas um modelo pode fazer antes de responder.',
+        folders: 'Pastas Permitidas',
+        foldersDesc: 'A ferramenta de leitura de arquivos só abre arquivos dentro destas pastas.',
+        addFolder: 'Adicionar Pasta',
+        removeFolder: 'Remover',
+        noFolders: 'Nenhuma pasta permitida',
       },
     },
 
+    tools: {
+      date_time: 'Data e hora',
+      calculator: 'Calculadora',
+      convert_units: 'Conversão de unidades',
+      read_file: 'Ler arquivo',
+      search_history: 'Buscar no histórico',
+    },
+
     about: {
       title: 'Sobre',
       version: 'Versão',
@@ -1042,6 +1270,10 @@ export const translations: Record<Language, Translations> = {
       saved: 'Salvo com sucesso',
       imageGenerated: 'Imagem Gerada',
       saveImage: 'Salvar',
+      pickImage: 'Usar esta imagem',
+      followUp: 'Continuar',
+      followingUp: 'Enviando...',
+      followUpPlaceholder: 'Peça alterações, ex.: "deixe mais curto" (Ctrl+Enter para enviar)',
     },
 
     audio: {
@@ -1052,6 +1284,27 @@ export const translations: Record<Language, Translations> = {
       maxSize: 'Tamanho máximo',
     },
 
+    images: {
+      title: 'Imagens',
+      paste: 'Colar da área de transferência',
+      browse: 'Anexar imagem',
+      clipboardImage: 'Imagem da área de transferência',
+      remove: 'Remover imagem',
+      inputImage: 'Imagem de entrada',
+      mask: 'Máscara (opcional)',
+      maskHelp: 'As áreas transparentes da máscara são as alteradas; sem máscara, as da imagem',
+      browseMask: 'Escolher máscara',
+      removeMask: 'Remover máscara',
+      variationNoPrompt: 'Variações são feitas só a partir da imagem; o prompt não é usado',
+    },
+
+    attachments: {
+      title: 'Arquivos',
+      browse: 'Anexar arquivo',
+      remove: 'Remover arquivo',
+      tokens: 'tokens',
+    },
+
     toast: {
       success: 'Sucesso',
       error: 'Erro',
@@ -1108,6 +1361,8 @@ export const translations: Record<Language, Translations> = {
       noHistory: 'Nenhuma entrada no histórico ainda',
       noResults: 'Nenhuma entrada encontrada',
       rerun: 'Reutilizar',
+      continue: 'Continuar',
+      useAsInput: 'Usar como imagem de entrada',
       delete: 'Excluir',
       clearAll: 'Limpar Tudo',
       confirmDelete: 'Tem certeza que deseja excluir esta entrada?',
@@ -1118,6 +1373,7 @@ export const translations: Record<Language, Translations> = {
       date: 'Data',
       prompt: 'Prompt',
       response: 'Resposta',
+      reasoning: 'Raciocínio',
     },
 
     customTasks: {
@@ -1150,6 +1406,26 @@ export const translations: Record<Language, Translations> = {
       optionMin: 'Mín',
       optionMax: 'Máx',
       optionStep: 'Passo',
+      generationParams: 'Parâmetros de Geração',
+      generationParamsHelp: 'Deixe um campo vazio para usar o padrão do provedor',
+      temperature: 'Temperatura',
+      topP: 'Top P',
+      maxTokens: 'Máximo de Tokens',
+      seed: 'Semente',
+      reasoningEffort: 'Esforço de Raciocínio',
+      providerDefault: 'Padrão do provedor',
+      stopSequences: 'Sequências de Parada',
+      stopSequencesPlaceholder: 'Uma sequência por linha',
+      extraBody: 'Corpo Extra da Requisição (JSON)',
+      extraBodyInvalid: 'O corpo extra da requisição deve ser um objeto JSON',
+      jsonOutput: 'Saída Estruturada (JSON)',
+      jsonOutputHelp: 'A resposta deve seguir este JSON Schema; respostas inválidas são devolvidas para correção. Deixe vazio para texto simples.',
+      outputSchema: 'JSON Schema',
+      outputSchemaInvalid: 'O schema de saída deve ser um objeto JSON',
+      outputTemplate: 'Modelo para Colar (opcional)',
+      outputTemplatePlaceholder: '{assunto} - prazo {prazo}. Sem modelo, o JSON é colado.',
+      tools: 'Ferramentas',
+      toolsHelp: 'Funções locais que o modelo pode chamar ao responder. A resposta aparece completa em vez de transmitida aos poucos.',
       save: 'Salvar Tarefa',
       cancel: 'Cancelar',
       validationError: 'Por favor, corrija os erros de validação',
@@ -6,11 +6,68 @@
 
 export type PasteBehavior = 'autoPaste' | 'clipboardMode' | 'reviewMode';
 
+export type ProviderKind = 'openai' | 'anthropic' | 'ollama' | 'gemini';
+
+export type BudgetUnit = 'tokens' | 'currency';
+
+export interface Budget {
+  unit: BudgetUnit;
+  dailyLimit?: number | null; // unset = no limit
+  monthlyLimit?: number | null;
+  warningPercent: number; // Notify when this share of a limit is used (1-100)
+}
+
+export interface EndpointProfile {
+  id: string;
+  name: string;
+  provider: ProviderKind;
+  apiBaseUrl: string;
+  apiKeySet: boolean;
+  headers: Record<string, string>;
+  budget: Budget;
+}
+
+export interface ModelTarget {
+  profileId: string; // empty = profile of the primary model
+  model: string;
+}
+
+export interface EndpointProfileInput {
+  id?: string; // omit for new profiles
+  name: string;
+  provider?: ProviderKind;
+  apiBaseUrl: string;
+  apiKey?: string; // omit or empty to keep the stored key
+  headers?: Record<string, string>;
+  budget?: Budget; // omit to keep the stored budget
+}
+
 export interface Configuration {
   hotkey: string;
+  // Default profile (single-endpoint view)
+  provider: ProviderKind;
   apiBaseUrl: string;
   apiKeySet: boolean;
+  // Endpoint profiles and routing (empty id = default profile)
+  profiles: EndpointProfile[];
+  defaultProfileId: string;
+  llmProfileId: string;
+  imageProfileId: string;
+  audioProfileId: string;
+  ttsProfileId: string;
+  operationProfiles: Record<string, string>;
+  ollamaKeepAlive: string;
+  ollamaNumCtx: number;
+  maxRetries: number;
+  retryBaseDelayMs: number;
+  maxContinuations: number; // Continuation turns for responses cut off at the token limit
+  maxToolRounds: number; // Rounds of tool calls before the model must answer
+  toolFolders: string[]; // Folders the read_file tool may read from
   llmModel: string;
+  fallbackModels: ModelTarget[];
+  contextWindows: Record<string, number>; // Context window overrides keyed by model name
+  modelPrices: Record<string, ModelPrice>; // Prices keyed by model name
+  currency: string;
   imageModel: string;
   audioModel: string;
   ttsModel: string;
@@ -25,13 +82,39 @@ export interface Configuration {
   // History settings
   historyLimit: number;
   mediaRetentionDays: number;
+  saveReasoningToHistory: boolean; // keep the reasoning of thinking models in history
+}
+
+export interface ModelPrice {
+  inputPerMillion: number; // Cost per million prompt tokens
+  outputPerMillion: number; // Cost per million completion tokens
 }
 
 export interface SaveConfigRequest {
   hotkey: string;
-  apiBaseUrl: string;
+  // Applied to the default profile when `profiles` is omitted
+  provider?: ProviderKind;
+  apiBaseUrl?: string;
   apiKey?: string;
+  profiles?: EndpointProfileInput[];
+  defaultProfileId?: string;
+  llmProfileId?: string;
+  imageProfileId?: string;
+  audioProfileId?: string;
+  ttsProfileId?: string;
+  operationProfiles?: Record<string, string>;
+  ollamaKeepAlive?: string;
+  ollamaNumCtx?: number;
+  maxRetries?: number;
+  retryBaseDelayMs?: number;
+  maxContinuations?: number;
+  maxToolRounds?: number;
+  toolFolders?: string[];
   llmModel: string;
+  fallbackModels?: ModelTarget[];
+  contextWindows?: Record<string, number>;
+  modelPrices?: Record<string, ModelPrice>;
+  currency?: string;
   imageModel: string;
   audioModel: string;
   ttsModel: string;
@@ -46,6 +129,7 @@ export interface SaveConfigRequest {
   // History settings
   historyLimit: number;
   mediaRetentionDays: number;
+  saveReasoningToHistory?: boolean;
 }
 
 // ============================================================================
@@ -81,6 +165,22 @@ export interface Operation {
   description: string;
   systemPrompt: string;
   options: OperationOption[];
+  params: GenerationParams;
+}
+
+export type ReasoningEffort = 'minimal' | 'low' | 'medium' | 'high';
+
+// Generation parameters of an operation or custom task; unset fields are not sent.
+// A single request can override them through LlmRequest.options
+// (temperature, topP, maxTokens, stop, seed, reasoningEffort, extraBody).
+export interface GenerationParams {
+  temperature?: number;
+  topP?: number;
+  maxTokens?: number;
+  stop?: string[];
+  seed?: number;
+  reasoningEffort?: ReasoningEffort;
+  extraBody?: Record<string, unknown>;
 }
 
 // ============================================================================
@@ -93,6 +193,30 @@ export interface LlmRequest {
   selectedText?: string;
   options: Record<string, string>;
   audioFilePath?: string;
+  images?: ImageInput[]; // images for multimodal models; the first is the input of an image edit or variation
+  mask?: ImageInput; // mask of an image edit (transparent areas are changed)
+  attachments?: string[]; // paths of files sent as text context
+  threadId?: string; // follow up this conversation instead of starting a new one
+  requestId?: string; // generated by the backend when omitted
+}
+
+// Where an image attached to a request comes from
+export type ImageInput =
+  | { source: 'clipboard' }
+  | { source: 'file'; path: string }
+  | { source: 'history'; path: string }; // a history entry's media file
+
+// A file attached as text context, with the tokens it adds to the prompt
+export interface AttachmentInfo {
+  path: string;
+  name: string;
+  tokens: number;
+}
+
+export interface Usage {
+  promptTokens: number;
+  completionTokens: number;
+  totalTokens: number;
 }
 
 export interface LlmResponse {
@@ -100,11 +224,18 @@ export interface LlmResponse {
   content?: string;
   error?: string;
   isImage: boolean;
-  imageUrl?: string;
+  images?: string[]; // media files of the generated images
   isAudio: boolean;
   audioData?: number[];
   audioFormat?: string;
   audioFilePath?: string;
+  model?: string; // model that produced the result (may be a fallback)
+  usage?: Usage;
+  reasoning?: string; // reasoning of a thinking model (never pasted)
+  incomplete?: boolean; // still cut off at the token limit after the continuation rounds
+  json?: unknown; // validated answer of a task with a JSON output schema
+  threadId?: string; // conversation the exchange was added to (text requests)
+  requestId?: string;
 }
 
 // ============================================================================
@@ -165,11 +296,20 @@ export const SUPPORTED_AUDIO_FORMATS = [
   'mp3', 'mp4', 'mpeg', 'mpga', 'm4a', 'wav', 'webm', 'ogg', 'flac', 'aac', 'wma'
 ];
 
-export const MAX_AUDIO_FILE_SIZE = 25 * 1024 * 1024; // 25MB
+export const MAX_AUDIO_FILE_SIZE = 25 * 1024 * 1024; // 25MB upload limit; larger files are split
+
+// Image formats accepted as vision input (others are converted by the backend)
+export const SUPPORTED_IMAGE_FORMATS = ['png', 'jpg', 'jpeg', 'gif', 'webp', 'bmp'];
+
+// Common attachment formats for the file dialog (source files are also accepted)
+export const SUPPORTED_ATTACHMENT_FORMATS = [
+  'txt', 'md', 'csv', 'tsv', 'json', 'jsonl', 'xml', 'yaml', 'yml', 'html', 'log', 'pdf', 'docx',
+  'rs', 'py', 'js', 'ts', 'tsx', 'java', 'c', 'cpp', 'cs', 'go', 'rb', 'php', 'sh', 'sql',
+];
 
 export function isValidAudioFile(file: File): boolean {
   const extension = file.name.split('.').pop()?.toLowerCase() || '';
-  return SUPPORTED_AUDIO_FORMATS.includes(extension) && file.size <= MAX_AUDIO_FILE_SIZE;
+  return SUPPORTED_AUDIO_FORMATS.includes(extension);
 }
 
 export function formatFileSize(bytes: number): string {
@@ -189,7 +329,37 @@ export interface HistoryEntryResponse {
   responseText?: string;
   operationOptions: Record<string, string>;
   mediaPath?: string;
+  model?: string;
+  usage?: Usage;
+  reasoning?: string;
+  threadId?: string;
+  createdAt: string;
+}
+
+// Details of the response a history entry is saved from
+export interface ResponseDetails {
+  model?: string;
+  usage?: Usage;
+  reasoning?: string;
+  threadId?: string;
+}
+
+// ============================================================================
+// Thread Types
+// ============================================================================
+
+export interface ThreadMessage {
+  role: 'system' | 'user' | 'assistant';
+  content: string;
+}
+
+// A conversation that can be followed up, starting with its system prompt
+export interface Thread {
+  id: string;
+  operationType: OperationType | string;
+  messages: ThreadMessage[];
   createdAt: string;
+  updatedAt: string;
 }
 
 export interface HistoryEntry {
@@ -199,9 +369,44 @@ export interface HistoryEntry {
   responseText?: string;
   operationOptions: Record<string, string>;
   mediaPath?: string;
+  model?: string;
+  usage?: Usage;
+  reasoning?: string;
+  threadId?: string;
   createdAt: string;
 }
 
+// ============================================================================
+// Usage Types
+// ============================================================================
+
+export interface UsageTotals {
+  requests: number;
+  promptTokens: number;
+  completionTokens: number;
+  totalTokens: number;
+  cost: number; // Cost of the priced requests
+  unpricedRequests: number; // Requests whose model had no price
+}
+
+export interface UsageGroup {
+  name: string; // Model name, or operation type / custom task name
+  totals: UsageTotals;
+}
+
+export interface UsagePeriod {
+  period: string; // "2026-10-17" or "2026-10"
+  totals: UsageTotals;
+  byModel: UsageGroup[];
+  byOperation: UsageGroup[];
+}
+
+export interface UsageSummary {
+  currency: string;
+  daily: UsagePeriod[]; // Newest first
+  monthly: UsagePeriod[];
+}
+
 // ============================================================================
 // Custom Task Types
 // ============================================================================
@@ -218,16 +423,35 @@ export interface CustomTaskOption {
   step?: number;
 }
 
+// JSON Schema of a custom task's answer and an optional template for pasting it
+export interface JsonOutput {
+  schema: Record<string, unknown>;
+  template?: string; // {field} placeholders, dots for nested fields ({sender.name})
+}
+
+// Built-in local tools a custom task can let the model call
+export type ToolName = 'date_time' | 'calculator' | 'convert_units' | 'read_file' | 'search_history';
+
+export const AVAILABLE_TOOLS: ToolName[] = [
+  'date_time', 'calculator', 'convert_units', 'read_file', 'search_history',
+];
+
 export interface CustomTask {
   id: string;
   name: string;
   description: string;
   systemPrompt: string;
   options: CustomTaskOption[];
+  params: GenerationParams;
+  jsonOutput?: JsonOutput; // structured output: the answer is validated JSON
+  tools?: ToolName[];
   createdAt: string;
   updatedAt: string;
 }
 
+// Custom task as submitted to create_custom_task / update_custom_task
+export type CustomTaskInput = Omit<CustomTask, 'id' | 'createdAt' | 'updatedAt'>;
+
 // ============================================================================
 // Streaming Types
 // ============================================================================
@@ -239,6 +463,58 @@ export interface StreamingState {
 }
 
 export interface StreamingChunk {
+  requestId: string;
   content: string;
   done: boolean;
 }
+
+export interface ReasoningChunk {
+  requestId: string;
+  content: string;
+}
+
+export interface StreamCancelled {
+  requestId: string;
+}
+
+export interface RetryEvent {
+  requestId: string;
+  attempt: number; // 1-based number of the upcoming retry
+  maxRetries: number;
+  delayMs: number;
+  error: string;
+}
+
+export interface ToolCallEvent {
+  requestId: string;
+  name: ToolName;
+  arguments: Record<string, unknown>;
+  round: number; // 1-based round of tool calls
+}
+
+export type ChunkStage = 'map' | 'reduce' | 'transcribe';
+
+export interface ChunkProgress {
+  requestId: string;
+  current: number; // 1-based number of the chunk being processed
+  total: number;
+  stage: ChunkStage;
+}
+
+export type TokenEncoding = 'cl100k_base' | 'o200k_base';
+
+export interface TokenCount {
+  tokens: number;
+  model: string;
+  encoding: TokenEncoding;
+  contextWindow: number;
+  maxInputTokens: number; // Prompt budget once the response is reserved
+  supportsChunking: boolean; // Longer prompts are split into chunks instead of rejected
+}
+
+export interface OllamaPullProgress {
+  model: string;
+  status: string;
+  completed?: number;
+  total?: number;
+}